@@ -0,0 +1,183 @@
+use serde::{Deserialize, Serialize};
+
+use crate::srt_utils::{parse_srt, split_speaker, SubtitleCue};
+
+/// Colours assigned to speakers in order of first appearance
+const SPEAKER_COLORS: [&str; 6] = ["#FFFFFF", "#FFE066", "#7FDBFF", "#FF9F80", "#A3F7A3", "#D9A3FF"];
+
+/// Default style used for every Dialogue line (and as the base for speaker styles)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AssStyle {
+    pub font_name: String,
+    pub font_size: u32,
+    /// Text colour as `#RRGGBB`
+    pub primary_color: String,
+    pub margin_l: u32,
+    pub margin_r: u32,
+    pub margin_v: u32,
+}
+
+impl Default for AssStyle {
+    fn default() -> Self {
+        Self {
+            font_name: "Noto Sans JP".to_string(),
+            font_size: 48,
+            primary_color: "#FFFFFF".to_string(),
+            margin_l: 20,
+            margin_r: 20,
+            margin_v: 40,
+        }
+    }
+}
+
+/// Formats milliseconds as an ASS timestamp (`h:mm:ss.cc`)
+pub fn format_ass_timestamp(ms: u64) -> String {
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        ms / 3_600_000,
+        (ms % 3_600_000) / 60_000,
+        (ms % 60_000) / 1000,
+        (ms % 1000) / 10
+    )
+}
+
+/// Converts `#RRGGBB` into the ASS `&HAABBGGRR` colour notation
+fn to_ass_color(color: &str) -> String {
+    let hex = color.trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return "&H00FFFFFF".to_string();
+    }
+    format!("&H00{}{}{}", &hex[4..6], &hex[2..4], &hex[0..2]).to_uppercase()
+}
+
+/// Escapes override-block braces and turns line breaks into `\N`
+fn escape_ass_text(text: &str) -> String {
+    text.replace('{', "\\{")
+        .replace('}', "\\}")
+        .replace("\r\n", "\\N")
+        .replace('\n', "\\N")
+}
+
+/// Style and field names cannot contain commas in ASS
+fn sanitize_field(value: &str) -> String {
+    value.replace([',', '，'], "_")
+}
+
+fn style_line(name: &str, style: &AssStyle, color: &str) -> String {
+    format!(
+        "Style: {},{},{},{},&H000000FF,&H00000000,&H80000000,0,0,0,0,100,100,0,0,1,2,1,2,{},{},{},1",
+        name,
+        sanitize_field(&style.font_name),
+        style.font_size,
+        to_ass_color(color),
+        style.margin_l,
+        style.margin_r,
+        style.margin_v
+    )
+}
+
+/// Builds an ASS script from cues.
+///
+/// With `speaker_styles`, every distinct speaker label gets its own style
+/// with a different colour and the label is moved into the Name field.
+pub fn cues_to_ass(cues: &[SubtitleCue], style: &AssStyle, speaker_styles: bool) -> String {
+    let mut speakers: Vec<String> = Vec::new();
+    let mut events = Vec::with_capacity(cues.len());
+
+    for cue in cues {
+        let mut style_name = "Default".to_string();
+        let mut name = String::new();
+        let mut lines = cue.lines.clone();
+
+        if speaker_styles {
+            if let Some((speaker, text)) = lines.first().and_then(|line| split_speaker(line)) {
+                let speaker = sanitize_field(speaker);
+                let text = text.to_string();
+                if !speakers.contains(&speaker) {
+                    speakers.push(speaker.clone());
+                }
+                style_name = speaker.clone();
+                name = speaker;
+                lines[0] = text;
+            }
+        }
+
+        events.push(format!(
+            "Dialogue: 0,{},{},{},{},0,0,0,,{}",
+            format_ass_timestamp(cue.start_ms),
+            format_ass_timestamp(cue.end_ms),
+            style_name,
+            name,
+            escape_ass_text(&lines.join("\n"))
+        ));
+    }
+
+    let mut styles = vec![style_line("Default", style, &style.primary_color)];
+    for (i, speaker) in speakers.iter().enumerate() {
+        styles.push(style_line(speaker, style, SPEAKER_COLORS[i % SPEAKER_COLORS.len()]));
+    }
+
+    format!(
+        "[Script Info]\n\
+         ScriptType: v4.00+\n\
+         PlayResX: 1920\n\
+         PlayResY: 1080\n\
+         WrapStyle: 0\n\
+         ScaledBorderAndShadow: yes\n\
+         \n\
+         [V4+ Styles]\n\
+         Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n\
+         {}\n\
+         \n\
+         [Events]\n\
+         Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+         {}\n",
+        styles.join("\n"),
+        events.join("\n")
+    )
+}
+
+/// Parses SRT content and converts it into an ASS script
+pub fn srt_to_ass(content: &str, style: &AssStyle, speaker_styles: bool) -> Result<String, String> {
+    let cues = parse_srt(content)?;
+    Ok(cues_to_ass(&cues, style, speaker_styles))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_ass_timestamp() {
+        assert_eq!(format_ass_timestamp(0), "0:00:00.00");
+        assert_eq!(format_ass_timestamp(5_520), "0:00:05.52");
+        assert_eq!(format_ass_timestamp(3_723_459), "1:02:03.45");
+    }
+
+    #[test]
+    fn test_to_ass_color() {
+        assert_eq!(to_ass_color("#FF8000"), "&H000080FF");
+        assert_eq!(to_ass_color("invalid"), "&H00FFFFFF");
+    }
+
+    #[test]
+    fn test_srt_to_ass_dialogue_lines() {
+        let input = "1\n00:00:05,520 --> 00:00:08,910\nこれは{強調}\n二行目\n";
+        let ass = srt_to_ass(input, &AssStyle::default(), false).unwrap();
+        assert!(ass.starts_with("[Script Info]\n"));
+        assert!(ass.contains("Style: Default,Noto Sans JP,48,&H00FFFFFF,"));
+        assert!(ass.contains("Dialogue: 0,0:00:05.52,0:00:08.91,Default,,0,0,0,,これは\\{強調\\}\\N二行目\n"));
+    }
+
+    #[test]
+    fn test_srt_to_ass_speaker_styles() {
+        let input = "1\n00:00:00,000 --> 00:00:01,000\nアオイ: こんにちは\n\n2\n00:00:01,000 --> 00:00:02,000\nユーザー: どうも\n\n3\n00:00:02,000 --> 00:00:03,000\nアオイ: はい\n";
+        let ass = srt_to_ass(input, &AssStyle::default(), true).unwrap();
+        assert!(ass.contains("Style: アオイ,Noto Sans JP,48,&H00FFFFFF,"));
+        assert!(ass.contains("Style: ユーザー,Noto Sans JP,48,&H0066E0FF,"));
+        assert!(ass.contains("Dialogue: 0,0:00:00.00,0:00:01.00,アオイ,アオイ,0,0,0,,こんにちは"));
+        assert!(ass.contains("Dialogue: 0,0:00:01.00,0:00:02.00,ユーザー,ユーザー,0,0,0,,どうも"));
+        assert_eq!(ass.matches("Style: アオイ").count(), 1);
+    }
+}
@@ -0,0 +1,81 @@
+use std::path::Path;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// Reads the duration of an audio or video file from its container headers,
+/// without decoding it. Fails for formats symphonia cannot read and for
+/// streams that do not record their length, such as MP3 without a Xing header.
+pub fn probe_duration_ms(path: &Path) -> Result<u32, String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("Failed to open audio file: {}", e))?;
+    let source = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(&hint, source, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|e| format!("Unsupported audio format: {}", e))?;
+    let track = probed
+        .format
+        .default_track()
+        .ok_or_else(|| "No audio track found".to_string())?;
+    let params = &track.codec_params;
+    let frames = params.n_frames.ok_or_else(|| "The file does not record its length".to_string())?;
+
+    let duration_ms = match (params.time_base, params.sample_rate) {
+        (Some(time_base), _) => {
+            let time = time_base.calc_time(frames);
+            time.seconds * 1000 + (time.frac * 1000.0).round() as u64
+        }
+        (None, Some(sample_rate)) if sample_rate > 0 => frames * 1000 / u64::from(sample_rate),
+        _ => return Err("The file does not record its sample rate".to_string()),
+    };
+    u32::try_from(duration_ms).map_err(|_| "The audio is too long".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 16-bit mono PCM WAV of `samples` silent samples
+    fn wav(sample_rate: u32, samples: u32) -> Vec<u8> {
+        let data_len = samples * 2;
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&sample_rate.to_le_bytes());
+        wav.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        wav.resize(wav.len() + data_len as usize, 0);
+        wav
+    }
+
+    #[test]
+    fn test_probe_wav_duration() {
+        let path = std::env::temp_dir().join(format!("probe_{}.wav", uuid::Uuid::new_v4()));
+        std::fs::write(&path, wav(16_000, 24_000)).unwrap();
+        let duration = probe_duration_ms(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(duration, Ok(1500));
+    }
+
+    #[test]
+    fn test_probe_rejects_unknown_formats() {
+        let path = std::env::temp_dir().join(format!("probe_{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "not audio").unwrap();
+        let duration = probe_duration_ms(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(duration.is_err());
+        assert!(probe_duration_ms(Path::new("/nonexistent/audio.wav")).is_err());
+    }
+}
@@ -0,0 +1,438 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::srt_utils::{parse_srt, serialize_srt};
+
+/// A dictionary row: `表記,ふりがな[,よくある誤表記]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DictionaryTerm {
+    pub term: String,
+    pub reading: String,
+    /// Spellings to replace with `term`; several are separated by `;`, `/` or `、`
+    pub misspellings: Vec<String>,
+}
+
+/// How often a term's misspellings were replaced
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TermReplacement {
+    pub term: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LocalDictionaryResult {
+    pub srt: String,
+    pub replacements: Vec<TermReplacement>,
+}
+
+/// Splits a CSV line into fields, honouring double-quoted fields
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields.into_iter().map(|field| field.trim().to_string()).collect()
+}
+
+/// Parses the dictionary CSV produced by `create_dictionary`, skipping blank
+/// lines and a `表記,ふりがな` header row
+pub fn parse_dictionary_csv(csv: &str) -> Vec<DictionaryTerm> {
+    csv.lines()
+        .map(split_csv_line)
+        .filter(|fields| !fields[0].is_empty() && fields[0] != "表記")
+        .map(|fields| DictionaryTerm {
+            term: fields[0].clone(),
+            reading: fields.get(1).cloned().unwrap_or_default(),
+            misspellings: fields
+                .get(2)
+                .map(|column| {
+                    column
+                        .split([';', '/', '、'])
+                        .map(str::trim)
+                        .filter(|spelling| !spelling.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// A validated `表記,ふりがな` row of a generated dictionary
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DictEntry {
+    pub term: String,
+    pub reading: String,
+}
+
+/// Parses a generated dictionary, which must have exactly two columns.
+///
+/// Fields are trimmed, blank rows, a `表記,ふりがな` header and stray code
+/// fence lines are skipped, and later duplicates of a term (compared
+/// case-insensitively) are dropped.
+pub fn validate_dictionary_csv(csv: &str) -> Result<Vec<DictEntry>, String> {
+    let content = csv
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes());
+
+    let mut seen = HashSet::new();
+    let mut entries = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Invalid dictionary CSV: {}", e))?;
+        let line = record.position().map_or(0, |position| position.line());
+        if record.iter().all(str::is_empty) || record.get(0) == Some("表記") {
+            continue;
+        }
+        if record.len() != 2 {
+            return Err(format!(
+                "Dictionary line {} has {} columns, expected 2 (表記,ふりがな)",
+                line,
+                record.len()
+            ));
+        }
+        if record[0].is_empty() {
+            return Err(format!("Dictionary line {} has an empty 表記", line));
+        }
+        if seen.insert(record[0].to_lowercase()) {
+            entries.push(DictEntry {
+                term: record[0].to_string(),
+                reading: record[1].to_string(),
+            });
+        }
+    }
+
+    if entries.is_empty() {
+        return Err("Dictionary is empty".to_string());
+    }
+    Ok(entries)
+}
+
+/// `analyze_topic` output, generated as JSON matching [`TopicAnalysis::schema`]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TopicAnalysis {
+    pub keywords: Vec<String>,
+    pub topic_summary: String,
+}
+
+impl TopicAnalysis {
+    pub fn schema() -> serde_json::Value {
+        serde_json::json!({
+            "type": "OBJECT",
+            "properties": {
+                "keywords": { "type": "ARRAY", "items": { "type": "STRING" } },
+                "topic_summary": { "type": "STRING" }
+            },
+            "required": ["keywords", "topic_summary"],
+            "propertyOrdering": ["keywords", "topic_summary"]
+        })
+    }
+
+    /// The `メイントピック:` / `キーワード:` lines `analyze_topic` returns
+    pub fn to_text(&self) -> String {
+        format!("メイントピック: {}\nキーワード: {}", self.topic_summary.trim(), self.keywords.join(", "))
+    }
+}
+
+/// A dictionary row generated as JSON matching [`GeneratedTerm::list_schema`]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct GeneratedTerm {
+    pub surface: String,
+    pub reading: String,
+}
+
+impl GeneratedTerm {
+    /// Schema of the array of terms
+    pub fn list_schema() -> serde_json::Value {
+        serde_json::json!({
+            "type": "ARRAY",
+            "items": {
+                "type": "OBJECT",
+                "properties": {
+                    "surface": { "type": "STRING" },
+                    "reading": { "type": "STRING" }
+                },
+                "required": ["surface", "reading"],
+                "propertyOrdering": ["surface", "reading"]
+            }
+        })
+    }
+}
+
+/// Applies the checks of [`validate_dictionary_csv`] to generated terms:
+/// fields are trimmed, blank terms skipped and later duplicates dropped
+pub fn validate_generated_terms(terms: Vec<GeneratedTerm>) -> Result<Vec<DictEntry>, String> {
+    let mut seen = HashSet::new();
+    let entries: Vec<DictEntry> = terms
+        .into_iter()
+        .filter(|term| !term.surface.trim().is_empty())
+        .filter(|term| seen.insert(term.surface.trim().to_lowercase()))
+        .map(|term| DictEntry {
+            term: term.surface.trim().to_string(),
+            reading: term.reading.trim().to_string(),
+        })
+        .collect();
+
+    if entries.is_empty() {
+        return Err("Dictionary is empty".to_string());
+    }
+    Ok(entries)
+}
+
+/// Writes entries back as `表記,ふりがな` CSV without a header row
+pub fn dictionary_to_csv(entries: &[DictEntry]) -> Result<String, String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for entry in entries {
+        writer
+            .write_record([&entry.term, &entry.reading])
+            .map_err(|e| format!("Failed to write dictionary CSV: {}", e))?;
+    }
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| format!("Failed to write dictionary CSV: {}", e))?;
+    String::from_utf8(bytes).map_err(|e| format!("Failed to write dictionary CSV: {}", e))
+}
+
+/// Reading used to compare and sort entries: katakana folded to hiragana,
+/// whitespace removed
+fn reading_key(reading: &str) -> String {
+    reading
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c {
+            'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+/// Unions dictionaries given as `(source name, entries)`, matching terms
+/// case-insensitively. A non-empty reading wins over an empty one; two
+/// different readings for the same term are an error naming both sources.
+/// The result is sorted by reading, or by term when there is none.
+pub fn merge_dictionary_entries(sources: &[(String, Vec<DictEntry>)]) -> Result<Vec<DictEntry>, String> {
+    let mut merged: Vec<(DictEntry, &str)> = Vec::new();
+    for (source, entries) in sources {
+        for entry in entries {
+            let existing = merged
+                .iter_mut()
+                .find(|(known, _)| known.term.to_lowercase() == entry.term.to_lowercase());
+            match existing {
+                None => merged.push((entry.clone(), source)),
+                Some(_) if entry.reading.is_empty() => {}
+                Some((known, known_source)) if known.reading.is_empty() => {
+                    known.reading = entry.reading.clone();
+                    *known_source = source;
+                }
+                Some((known, known_source)) => {
+                    if reading_key(&known.reading) != reading_key(&entry.reading) {
+                        return Err(format!(
+                            "Conflicting readings for {}: {} ({}) and {} ({})",
+                            known.term, known.reading, known_source, entry.reading, source
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut merged: Vec<DictEntry> = merged.into_iter().map(|(entry, _)| entry).collect();
+    merged.sort_by_cached_key(|entry| {
+        let key = reading_key(&entry.reading);
+        if key.is_empty() { entry.term.to_lowercase() } else { key }
+    });
+    Ok(merged)
+}
+
+/// A replacement rule; `term` is `None` for the identity rules that keep
+/// correct spellings from being rewritten by a shorter misspelling
+struct Rule<'a> {
+    pattern: &'a str,
+    replacement: &'a str,
+    term: Option<usize>,
+}
+
+/// Replaces misspellings with their dictionary terms in cue text only,
+/// preferring the longest match at each position.
+///
+/// Readings are not used as patterns: they are ordinary kana words as often
+/// as not, and replacing them would rewrite unrelated text.
+pub fn apply_dictionary(srt: &str, terms: &[DictionaryTerm]) -> Result<LocalDictionaryResult, String> {
+    let mut rules: Vec<Rule> = Vec::new();
+    for (i, term) in terms.iter().enumerate() {
+        rules.push(Rule {
+            pattern: &term.term,
+            replacement: &term.term,
+            term: None,
+        });
+        for misspelling in term.misspellings.iter().filter(|spelling| **spelling != term.term) {
+            rules.push(Rule {
+                pattern: misspelling,
+                replacement: &term.term,
+                term: Some(i),
+            });
+        }
+    }
+    rules.sort_by_key(|rule| std::cmp::Reverse(rule.pattern.len()));
+
+    let mut counts = vec![0; terms.len()];
+    let mut cues = parse_srt(srt)?;
+    for cue in &mut cues {
+        for line in &mut cue.lines {
+            let mut replaced = String::with_capacity(line.len());
+            let mut rest = line.as_str();
+            while let Some(c) = rest.chars().next() {
+                match rules.iter().find(|rule| rest.starts_with(rule.pattern)) {
+                    Some(rule) => {
+                        replaced.push_str(rule.replacement);
+                        rest = &rest[rule.pattern.len()..];
+                        if let Some(i) = rule.term {
+                            counts[i] += 1;
+                        }
+                    }
+                    None => {
+                        replaced.push(c);
+                        rest = &rest[c.len_utf8()..];
+                    }
+                }
+            }
+            *line = replaced;
+        }
+    }
+
+    let replacements = terms
+        .iter()
+        .zip(counts)
+        .filter(|(term, _)| !term.misspellings.is_empty())
+        .map(|(term, count)| TermReplacement {
+            term: term.term.clone(),
+            count,
+        })
+        .collect();
+
+    Ok(LocalDictionaryResult {
+        srt: serialize_srt(&cues),
+        replacements,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dictionary_csv() {
+        let csv = "表記,ふりがな\nTauri,たうり,タウリ;トーリ\n\n\"Gemini, Pro\",じぇみにぷろ\n";
+        let terms = parse_dictionary_csv(csv);
+        assert_eq!(terms.len(), 2);
+        assert_eq!(terms[0].misspellings, vec!["タウリ", "トーリ"]);
+        assert_eq!(terms[1].term, "Gemini, Pro");
+        assert!(terms[1].misspellings.is_empty());
+    }
+
+    /// Text of the first candidate of a generateContent response body
+    fn response_text(body: &str) -> String {
+        let response: crate::gemini::GenerateContentResponse = serde_json::from_str(body).unwrap();
+        match &response.candidates[0].content.parts[0] {
+            crate::gemini::Part::Text { text } => text.clone(),
+            part => panic!("unexpected part {:?}", part),
+        }
+    }
+
+    #[test]
+    fn test_parse_structured_topic_analysis() {
+        let body = r#"{"candidates": [{"content": {"parts": [{"text": "{\"keywords\": [\"Tauri\", \"Rust\", \"Gemini API\"], \"topic_summary\": \"Tauriでのデスクトップアプリ開発\"}"}], "role": "model"}, "finishReason": "STOP", "index": 0}], "usageMetadata": {"promptTokenCount": 812, "candidatesTokenCount": 31, "totalTokenCount": 843}, "modelVersion": "gemini-2.0-flash"}"#;
+        let analysis: TopicAnalysis = serde_json::from_str(&response_text(body)).unwrap();
+        assert_eq!(analysis.keywords, vec!["Tauri", "Rust", "Gemini API"]);
+        assert_eq!(analysis.to_text(), "メイントピック: Tauriでのデスクトップアプリ開発\nキーワード: Tauri, Rust, Gemini API");
+    }
+
+    #[test]
+    fn test_parse_structured_dictionary() {
+        let body = r#"{"candidates": [{"content": {"parts": [{"text": "[{\"surface\": \"Tauri\", \"reading\": \"たうり\"}, {\"surface\": \" 生成AI \", \"reading\": \"せいせいえーあい\"}, {\"surface\": \"tauri\", \"reading\": \"とーり\"}, {\"surface\": \"\", \"reading\": \"\"}]"}], "role": "model"}, "finishReason": "STOP", "index": 0}], "usageMetadata": {"promptTokenCount": 402, "candidatesTokenCount": 58, "totalTokenCount": 460}}"#;
+        let terms: Vec<GeneratedTerm> = serde_json::from_str(&response_text(body)).unwrap();
+        let entries = validate_generated_terms(terms).unwrap();
+        assert_eq!(dictionary_to_csv(&entries).unwrap(), "Tauri,たうり\n生成AI,せいせいえーあい\n");
+        assert!(validate_generated_terms(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_validate_dictionary_csv() {
+        let csv = "```csv\n表記,ふりがな\n Tauri , たうり \n\ntauri,とーり\n\"Gemini, Pro\",じぇみにぷろ\n```\n";
+        let entries = validate_dictionary_csv(csv).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                DictEntry { term: "Tauri".to_string(), reading: "たうり".to_string() },
+                DictEntry { term: "Gemini, Pro".to_string(), reading: "じぇみにぷろ".to_string() },
+            ]
+        );
+        assert_eq!(dictionary_to_csv(&entries).unwrap(), "Tauri,たうり\n\"Gemini, Pro\",じぇみにぷろ\n");
+
+        let error = validate_dictionary_csv("Tauri,たうり\nRust,らすと,extra\n").unwrap_err();
+        assert!(error.contains("line 2 has 3 columns"), "{}", error);
+        assert!(validate_dictionary_csv("表記,ふりがな\n").is_err());
+    }
+
+    fn entry(term: &str, reading: &str) -> DictEntry {
+        DictEntry { term: term.to_string(), reading: reading.to_string() }
+    }
+
+    #[test]
+    fn test_merge_dictionary_entries() {
+        let sources = vec![
+            ("a.csv".to_string(), vec![entry("Tauri", ""), entry("Rust", "らすと")]),
+            ("b.csv".to_string(), vec![entry("tauri", "たうり"), entry("RUST", "ラスト"), entry("Gemini", "じぇみに")]),
+        ];
+        assert_eq!(
+            merge_dictionary_entries(&sources).unwrap(),
+            vec![entry("Gemini", "じぇみに"), entry("Tauri", "たうり"), entry("Rust", "らすと")]
+        );
+
+        let conflicting = vec![
+            ("a.csv".to_string(), vec![entry("Rust", "らすと")]),
+            ("b.csv".to_string(), vec![entry("Rust", "さび")]),
+        ];
+        let error = merge_dictionary_entries(&conflicting).unwrap_err();
+        assert!(error.contains("らすと (a.csv) and さび (b.csv)"), "{}", error);
+    }
+
+    #[test]
+    fn test_apply_dictionary_longest_match_and_text_only() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nタウリとタウリアプリ\n\n2\n00:00:03,000 --> 00:00:04,000\nTauri Appはトーリで動く\n";
+        let terms = parse_dictionary_csv("Tauri,たうり,タウリ;トーリ\nTauri App,たうりあぷり,タウリアプリ\n00,ぜろぜろ,01\n");
+        let result = apply_dictionary(srt, &terms).unwrap();
+
+        assert_eq!(
+            result.srt,
+            "1\n00:00:01,000 --> 00:00:02,000\nTauriとTauri App\n\n2\n00:00:03,000 --> 00:00:04,000\nTauri AppはTauriで動く\n"
+        );
+        assert_eq!(
+            result.replacements,
+            vec![
+                TermReplacement { term: "Tauri".to_string(), count: 2 },
+                TermReplacement { term: "Tauri App".to_string(), count: 1 },
+                TermReplacement { term: "00".to_string(), count: 0 },
+            ]
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::srt_utils::{join_text, parse_srt, SubtitleCue};
+
+/// Timing differences up to this are not reported by default
+pub const DEFAULT_TIMING_THRESHOLD_MS: u64 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffKind {
+    Equal,
+    Added,
+    Removed,
+}
+
+/// A run of characters that is unchanged, only in the new text or only in the old text
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiffSegment {
+    pub kind: DiffKind,
+    pub text: String,
+}
+
+/// Cues from both sides covering the same stretch of time. Re-segmented
+/// output makes these many-to-many, so texts are compared as a whole.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MatchedCues {
+    pub before_indices: Vec<u32>,
+    pub after_indices: Vec<u32>,
+    pub before_text: String,
+    pub after_text: String,
+    pub changed: bool,
+    pub segments: Vec<DiffSegment>,
+}
+
+/// Start or end of a matched group moved by more than the threshold
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimingChange {
+    pub before_indices: Vec<u32>,
+    pub after_indices: Vec<u32>,
+    pub start_delta_ms: i64,
+    pub end_delta_ms: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TranscriptionDiff {
+    pub matched: Vec<MatchedCues>,
+    pub only_before: Vec<SubtitleCue>,
+    pub only_after: Vec<SubtitleCue>,
+    pub timing_changes: Vec<TimingChange>,
+}
+
+/// Text changes of the cues with the same sequence number on both sides
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiffHunk {
+    pub index: u32,
+    /// `None` when the cue only exists on the other side
+    pub before_text: Option<String>,
+    pub after_text: Option<String>,
+    pub changed: bool,
+    pub segments: Vec<DiffSegment>,
+}
+
+/// Groups cues whose time ranges overlap, directly or through a chain of
+/// overlapping cues, returning each group's cues from both sides
+fn align_cues<'a>(before: &'a [SubtitleCue], after: &'a [SubtitleCue]) -> Vec<(Vec<&'a SubtitleCue>, Vec<&'a SubtitleCue>)> {
+    let mut before: Vec<&SubtitleCue> = before.iter().collect();
+    let mut after: Vec<&SubtitleCue> = after.iter().collect();
+    before.sort_by_key(|cue| cue.start_ms);
+    after.sort_by_key(|cue| cue.start_ms);
+
+    let (mut i, mut j) = (0, 0);
+    let mut groups = Vec::new();
+    loop {
+        let (mut group_before, mut group_after) = (Vec::new(), Vec::new());
+        let first_is_before = match (before.get(i), after.get(j)) {
+            (Some(b), Some(a)) => b.start_ms <= a.start_ms,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => break,
+        };
+        let mut group_end = if first_is_before {
+            group_before.push(before[i]);
+            i += 1;
+            before[i - 1].end_ms
+        } else {
+            group_after.push(after[j]);
+            j += 1;
+            after[j - 1].end_ms
+        };
+
+        loop {
+            if let Some(cue) = before.get(i).filter(|cue| cue.start_ms < group_end) {
+                group_end = group_end.max(cue.end_ms);
+                group_before.push(*cue);
+                i += 1;
+            } else if let Some(cue) = after.get(j).filter(|cue| cue.start_ms < group_end) {
+                group_end = group_end.max(cue.end_ms);
+                group_after.push(*cue);
+                j += 1;
+            } else {
+                break;
+            }
+        }
+        groups.push((group_before, group_after));
+    }
+    groups
+}
+
+fn push_segment(segments: &mut Vec<DiffSegment>, kind: DiffKind, c: char) {
+    match segments.last_mut() {
+        Some(segment) if segment.kind == kind => segment.text.push(c),
+        _ => segments.push(DiffSegment { kind, text: c.to_string() }),
+    }
+}
+
+/// Character-level diff based on the longest common subsequence
+pub fn diff_text(before: &str, after: &str) -> Vec<DiffSegment> {
+    let a: Vec<char> = before.chars().collect();
+    let b: Vec<char> = after.chars().collect();
+
+    // lcs[i][j] is the LCS length of a[i..] and b[j..]
+    let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut segments = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            push_segment(&mut segments, DiffKind::Equal, a[i]);
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            push_segment(&mut segments, DiffKind::Removed, a[i]);
+            i += 1;
+        } else {
+            push_segment(&mut segments, DiffKind::Added, b[j]);
+            j += 1;
+        }
+    }
+    segments
+}
+
+fn group_text(cues: &[&SubtitleCue]) -> String {
+    cues.iter()
+        .flat_map(|cue| cue.lines.iter())
+        .fold(String::new(), |text, line| join_text(&text, line))
+}
+
+/// Compares two transcriptions of the same audio, aligning cues by time
+pub fn diff_srt(before_srt: &str, after_srt: &str, timing_threshold_ms: u64) -> Result<TranscriptionDiff, String> {
+    let before = parse_srt(before_srt)?;
+    let after = parse_srt(after_srt)?;
+
+    let mut diff = TranscriptionDiff {
+        matched: Vec::new(),
+        only_before: Vec::new(),
+        only_after: Vec::new(),
+        timing_changes: Vec::new(),
+    };
+
+    for (group_before, group_after) in align_cues(&before, &after) {
+        if group_after.is_empty() {
+            diff.only_before.extend(group_before.into_iter().cloned());
+            continue;
+        }
+        if group_before.is_empty() {
+            diff.only_after.extend(group_after.into_iter().cloned());
+            continue;
+        }
+
+        let before_indices: Vec<u32> = group_before.iter().map(|cue| cue.index).collect();
+        let after_indices: Vec<u32> = group_after.iter().map(|cue| cue.index).collect();
+
+        let span = |cues: &[&SubtitleCue]| {
+            (
+                cues.iter().map(|cue| cue.start_ms).min().unwrap_or(0) as i64,
+                cues.iter().map(|cue| cue.end_ms).max().unwrap_or(0) as i64,
+            )
+        };
+        let (before_start, before_end) = span(&group_before);
+        let (after_start, after_end) = span(&group_after);
+        let start_delta_ms = after_start - before_start;
+        let end_delta_ms = after_end - before_end;
+        if start_delta_ms.unsigned_abs() > timing_threshold_ms || end_delta_ms.unsigned_abs() > timing_threshold_ms {
+            diff.timing_changes.push(TimingChange {
+                before_indices: before_indices.clone(),
+                after_indices: after_indices.clone(),
+                start_delta_ms,
+                end_delta_ms,
+            });
+        }
+
+        let before_text = group_text(&group_before);
+        let after_text = group_text(&group_after);
+        let segments = diff_text(&before_text, &after_text);
+        diff.matched.push(MatchedCues {
+            before_indices,
+            after_indices,
+            changed: segments.iter().any(|segment| segment.kind != DiffKind::Equal),
+            before_text,
+            after_text,
+            segments,
+        });
+    }
+
+    Ok(diff)
+}
+
+/// Compares cue texts matched by sequence number, ignoring timestamps, e.g.
+/// to show what the dictionary changed during enhancement. A cue missing on
+/// one side is entirely added or removed.
+pub fn diff_srt_by_index(before_srt: &str, after_srt: &str) -> Result<Vec<DiffHunk>, String> {
+    let mut texts: BTreeMap<u32, (Option<String>, Option<String>)> = BTreeMap::new();
+    for cue in parse_srt(before_srt)? {
+        texts.entry(cue.index).or_default().0 = Some(group_text(&[&cue]));
+    }
+    for cue in parse_srt(after_srt)? {
+        texts.entry(cue.index).or_default().1 = Some(group_text(&[&cue]));
+    }
+
+    Ok(texts
+        .into_iter()
+        .map(|(index, (before_text, after_text))| {
+            let segments = diff_text(before_text.as_deref().unwrap_or(""), after_text.as_deref().unwrap_or(""));
+            DiffHunk {
+                index,
+                changed: segments.iter().any(|segment| segment.kind != DiffKind::Equal),
+                before_text,
+                after_text,
+                segments,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_text() {
+        assert_eq!(
+            diff_text("ジェミニを使う", "Geminiを使う"),
+            vec![
+                DiffSegment { kind: DiffKind::Removed, text: "ジェミニ".to_string() },
+                DiffSegment { kind: DiffKind::Added, text: "Gemini".to_string() },
+                DiffSegment { kind: DiffKind::Equal, text: "を使う".to_string() },
+            ]
+        );
+        assert!(diff_text("", "").is_empty());
+    }
+
+    #[test]
+    fn test_diff_srt_aligns_resegmented_cues() {
+        let before = "1\n00:00:00,000 --> 00:00:02,000\n今日はいい天気\n\n2\n00:00:02,000 --> 00:00:04,000\nですね\n\n3\n00:00:05,000 --> 00:00:06,000\nタウリで作る\n\n4\n00:00:10,000 --> 00:00:11,000\nえーっと\n";
+        let after = "1\n00:00:00,000 --> 00:00:04,000\n今日はいい天気ですね\n\n2\n00:00:05,000 --> 00:00:07,000\nTauriで作る\n\n3\n00:00:20,000 --> 00:00:21,000\n追加\n";
+        let diff = diff_srt(before, after, DEFAULT_TIMING_THRESHOLD_MS).unwrap();
+
+        assert_eq!(diff.matched.len(), 2);
+        assert_eq!(diff.matched[0].before_indices, vec![1, 2]);
+        assert_eq!(diff.matched[0].after_indices, vec![1]);
+        assert!(!diff.matched[0].changed);
+        assert!(diff.matched[1].changed);
+        assert_eq!(diff.matched[1].segments[0].text, "タウリ");
+
+        assert_eq!(diff.only_before.len(), 1);
+        assert_eq!(diff.only_before[0].index, 4);
+        assert_eq!(diff.only_after.len(), 1);
+        assert_eq!(diff.only_after[0].index, 3);
+
+        assert_eq!(
+            diff.timing_changes,
+            vec![TimingChange {
+                before_indices: vec![3],
+                after_indices: vec![2],
+                start_delta_ms: 0,
+                end_delta_ms: 1000,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_diff_srt_by_index_ignores_timestamps() {
+        let before = "1\n00:00:00,000 --> 00:00:02,000\nジェミニを使う\n\n2\n00:00:02,000 --> 00:00:04,000\nこんにちは\n\n3\n00:00:04,000 --> 00:00:05,000\n以上\n";
+        let after = "1\n00:00:00,500 --> 00:00:02,500\nGeminiを使う\n\n2\n00:00:03,000 --> 00:00:04,000\nこんにちは\n";
+        let hunks = diff_srt_by_index(before, after).unwrap();
+
+        assert_eq!(hunks.len(), 3);
+        assert!(hunks[0].changed);
+        assert_eq!(hunks[0].segments[0], DiffSegment { kind: DiffKind::Removed, text: "ジェミニ".to_string() });
+        assert!(!hunks[1].changed);
+        assert_eq!(hunks[2].after_text, None);
+        assert_eq!(hunks[2].segments, vec![DiffSegment { kind: DiffKind::Removed, text: "以上".to_string() }]);
+    }
+}
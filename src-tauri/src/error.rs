@@ -0,0 +1,437 @@
+use reqwest::StatusCode;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+
+use crate::srt_utils::SrtParseError;
+
+/// Errors commands report besides plain messages. Commands that call Gemini
+/// return this directly so the frontend receives `{code, message, retryable}`;
+/// the others convert it to `String` like [`crate::srt_utils::SrtParseError`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum AppError {
+    /// Another transcription is still running
+    Busy,
+    /// The transcription was stopped with `cancel_transcription`
+    Cancelled,
+    Gemini(GeminiError),
+    /// Any other failure, already worded for the user
+    Message(String),
+}
+
+impl AppError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Busy => "busy",
+            AppError::Cancelled => "cancelled",
+            AppError::Gemini(error) => error.code(),
+            AppError::Message(_) => "error",
+        }
+    }
+
+    pub fn retryable(&self) -> bool {
+        match self {
+            AppError::Busy => true,
+            AppError::Gemini(error) => error.retryable(),
+            AppError::Cancelled | AppError::Message(_) => false,
+        }
+    }
+
+    /// Prefixes the message with what was being done, keeping the error code
+    pub fn context(self, context: &str) -> Self {
+        match self {
+            AppError::Gemini(error) => AppError::Gemini(error.context(context)),
+            AppError::Message(message) => AppError::Message(format!("{}: {}", context, message)),
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Busy => write!(f, "A transcription is already running. Please wait for it to finish."),
+            AppError::Cancelled => write!(f, "The transcription was cancelled."),
+            AppError::Gemini(error) => write!(f, "{}", error),
+            AppError::Message(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("retryable", &self.retryable())?;
+        state.end()
+    }
+}
+
+impl From<AppError> for String {
+    fn from(error: AppError) -> Self {
+        error.to_string()
+    }
+}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        AppError::Message(message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        AppError::Message(message.to_string())
+    }
+}
+
+impl From<SrtParseError> for AppError {
+    fn from(error: SrtParseError) -> Self {
+        AppError::Message(error.to_string())
+    }
+}
+
+impl From<GeminiError> for AppError {
+    fn from(error: GeminiError) -> Self {
+        match error {
+            GeminiError::Cancelled => AppError::Cancelled,
+            error => AppError::Gemini(error),
+        }
+    }
+}
+
+/// Failures of [`crate::gemini::GeminiClient`] requests, classified from the
+/// HTTP status and the error payload Google returns
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeminiError {
+    InvalidApiKey,
+    /// Rate limit or quota hit; `retry_after` is in seconds when the API says
+    QuotaExceeded { retry_after: Option<u64> },
+    FileTooLarge,
+    UnsupportedMime(String),
+    /// Any other non-success response; `retry_after` is the `Retry-After`
+    /// header in seconds, if sent
+    Http { status: u16, body: String, retry_after: Option<u64> },
+    Network(String),
+    /// The response (or a local file) could not be read as expected
+    Parse(String),
+    Timeout,
+    /// Generation stopped by a safety or recitation filter
+    /// `category` is the harm category that triggered the block, when known
+    Safety { reason: String, category: Option<String>, ratings: Vec<String> },
+    /// The Files API reported the upload as failed
+    FileProcessingFailed,
+    Io(String),
+    /// Stopped through the cancellation token
+    Cancelled,
+    /// The operation is not available with the configured endpoint
+    Unsupported(String),
+    /// Another error with a description of the failed step
+    Context { context: String, source: Box<GeminiError> },
+}
+
+/// `error` member of a Google API error response
+#[derive(Debug, Deserialize)]
+struct GoogleErrorBody {
+    error: GoogleError,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoogleError {
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    details: Vec<GoogleErrorDetail>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoogleErrorDetail {
+    #[serde(default)]
+    reason: Option<String>,
+    /// Set on `google.rpc.RetryInfo` details, e.g. `"37s"`
+    #[serde(rename = "retryDelay", default)]
+    retry_delay: Option<String>,
+}
+
+impl GeminiError {
+    /// Classifies a non-success response. `retry_after` is the value of the
+    /// `Retry-After` header, which takes precedence over the payload's
+    /// `RetryInfo`.
+    pub fn from_response(status: StatusCode, retry_after: Option<&str>, body: &str) -> Self {
+        let google = serde_json::from_str::<GoogleErrorBody>(body).ok().map(|body| body.error);
+        let retry_after_secs = retry_after.and_then(|value| value.trim().parse().ok());
+        let message = google.as_ref().map_or(body, |error| error.message.as_str());
+        let google_status = google.as_ref().map_or("", |error| error.status.as_str());
+        let has_reason = |reason: &str| {
+            google
+                .as_ref()
+                .is_some_and(|error| error.details.iter().any(|detail| detail.reason.as_deref() == Some(reason)))
+        };
+
+        if status == StatusCode::UNAUTHORIZED || has_reason("API_KEY_INVALID") || message.contains("API key not valid") {
+            return GeminiError::InvalidApiKey;
+        }
+        if status == StatusCode::TOO_MANY_REQUESTS || google_status == "RESOURCE_EXHAUSTED" {
+            let retry_info = google
+                .as_ref()
+                .and_then(|error| error.details.iter().find_map(|detail| detail.retry_delay.as_deref()));
+            let retry_after = retry_after_secs
+                .or_else(|| retry_info.and_then(|delay| delay.trim_end_matches('s').parse::<f64>().ok()).map(|secs| secs.ceil() as u64));
+            return GeminiError::QuotaExceeded { retry_after };
+        }
+        if status == StatusCode::PAYLOAD_TOO_LARGE {
+            return GeminiError::FileTooLarge;
+        }
+        if status == StatusCode::BAD_REQUEST && message.to_lowercase().contains("mime type") {
+            return GeminiError::UnsupportedMime(message.to_string());
+        }
+        if status == StatusCode::REQUEST_TIMEOUT || status == StatusCode::GATEWAY_TIMEOUT || google_status == "DEADLINE_EXCEEDED" {
+            return GeminiError::Timeout;
+        }
+        GeminiError::Http {
+            status: status.as_u16(),
+            body: message.to_string(),
+            retry_after: retry_after_secs,
+        }
+    }
+
+    /// Wraps the error with a description of the failed step
+    pub fn context(self, context: &str) -> Self {
+        GeminiError::Context {
+            context: context.to_string(),
+            source: Box::new(self),
+        }
+    }
+
+    /// The error without any [`GeminiError::Context`] wrapping
+    pub fn root(&self) -> &GeminiError {
+        match self {
+            GeminiError::Context { source, .. } => source.root(),
+            error => error,
+        }
+    }
+
+    /// Stable identifier the frontend can switch on
+    pub fn code(&self) -> &'static str {
+        match self.root() {
+            GeminiError::InvalidApiKey => "invalid_api_key",
+            GeminiError::QuotaExceeded { .. } => "quota_exceeded",
+            GeminiError::FileTooLarge => "file_too_large",
+            GeminiError::UnsupportedMime(_) => "unsupported_mime",
+            GeminiError::Http { .. } => "http",
+            GeminiError::Network(_) => "network",
+            GeminiError::Parse(_) => "parse",
+            GeminiError::Timeout => "timeout",
+            GeminiError::Safety { .. } => "safety",
+            GeminiError::FileProcessingFailed => "file_processing_failed",
+            GeminiError::Io(_) => "io",
+            GeminiError::Cancelled => "cancelled",
+            GeminiError::Unsupported(_) => "unsupported",
+            GeminiError::Context { .. } => unreachable!("root() unwraps context"),
+        }
+    }
+
+    /// Seconds the API asked to wait before retrying
+    pub fn retry_after(&self) -> Option<u64> {
+        match self.root() {
+            GeminiError::QuotaExceeded { retry_after } | GeminiError::Http { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Whether the same request may succeed when sent again later
+    pub fn retryable(&self) -> bool {
+        match self.root() {
+            GeminiError::QuotaExceeded { .. } | GeminiError::Network(_) | GeminiError::Timeout => true,
+            GeminiError::Http { status, .. } => matches!(status, 500 | 502 | 503 | 504),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for GeminiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeminiError::InvalidApiKey => write!(f, "The Gemini API key is invalid. Please check it in settings."),
+            GeminiError::QuotaExceeded { retry_after: Some(secs) } => {
+                write!(f, "The Gemini API quota was exceeded. Please retry in {} seconds.", secs)
+            }
+            GeminiError::QuotaExceeded { retry_after: None } => write!(f, "The Gemini API quota was exceeded. Please retry later."),
+            GeminiError::FileTooLarge => write!(f, "The file is too large for the Gemini Files API."),
+            GeminiError::UnsupportedMime(message) => write!(f, "Gemini does not accept this file type: {}", message),
+            GeminiError::Http { status, body, .. } => write!(f, "Gemini API request failed ({}): {}", status, body),
+            GeminiError::Network(message) => write!(f, "Could not reach the Gemini API: {}", message),
+            GeminiError::Parse(message) => write!(f, "Unexpected response from the Gemini API: {}", message),
+            GeminiError::Timeout => write!(f, "The Gemini API request timed out."),
+            GeminiError::Safety { reason, ratings, .. } if reason == "RECITATION" => write!(
+                f,
+                "Gemini stopped the response because it closely matched existing material (finishReason: RECITATION). Please try again or use a shorter excerpt.{}",
+                format_ratings(ratings)
+            ),
+            GeminiError::Safety { reason, category: Some(category), ratings } => write!(
+                f,
+                "Gemini blocked the response for safety reasons (finishReason: {}, category: {}). The audio may contain content the model refuses to process; the threshold for this category can be lowered in the safety settings.{}",
+                reason,
+                category,
+                format_ratings(ratings)
+            ),
+            GeminiError::Safety { reason, category: None, ratings } => write!(
+                f,
+                "Gemini blocked the response for safety reasons (finishReason: {}). The audio may contain content the model refuses to process.{}",
+                reason,
+                format_ratings(ratings)
+            ),
+            GeminiError::FileProcessingFailed => write!(f, "Gemini failed to process the uploaded file."),
+            GeminiError::Io(message) => write!(f, "{}", message),
+            GeminiError::Cancelled => write!(f, "{}", AppError::Cancelled),
+            GeminiError::Unsupported(message) => write!(f, "{}", message),
+            GeminiError::Context { context, source } => write!(f, "{}: {}", context, source),
+        }
+    }
+}
+
+fn format_ratings(ratings: &[String]) -> String {
+    if ratings.is_empty() {
+        String::new()
+    } else {
+        format!(" Ratings: {}", ratings.join(", "))
+    }
+}
+
+impl std::error::Error for GeminiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GeminiError::Context { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Removes a `key` query parameter from `url`. Keys are sent in a header,
+/// but URLs handed back by the API (such as upload session URLs) may still
+/// carry one, and reqwest includes the URL in its error messages.
+fn redact_api_key(url: &mut reqwest::Url) {
+    if !url.query_pairs().any(|(name, _)| name == "key") {
+        return;
+    }
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(name, _)| name != "key")
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    if pairs.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+}
+
+impl From<reqwest::Error> for GeminiError {
+    fn from(mut error: reqwest::Error) -> Self {
+        if let Some(url) = error.url_mut() {
+            redact_api_key(url);
+        }
+        if error.is_timeout() {
+            GeminiError::Timeout
+        } else if error.is_decode() {
+            GeminiError::Parse(error.to_string())
+        } else {
+            GeminiError::Network(error.to_string())
+        }
+    }
+}
+
+impl From<serde_json::Error> for GeminiError {
+    fn from(error: serde_json::Error) -> Self {
+        GeminiError::Parse(error.to_string())
+    }
+}
+
+impl From<std::io::Error> for GeminiError {
+    fn from(error: std::io::Error) -> Self {
+        GeminiError::Io(error.to_string())
+    }
+}
+
+impl From<GeminiError> for String {
+    fn from(error: GeminiError) -> Self {
+        error.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_api_key() {
+        let mut url = reqwest::Url::parse("https://example.com/upload?upload_id=1&key=AIzaSecret").unwrap();
+        redact_api_key(&mut url);
+        assert_eq!(url.as_str(), "https://example.com/upload?upload_id=1");
+
+        let mut url = reqwest::Url::parse("https://example.com/v1beta/files/a?key=AIzaSecret").unwrap();
+        redact_api_key(&mut url);
+        assert_eq!(url.as_str(), "https://example.com/v1beta/files/a");
+    }
+
+    #[tokio::test]
+    async fn test_request_errors_do_not_include_the_key() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1beta/files?key=AIzaSecret", listener.local_addr().unwrap());
+        drop(listener);
+        let error = GeminiError::from(reqwest::get(&url).await.unwrap_err());
+        assert!(matches!(error, GeminiError::Network(_)), "{:?}", error);
+        assert!(!error.to_string().contains("AIzaSecret"), "{}", error);
+    }
+
+    #[test]
+    fn test_from_response_classifies_google_errors() {
+        let invalid_key = r#"{"error":{"code":400,"message":"API key not valid. Please pass a valid API key.","status":"INVALID_ARGUMENT","details":[{"@type":"type.googleapis.com/google.rpc.ErrorInfo","reason":"API_KEY_INVALID"}]}}"#;
+        assert_eq!(GeminiError::from_response(StatusCode::BAD_REQUEST, None, invalid_key), GeminiError::InvalidApiKey);
+
+        let quota = r#"{"error":{"code":429,"message":"Quota exceeded","status":"RESOURCE_EXHAUSTED","details":[{"@type":"type.googleapis.com/google.rpc.RetryInfo","retryDelay":"36.5s"}]}}"#;
+        assert_eq!(
+            GeminiError::from_response(StatusCode::TOO_MANY_REQUESTS, None, quota),
+            GeminiError::QuotaExceeded { retry_after: Some(37) }
+        );
+        assert_eq!(
+            GeminiError::from_response(StatusCode::TOO_MANY_REQUESTS, Some("5"), quota),
+            GeminiError::QuotaExceeded { retry_after: Some(5) }
+        );
+
+        let overloaded = r#"{"error":{"code":503,"message":"The model is overloaded.","status":"UNAVAILABLE"}}"#;
+        let error = GeminiError::from_response(StatusCode::SERVICE_UNAVAILABLE, None, overloaded);
+        assert_eq!(
+            error,
+            GeminiError::Http { status: 503, body: "The model is overloaded.".to_string(), retry_after: None }
+        );
+        assert!(error.retryable());
+
+        assert_eq!(GeminiError::from_response(StatusCode::PAYLOAD_TOO_LARGE, None, "too big"), GeminiError::FileTooLarge);
+        assert_eq!(
+            GeminiError::from_response(StatusCode::NOT_FOUND, Some("10"), "not json"),
+            GeminiError::Http { status: 404, body: "not json".to_string(), retry_after: Some(10) }
+        );
+    }
+
+    #[test]
+    fn test_app_error_serializes_code_message_and_retryable() {
+        let error = AppError::from(GeminiError::QuotaExceeded { retry_after: None }.context("Failed to generate transcription"));
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "code": "quota_exceeded",
+                "message": "Failed to generate transcription: The Gemini API quota was exceeded. Please retry later.",
+                "retryable": true,
+            })
+        );
+
+        assert_eq!(AppError::from(GeminiError::Cancelled), AppError::Cancelled);
+        assert_eq!(serde_json::to_value(AppError::from("Audio file not found")).unwrap()["code"], "error");
+    }
+}
@@ -0,0 +1,405 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::srt_utils::{cue_body, cue_speaker, format_timestamp, join_text, parse_srt, split_speaker, SubtitleCue};
+
+/// Cues further apart than this start a new paragraph even without a speaker change
+const PARAGRAPH_GAP_MS: u64 = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptFormat {
+    Plain,
+    Markdown,
+}
+
+impl TranscriptFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            TranscriptFormat::Plain => "txt",
+            TranscriptFormat::Markdown => "md",
+        }
+    }
+}
+
+struct Paragraph {
+    speaker: Option<String>,
+    start_ms: u64,
+    text: String,
+}
+
+/// Groups consecutive cues from the same speaker into paragraphs
+fn group_paragraphs(cues: &[SubtitleCue]) -> Vec<Paragraph> {
+    let mut paragraphs: Vec<Paragraph> = Vec::new();
+    let mut last_end_ms = 0;
+
+    for cue in cues {
+        let speaker = cue_speaker(cue).map(str::to_string);
+        let body = cue_body(cue);
+        if body.is_empty() {
+            continue;
+        }
+
+        match paragraphs.last_mut() {
+            Some(paragraph)
+                if paragraph.speaker == speaker
+                    && (speaker.is_some() || cue.start_ms.saturating_sub(last_end_ms) <= PARAGRAPH_GAP_MS) =>
+            {
+                paragraph.text = join_text(&paragraph.text, &body);
+            }
+            _ => paragraphs.push(Paragraph {
+                speaker,
+                start_ms: cue.start_ms,
+                text: body,
+            }),
+        }
+        last_end_ms = cue.end_ms;
+    }
+
+    paragraphs
+}
+
+/// Formats milliseconds as `[mm:ss]`, or `[h:mm:ss]` past the first hour
+fn format_short_timestamp(ms: u64) -> String {
+    let total_seconds = ms / 1000;
+    let hours = total_seconds / 3600;
+    if hours > 0 {
+        format!("[{}:{:02}:{:02}]", hours, (total_seconds % 3600) / 60, total_seconds % 60)
+    } else {
+        format!("[{:02}:{:02}]", total_seconds / 60, total_seconds % 60)
+    }
+}
+
+/// Converts SRT content into a readable transcript.
+///
+/// Consecutive cues from the same speaker are merged into paragraphs and
+/// line breaks inside cues are collapsed. Plain output keeps `話者: `
+/// labels; Markdown output adds a `##` header at every speaker change and,
+/// with `include_timestamps`, a `[mm:ss]` marker at each paragraph start.
+pub fn srt_to_transcript(content: &str, format: TranscriptFormat, include_timestamps: bool) -> Result<String, String> {
+    let cues = parse_srt(content)?;
+    let paragraphs = group_paragraphs(&cues);
+
+    let transcript = match format {
+        TranscriptFormat::Plain => paragraphs
+            .iter()
+            .map(|paragraph| match &paragraph.speaker {
+                Some(speaker) => format!("{}: {}", speaker, paragraph.text),
+                None => paragraph.text.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        TranscriptFormat::Markdown => {
+            let mut blocks = Vec::new();
+            let mut current_speaker: Option<&str> = None;
+            for paragraph in &paragraphs {
+                if let Some(speaker) = paragraph.speaker.as_deref() {
+                    if current_speaker != Some(speaker) {
+                        blocks.push(format!("## {}", speaker));
+                        current_speaker = Some(speaker);
+                    }
+                }
+                if include_timestamps {
+                    blocks.push(format!("{} {}", format_short_timestamp(paragraph.start_ms), paragraph.text));
+                } else {
+                    blocks.push(paragraph.text.clone());
+                }
+            }
+            blocks.join("\n\n")
+        }
+    };
+
+    Ok(transcript + "\n")
+}
+
+/// Flat representation of a cue shared by the CSV and JSON exports
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CueRecord {
+    pub index: u32,
+    pub start: String,
+    pub end: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub duration_ms: u64,
+    pub speaker: Option<String>,
+    pub text: String,
+}
+
+impl From<&SubtitleCue> for CueRecord {
+    fn from(cue: &SubtitleCue) -> Self {
+        let speaker = cue_speaker(cue).map(str::to_string);
+        let text = cue
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| match split_speaker(line) {
+                Some((_, text)) if i == 0 => text,
+                _ => line.as_str(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Self {
+            index: cue.index,
+            start: format_timestamp(cue.start_ms),
+            end: format_timestamp(cue.end_ms),
+            start_ms: cue.start_ms,
+            end_ms: cue.end_ms,
+            duration_ms: cue.end_ms.saturating_sub(cue.start_ms),
+            speaker,
+            text,
+        }
+    }
+}
+
+/// Quotes a CSV field when it contains a comma, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Converts SRT content into CSV with an
+/// `index,start,end,duration_ms,speaker,text` header row
+pub fn srt_to_csv(content: &str) -> Result<String, String> {
+    let cues = parse_srt(content)?;
+
+    let mut csv = String::from("index,start,end,duration_ms,speaker,text\n");
+    for record in cues.iter().map(CueRecord::from) {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{}\n",
+            record.index,
+            record.start,
+            record.end,
+            record.duration_ms,
+            csv_field(record.speaker.as_deref().unwrap_or("")),
+            csv_field(&record.text)
+        ));
+    }
+    Ok(csv)
+}
+
+/// Converts SRT content into a pretty-printed JSON array of [`CueRecord`]s
+pub fn srt_to_json(content: &str) -> Result<String, String> {
+    let records: Vec<CueRecord> = parse_srt(content)?.iter().map(CueRecord::from).collect();
+    serde_json::to_string_pretty(&records).map_err(|e| format!("Failed to serialize cues: {}", e))
+}
+
+/// Formats milliseconds as an SBV timestamp (`h:mm:ss.mmm`)
+pub fn format_sbv_timestamp(ms: u64) -> String {
+    format!(
+        "{}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        (ms % 3_600_000) / 60_000,
+        (ms % 60_000) / 1000,
+        ms % 1000
+    )
+}
+
+/// Converts SRT content into YouTube's SubViewer (`.sbv`) format.
+///
+/// Speaker labels are kept as plain `話者: ` prefixes when
+/// `keep_speaker_labels` is set and dropped otherwise.
+pub fn srt_to_sbv(content: &str, keep_speaker_labels: bool) -> Result<String, String> {
+    let cues = parse_srt(content)?;
+
+    let blocks: Vec<String> = cues
+        .iter()
+        .map(|cue| {
+            let lines: Vec<&str> = cue
+                .lines
+                .iter()
+                .enumerate()
+                .map(|(i, line)| match split_speaker(line) {
+                    Some((_, text)) if i == 0 && !keep_speaker_labels => text,
+                    _ => line.as_str(),
+                })
+                .collect();
+            format!(
+                "{},{}\n{}\n",
+                format_sbv_timestamp(cue.start_ms),
+                format_sbv_timestamp(cue.end_ms),
+                lines.join("\n")
+            )
+        })
+        .collect();
+
+    Ok(blocks.join("\n"))
+}
+
+/// Converts SRT content into an Audacity label track
+/// (`start_seconds<TAB>end_seconds<TAB>text` per line).
+///
+/// Line breaks and tabs inside cue text are replaced with spaces so that
+/// every cue stays on a single, correctly delimited line.
+pub fn srt_to_audacity_labels(content: &str) -> Result<String, String> {
+    let cues = parse_srt(content)?;
+
+    Ok(cues
+        .iter()
+        .map(|cue| {
+            let text = cue.lines.join(" ").replace('\t', " ");
+            format!(
+                "{:.6}\t{:.6}\t{}\n",
+                cue.start_ms as f64 / 1000.0,
+                cue.end_ms as f64 / 1000.0,
+                text
+            )
+        })
+        .collect())
+}
+
+const UTF8_BOM: &str = "\u{feff}";
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+/// How saved subtitle files are encoded. The default writes the content
+/// unchanged; Premiere Pro and some Windows tools need a BOM and CRLF.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct EncodingOptions {
+    pub bom: bool,
+    /// `None` keeps the line endings of the content
+    pub line_ending: Option<LineEnding>,
+}
+
+/// Applies `options` to `content`. Existing CRLF endings are normalized
+/// first so that no CR is doubled, and a BOM is never added twice.
+pub fn encode_output(content: &str, options: &EncodingOptions) -> Vec<u8> {
+    let body = content.strip_prefix(UTF8_BOM).unwrap_or(content);
+    let body = match options.line_ending {
+        Some(LineEnding::Lf) => body.replace("\r\n", "\n"),
+        Some(LineEnding::Crlf) => body.replace("\r\n", "\n").replace('\n', "\r\n"),
+        None => body.to_string(),
+    };
+    let has_bom = options.bom || content.starts_with(UTF8_BOM);
+    let mut bytes = Vec::with_capacity(body.len() + UTF8_BOM.len());
+    if has_bom {
+        bytes.extend_from_slice(UTF8_BOM.as_bytes());
+    }
+    bytes.extend_from_slice(body.as_bytes());
+    bytes
+}
+
+/// Writes `content` to `path` encoded with `options`
+pub async fn write_encoded(path: &Path, content: &str, options: &EncodingOptions) -> Result<(), String> {
+    tokio::fs::write(path, encode_output(content, options))
+        .await
+        .map_err(|e| format!("Failed to write file: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEAKER_SRT: &str = "1\n00:00:00,000 --> 00:00:02,000\nアオイ: こんにちは。\n\n2\n00:00:02,000 --> 00:00:04,000\nアオイ: 今日は\nよろしく。\n\n3\n00:01:05,000 --> 00:01:07,000\nユーザー: はい。\n";
+
+    #[test]
+    fn test_plain_transcript_merges_same_speaker() {
+        let transcript = srt_to_transcript(SPEAKER_SRT, TranscriptFormat::Plain, false).unwrap();
+        assert_eq!(transcript, "アオイ: こんにちは。今日はよろしく。\nユーザー: はい。\n");
+    }
+
+    #[test]
+    fn test_markdown_transcript_with_timestamps() {
+        let transcript = srt_to_transcript(SPEAKER_SRT, TranscriptFormat::Markdown, true).unwrap();
+        assert_eq!(
+            transcript,
+            "## アオイ\n\n[00:00] こんにちは。今日はよろしく。\n\n## ユーザー\n\n[01:05] はい。\n"
+        );
+    }
+
+    #[test]
+    fn test_plain_transcript_without_speakers_breaks_on_gaps() {
+        let input = "1\n00:00:00,000 --> 00:00:01,000\nHello\nthere.\n\n2\n00:00:01,500 --> 00:00:02,000\nHow are you?\n\n3\n00:00:10,000 --> 00:00:11,000\nFine.\n";
+        let transcript = srt_to_transcript(input, TranscriptFormat::Plain, false).unwrap();
+        assert_eq!(transcript, "Hello there. How are you?\nFine.\n");
+    }
+
+    #[test]
+    fn test_format_short_timestamp() {
+        assert_eq!(format_short_timestamp(65_000), "[01:05]");
+        assert_eq!(format_short_timestamp(3_725_000), "[1:02:05]");
+    }
+
+    const QUOTED_SRT: &str = "1\n00:00:01,000 --> 00:00:03,500\nアオイ: 「はい」, そうです\n\n2\n00:00:04,000 --> 00:00:05,000\n彼は\"OK\"と言った\n二行目\n";
+
+    #[test]
+    fn test_srt_to_csv_quotes_fields() {
+        let csv = srt_to_csv(QUOTED_SRT).unwrap();
+        assert_eq!(
+            csv,
+            "index,start,end,duration_ms,speaker,text\n\
+             1,00:00:01,000,00:00:03,500,2500,アオイ,\"「はい」, そうです\"\n\
+             2,00:00:04,000,00:00:05,000,1000,,\"彼は\"\"OK\"\"と言った\n二行目\"\n"
+        );
+    }
+
+    #[test]
+    fn test_srt_to_json() {
+        let json = srt_to_json(QUOTED_SRT).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value[0]["speaker"], "アオイ");
+        assert_eq!(value[0]["text"], "「はい」, そうです");
+        assert_eq!(value[0]["duration_ms"], 2500);
+        assert_eq!(value[1]["speaker"], serde_json::Value::Null);
+        assert_eq!(value[1]["text"], "彼は\"OK\"と言った\n二行目");
+    }
+
+    #[test]
+    fn test_srt_to_sbv() {
+        let input = "1\n00:00:05,520 --> 00:00:08,910\nアオイ: こんにちは\n二行目\n\n2\n01:00:09,150 --> 01:00:11,300\n次の字幕\n";
+        assert_eq!(
+            srt_to_sbv(input, false).unwrap(),
+            "0:00:05.520,0:00:08.910\nこんにちは\n二行目\n\n1:00:09.150,1:00:11.300\n次の字幕\n"
+        );
+        assert!(srt_to_sbv(input, true).unwrap().contains("\nアオイ: こんにちは\n"));
+    }
+
+    #[test]
+    fn test_srt_to_audacity_labels() {
+        let input = "1\n00:00:05,520 --> 00:00:08,910\nこんにちは\t世界\n二行目\n\n2\n01:00:00,001 --> 01:00:01,000\n次\n";
+        assert_eq!(
+            srt_to_audacity_labels(input).unwrap(),
+            "5.520000\t8.910000\tこんにちは 世界 二行目\n3600.001000\t3601.000000\t次\n"
+        );
+    }
+
+    async fn written_bytes(content: &str, options: EncodingOptions) -> Vec<u8> {
+        let path = std::env::temp_dir().join(format!("encoded_{}.srt", uuid::Uuid::new_v4()));
+        write_encoded(&path, content, &options).await.unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        bytes
+    }
+
+    #[tokio::test]
+    async fn test_write_encoded_default_keeps_content() {
+        let content = "1\n00:00:00,000 --> 00:00:01,000\nこんにちは\n";
+        assert_eq!(written_bytes(content, EncodingOptions::default()).await, content.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_write_encoded_bom_and_crlf() {
+        let options = EncodingOptions { bom: true, line_ending: Some(LineEnding::Crlf) };
+        let mut expected = vec![0xEF, 0xBB, 0xBF];
+        expected.extend_from_slice(b"1\r\n00:00:00,000 --> 00:00:01,000\r\nA\r\n");
+
+        assert_eq!(written_bytes("1\n00:00:00,000 --> 00:00:01,000\nA\n", options).await, expected);
+        // Content that already has CRLF and a BOM is not doubled up
+        assert_eq!(written_bytes("\u{feff}1\r\n00:00:00,000 --> 00:00:01,000\r\nA\n", options).await, expected);
+    }
+
+    #[test]
+    fn test_encode_output_to_lf() {
+        let options = EncodingOptions { bom: false, line_ending: Some(LineEnding::Lf) };
+        assert_eq!(encode_output("A\r\nB\n", &options), b"A\nB\n");
+    }
+}
@@ -0,0 +1,176 @@
+use std::fs::OpenOptions;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Used when nothing is left of the suggested name
+pub const DEFAULT_FILE_NAME: &str = "untitled";
+
+/// Sanitized names are cut to this many bytes, leaving room within the usual
+/// 255-byte limit for the ` (n)` suffix and extension `unique_path` appends
+const MAX_FILE_NAME_BYTES: usize = 200;
+
+/// `unique_path` gives up after trying this many numbered names
+const MAX_UNIQUE_SUFFIX: u32 = 999;
+
+/// Device names Windows reserves regardless of extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "LPT1", "LPT2", "LPT3",
+    "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+fn trim_name(name: &str) -> &str {
+    name.trim_start().trim_end_matches(['.', ' '])
+}
+
+/// Makes `name` safe to use as a file name on Windows, macOS and Linux.
+///
+/// Path separators, characters Windows rejects and control characters become
+/// `_`, trailing dots and spaces are removed, reserved device names such as
+/// `CON` get a `_` prefix, and the result is cut to a safe byte length on a
+/// character boundary, shortening the stem so the extension survives.
+/// Returns [`DEFAULT_FILE_NAME`] when nothing is left.
+pub fn sanitize_filename(name: &str) -> String {
+    let replaced: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    let mut sanitized = trim_name(&replaced).to_string();
+    let stem = sanitized.split('.').next().unwrap_or_default().trim_end();
+    if RESERVED_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem)) {
+        sanitized.insert(0, '_');
+    }
+
+    if sanitized.len() > MAX_FILE_NAME_BYTES {
+        sanitized = match sanitized.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() && ext.len() < MAX_FILE_NAME_BYTES / 2 => {
+                let stem = truncate_bytes(stem, MAX_FILE_NAME_BYTES - ext.len() - 1);
+                format!("{}.{}", trim_name(stem), ext)
+            }
+            _ => trim_name(truncate_bytes(&sanitized, MAX_FILE_NAME_BYTES)).to_string(),
+        };
+    }
+
+    if sanitized.is_empty() {
+        DEFAULT_FILE_NAME.to_string()
+    } else {
+        sanitized
+    }
+}
+
+fn truncate_bytes(s: &str, max: usize) -> &str {
+    let mut end = max.min(s.len());
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// Checks that `dir` exists, is a directory and accepts new files
+pub fn validate_output_dir(dir: &Path) -> Result<(), String> {
+    if !dir.is_dir() {
+        return Err(format!("Output directory does not exist: {}", dir.display()));
+    }
+    let probe = dir.join(format!(".str_app_write_test_{}", std::process::id()));
+    std::fs::write(&probe, b"")
+        .and_then(|_| std::fs::remove_file(&probe))
+        .map_err(|e| format!("Output directory is not writable: {} ({})", dir.display(), e))
+}
+
+/// `dir/stem.ext`, or the first free `dir/stem (n).ext` counting from 2 when
+/// that file already exists.
+///
+/// The returned path is claimed by creating it empty with `create_new`, so a
+/// concurrent save cannot pick the same name before the caller writes to it.
+pub fn unique_path(dir: &Path, stem: &str, ext: &str) -> Result<PathBuf, String> {
+    let candidates = std::iter::once(stem.to_string()).chain((2..=MAX_UNIQUE_SUFFIX).map(|n| format!("{} ({})", stem, n)));
+    for name in candidates {
+        let path = dir.join(format!("{}.{}", name, ext));
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(_) => return Ok(path),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("Failed to create {}: {}", path.display(), e)),
+        }
+    }
+    Err(format!("Too many files named {}.{} in {}", stem, ext, dir.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_filename_reserved_names() {
+        assert_eq!(sanitize_filename("CON.srt"), "_CON.srt");
+        assert_eq!(sanitize_filename("nul"), "_nul");
+        assert_eq!(sanitize_filename("com1.tar.gz"), "_com1.tar.gz");
+        assert_eq!(sanitize_filename("CONSOLE.srt"), "CONSOLE.srt");
+    }
+
+    #[test]
+    fn test_sanitize_filename_trailing_dots_and_characters() {
+        assert_eq!(sanitize_filename("interview. . "), "interview");
+        assert_eq!(sanitize_filename("a/b:c*d?\u{7}"), "a_b_c_d__");
+        assert_eq!(sanitize_filename(" ... "), DEFAULT_FILE_NAME);
+        assert_eq!(sanitize_filename(""), DEFAULT_FILE_NAME);
+    }
+
+    #[test]
+    fn test_validate_output_dir() {
+        let dir = std::env::temp_dir();
+        assert!(validate_output_dir(&dir).is_ok());
+        assert!(validate_output_dir(&dir.join(format!("missing_{}", uuid::Uuid::new_v4()))).is_err());
+
+        let file = dir.join(format!("output_dir_file_{}", uuid::Uuid::new_v4()));
+        std::fs::write(&file, "").unwrap();
+        assert!(validate_output_dir(&file).is_err());
+        std::fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn test_unique_path() {
+        let dir = std::env::temp_dir().join(format!("unique_path_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        assert_eq!(unique_path(&dir, "talk", "srt").unwrap(), dir.join("talk.srt"));
+
+        assert!(dir.join("talk.srt").exists());
+        assert_eq!(unique_path(&dir, "talk", "srt").unwrap(), dir.join("talk (2).srt"));
+        assert_eq!(unique_path(&dir, "talk", "srt").unwrap(), dir.join("talk (3).srt"));
+        assert_eq!(unique_path(&dir, "talk", "csv").unwrap(), dir.join("talk.csv"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_sanitize_filename_truncates_long_names() {
+        let long = "字".repeat(100);
+        let sanitized = sanitize_filename(&long);
+        assert!(long.len() > 255);
+        assert!(sanitized.len() <= MAX_FILE_NAME_BYTES);
+        assert_eq!(sanitized, "字".repeat(MAX_FILE_NAME_BYTES / 3));
+
+        let with_extension = sanitize_filename(&format!("{}.mp3", long));
+        assert!(with_extension.len() <= MAX_FILE_NAME_BYTES);
+        assert!(with_extension.ends_with("字.mp3"));
+    }
+
+    #[test]
+    fn test_unique_path_concurrent_callers_get_distinct_names() {
+        let dir = std::env::temp_dir().join(format!("unique_path_race_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let dir = dir.clone();
+                std::thread::spawn(move || unique_path(&dir, "talk", "srt").unwrap())
+            })
+            .collect();
+        let mut paths: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        paths.sort();
+        paths.dedup();
+        assert_eq!(paths.len(), 8);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
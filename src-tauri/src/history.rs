@@ -0,0 +1,183 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// A stored transcription result
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistoryEntry {
+    pub id: i64,
+    /// Unix timestamp (seconds) of when the entry was recorded
+    pub created_at: i64,
+    pub source_filename: Option<String>,
+    pub model: String,
+    pub max_chars_per_subtitle: u32,
+    pub srt: String,
+    pub prompt_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+}
+
+/// History entry without the SRT body, for listing
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistorySummary {
+    pub id: i64,
+    pub created_at: i64,
+    pub source_filename: Option<String>,
+    pub model: String,
+    pub max_chars_per_subtitle: u32,
+    pub prompt_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+}
+
+/// Fields supplied when recording a new entry
+pub struct NewHistoryEntry<'a> {
+    pub source_filename: Option<&'a str>,
+    pub model: &'a str,
+    pub max_chars_per_subtitle: u32,
+    pub srt: &'a str,
+    pub prompt_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+}
+
+/// SQLite-backed transcription history, kept in Tauri managed state
+pub struct HistoryStore {
+    conn: Mutex<Connection>,
+}
+
+impl HistoryStore {
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(conn: Connection) -> rusqlite::Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                created_at INTEGER NOT NULL,
+                source_filename TEXT,
+                model TEXT NOT NULL,
+                max_chars_per_subtitle INTEGER NOT NULL,
+                srt TEXT NOT NULL,
+                prompt_tokens INTEGER,
+                output_tokens INTEGER
+            );",
+        )?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        // A panic while holding the lock cannot leave SQLite in a broken state
+        self.conn.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn insert(&self, entry: &NewHistoryEntry) -> rusqlite::Result<i64> {
+        let created_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default();
+        let conn = self.conn();
+        conn.execute(
+            "INSERT INTO history (created_at, source_filename, model, max_chars_per_subtitle, srt, prompt_tokens, output_tokens)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                created_at,
+                entry.source_filename,
+                entry.model,
+                entry.max_chars_per_subtitle,
+                entry.srt,
+                entry.prompt_tokens,
+                entry.output_tokens
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Lists entries, newest first
+    pub fn list(&self) -> rusqlite::Result<Vec<HistorySummary>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT id, created_at, source_filename, model, max_chars_per_subtitle, prompt_tokens, output_tokens
+             FROM history ORDER BY created_at DESC, id DESC",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok(HistorySummary {
+                id: row.get(0)?,
+                created_at: row.get(1)?,
+                source_filename: row.get(2)?,
+                model: row.get(3)?,
+                max_chars_per_subtitle: row.get(4)?,
+                prompt_tokens: row.get(5)?,
+                output_tokens: row.get(6)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn get(&self, id: i64) -> rusqlite::Result<Option<HistoryEntry>> {
+        self.conn()
+            .query_row(
+                "SELECT id, created_at, source_filename, model, max_chars_per_subtitle, srt, prompt_tokens, output_tokens
+                 FROM history WHERE id = ?1",
+                params![id],
+                |row| {
+                    Ok(HistoryEntry {
+                        id: row.get(0)?,
+                        created_at: row.get(1)?,
+                        source_filename: row.get(2)?,
+                        model: row.get(3)?,
+                        max_chars_per_subtitle: row.get(4)?,
+                        srt: row.get(5)?,
+                        prompt_tokens: row.get(6)?,
+                        output_tokens: row.get(7)?,
+                    })
+                },
+            )
+            .optional()
+    }
+
+    /// Deletes an entry, returning whether it existed
+    pub fn delete(&self, id: i64) -> rusqlite::Result<bool> {
+        Ok(self.conn().execute("DELETE FROM history WHERE id = ?1", params![id])? > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(srt: &str) -> NewHistoryEntry<'_> {
+        NewHistoryEntry {
+            source_filename: Some("interview.mp3"),
+            model: "gemini-2.5-pro",
+            max_chars_per_subtitle: 20,
+            srt,
+            prompt_tokens: Some(1200),
+            output_tokens: None,
+        }
+    }
+
+    #[test]
+    fn test_insert_get_list_delete() {
+        let store = HistoryStore::open_in_memory().unwrap();
+        let first = store.insert(&sample("1\n00:00:00,000 --> 00:00:01,000\nA\n")).unwrap();
+        let second = store.insert(&sample("1\n00:00:00,000 --> 00:00:01,000\nB\n")).unwrap();
+
+        let entry = store.get(first).unwrap().unwrap();
+        assert_eq!(entry.source_filename.as_deref(), Some("interview.mp3"));
+        assert_eq!(entry.srt, "1\n00:00:00,000 --> 00:00:01,000\nA\n");
+        assert_eq!(entry.prompt_tokens, Some(1200));
+        assert_eq!(entry.output_tokens, None);
+
+        let ids: Vec<i64> = store.list().unwrap().iter().map(|summary| summary.id).collect();
+        assert_eq!(ids, vec![second, first]);
+
+        assert!(store.delete(first).unwrap());
+        assert!(!store.delete(first).unwrap());
+        assert_eq!(store.get(first).unwrap(), None);
+    }
+}
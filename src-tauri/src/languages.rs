@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+
+/// ISO 639-1 codes with their English names
+const ISO_639_1: [(&str, &str); 183] = [
+    ("aa", "Afar"), ("ab", "Abkhazian"), ("ae", "Avestan"), ("af", "Afrikaans"), ("ak", "Akan"), ("am", "Amharic"),
+    ("an", "Aragonese"), ("ar", "Arabic"), ("as", "Assamese"), ("av", "Avaric"), ("ay", "Aymara"), ("az", "Azerbaijani"),
+    ("ba", "Bashkir"), ("be", "Belarusian"), ("bg", "Bulgarian"), ("bi", "Bislama"), ("bm", "Bambara"), ("bn", "Bengali"),
+    ("bo", "Tibetan"), ("br", "Breton"), ("bs", "Bosnian"), ("ca", "Catalan"), ("ce", "Chechen"), ("ch", "Chamorro"),
+    ("co", "Corsican"), ("cr", "Cree"), ("cs", "Czech"), ("cu", "Church Slavic"), ("cv", "Chuvash"), ("cy", "Welsh"),
+    ("da", "Danish"), ("de", "German"), ("dv", "Divehi"), ("dz", "Dzongkha"), ("ee", "Ewe"), ("el", "Greek"),
+    ("en", "English"), ("eo", "Esperanto"), ("es", "Spanish"), ("et", "Estonian"), ("eu", "Basque"), ("fa", "Persian"),
+    ("ff", "Fulah"), ("fi", "Finnish"), ("fj", "Fijian"), ("fo", "Faroese"), ("fr", "French"), ("fy", "Western Frisian"),
+    ("ga", "Irish"), ("gd", "Scottish Gaelic"), ("gl", "Galician"), ("gn", "Guarani"), ("gu", "Gujarati"), ("gv", "Manx"),
+    ("ha", "Hausa"), ("he", "Hebrew"), ("hi", "Hindi"), ("ho", "Hiri Motu"), ("hr", "Croatian"), ("ht", "Haitian"),
+    ("hu", "Hungarian"), ("hy", "Armenian"), ("hz", "Herero"), ("ia", "Interlingua"), ("id", "Indonesian"), ("ie", "Interlingue"),
+    ("ig", "Igbo"), ("ii", "Sichuan Yi"), ("ik", "Inupiaq"), ("io", "Ido"), ("is", "Icelandic"), ("it", "Italian"),
+    ("iu", "Inuktitut"), ("ja", "Japanese"), ("jv", "Javanese"), ("ka", "Georgian"), ("kg", "Kongo"), ("ki", "Kikuyu"),
+    ("kj", "Kuanyama"), ("kk", "Kazakh"), ("kl", "Kalaallisut"), ("km", "Khmer"), ("kn", "Kannada"), ("ko", "Korean"),
+    ("kr", "Kanuri"), ("ks", "Kashmiri"), ("ku", "Kurdish"), ("kv", "Komi"), ("kw", "Cornish"), ("ky", "Kyrgyz"),
+    ("la", "Latin"), ("lb", "Luxembourgish"), ("lg", "Ganda"), ("li", "Limburgish"), ("ln", "Lingala"), ("lo", "Lao"),
+    ("lt", "Lithuanian"), ("lu", "Luba-Katanga"), ("lv", "Latvian"), ("mg", "Malagasy"), ("mh", "Marshallese"), ("mi", "Maori"),
+    ("mk", "Macedonian"), ("ml", "Malayalam"), ("mn", "Mongolian"), ("mr", "Marathi"), ("ms", "Malay"), ("mt", "Maltese"),
+    ("my", "Burmese"), ("na", "Nauru"), ("nb", "Norwegian Bokmål"), ("nd", "North Ndebele"), ("ne", "Nepali"), ("ng", "Ndonga"),
+    ("nl", "Dutch"), ("nn", "Norwegian Nynorsk"), ("no", "Norwegian"), ("nr", "South Ndebele"), ("nv", "Navajo"), ("ny", "Chichewa"),
+    ("oc", "Occitan"), ("oj", "Ojibwa"), ("om", "Oromo"), ("or", "Oriya"), ("os", "Ossetian"), ("pa", "Punjabi"),
+    ("pi", "Pali"), ("pl", "Polish"), ("ps", "Pashto"), ("pt", "Portuguese"), ("qu", "Quechua"), ("rm", "Romansh"),
+    ("rn", "Rundi"), ("ro", "Romanian"), ("ru", "Russian"), ("rw", "Kinyarwanda"), ("sa", "Sanskrit"), ("sc", "Sardinian"),
+    ("sd", "Sindhi"), ("se", "Northern Sami"), ("sg", "Sango"), ("si", "Sinhala"), ("sk", "Slovak"), ("sl", "Slovenian"),
+    ("sm", "Samoan"), ("sn", "Shona"), ("so", "Somali"), ("sq", "Albanian"), ("sr", "Serbian"), ("ss", "Swati"),
+    ("st", "Southern Sotho"), ("su", "Sundanese"), ("sv", "Swedish"), ("sw", "Swahili"), ("ta", "Tamil"), ("te", "Telugu"),
+    ("tg", "Tajik"), ("th", "Thai"), ("ti", "Tigrinya"), ("tk", "Turkmen"), ("tl", "Tagalog"), ("tn", "Tswana"),
+    ("to", "Tonga"), ("tr", "Turkish"), ("ts", "Tsonga"), ("tt", "Tatar"), ("tw", "Twi"), ("ty", "Tahitian"),
+    ("ug", "Uyghur"), ("uk", "Ukrainian"), ("ur", "Urdu"), ("uz", "Uzbek"), ("ve", "Venda"), ("vi", "Vietnamese"),
+    ("vo", "Volapük"), ("wa", "Walloon"), ("wo", "Wolof"), ("xh", "Xhosa"), ("yi", "Yiddish"), ("yo", "Yoruba"),
+    ("za", "Zhuang"), ("zh", "Chinese"), ("zu", "Zulu"),
+];
+
+/// English name of an ISO 639-1 code, compared case-insensitively
+pub fn language_name(code: &str) -> Option<&'static str> {
+    let code = code.trim().to_lowercase();
+    ISO_639_1.iter().find(|(known, _)| *known == code).map(|(_, name)| *name)
+}
+
+/// Gemini's answer to the language detection prompt
+#[derive(Debug, Deserialize)]
+pub struct DetectedLanguage {
+    pub code: String,
+    pub confidence: f32,
+}
+
+impl DetectedLanguage {
+    /// Response schema for [`DetectedLanguage`]
+    pub fn schema() -> serde_json::Value {
+        serde_json::json!({
+            "type": "OBJECT",
+            "properties": {
+                "code": { "type": "STRING" },
+                "confidence": { "type": "NUMBER" }
+            },
+            "required": ["code", "confidence"]
+        })
+    }
+}
+
+/// Result of `detect_language`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LanguageResult {
+    /// Lowercase ISO 639-1 code, usable as the `language` of `transcribe_audio`
+    pub code: String,
+    pub name: String,
+    /// Between 0 and 1
+    pub confidence: f32,
+}
+
+impl TryFrom<DetectedLanguage> for LanguageResult {
+    type Error = String;
+
+    fn try_from(detected: DetectedLanguage) -> Result<Self, Self::Error> {
+        let code = detected.code.trim().to_lowercase();
+        let name = language_name(&code).ok_or_else(|| format!("Unknown language code: {}", detected.code))?;
+        Ok(LanguageResult {
+            code,
+            name: name.to_string(),
+            confidence: detected.confidence.clamp(0.0, 1.0),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detected_language_is_validated() {
+        let detected: DetectedLanguage = serde_json::from_str(r#"{"code": "JA", "confidence": 0.97}"#).unwrap();
+        assert_eq!(
+            LanguageResult::try_from(detected).unwrap(),
+            LanguageResult { code: "ja".to_string(), name: "Japanese".to_string(), confidence: 0.97 }
+        );
+
+        let out_of_range = DetectedLanguage { code: "en".to_string(), confidence: 1.5 };
+        assert_eq!(LanguageResult::try_from(out_of_range).unwrap().confidence, 1.0);
+
+        for code in ["jp", "eng", ""] {
+            let unknown = DetectedLanguage { code: code.to_string(), confidence: 0.9 };
+            assert!(LanguageResult::try_from(unknown).is_err(), "{}", code);
+        }
+    }
+}
@@ -1,491 +1,3121 @@
-use keyring::Entry;
-use std::path::Path;
+use reqwest::{Certificate, Proxy};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_store::StoreExt;
 use tokio::fs;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+mod error;
+use error::{AppError, GeminiError};
+
+mod auth;
+use auth::{AuthMode, ServiceAccountAuth};
+
+mod fs_utils;
+use fs_utils::{sanitize_filename, unique_path, validate_output_dir};
 
 mod gemini;
-use gemini::GeminiClient;
+use gemini::{
+    default_safety_settings, extension_for_mime_type, load_root_certificates, normalize_base_url, parse_proxy, vertex_base_url, DEFAULT_BASE_URL, pick_model, resolve_mime_type, AvailableModel, Citation, ConcurrencyLimits, FileInfo, GeminiClient, GenerationConfig, GenerationResult, GenerationSettings, ProcessingProgress, RequestSlots, RetryEvent, RetrySettings, SafetySetting, SlotKind, SlotNotifier, StreamChunk, TimeoutSettings,
+    UploadProgress, UsageMetadata,
+};
 
 mod srt_utils;
-use srt_utils::extract_srt_content;
+use srt_utils::{
+    chunk_ranges, close_truncated_fence, collapse_repeated_cues, drop_incomplete_final_cue, enforce_durations, estimate_cue_quality, extract_all_srt_content,
+    extract_srt_block, extract_srt_content, fix_overlaps, format_timestamp, merge_short_cues, merge_srt_parts, merge_translation, normalize_srt, parse_srt, renumber, serialize_srt,
+    remap_speaker_labels, remove_filler_words, repair_srt, validate_word_timings, word_timings_to_srt, WordTiming, remove_speaker_labels, sanitize_markup, lint_srt, shift_srt, split_long_cues, split_srt_at, stitch_chunks, stitch_continuation, wrap_cue_lines, DurationAdjustments, DurationLimits, LintWarning, SubtitleCue, DEFAULT_ALLOWED_TAGS, DEFAULT_MAX_CUE_MS, DEFAULT_MIN_CUE_MS,
+    DEFAULT_SEAM_TOLERANCE_MS, MIN_REPEAT_RUN,
+};
+
+mod vtt_utils;
+use vtt_utils::{srt_to_vtt, vtt_to_srt};
+
+mod ass_utils;
+use ass_utils::{srt_to_ass, AssStyle};
+
+mod history;
+use history::{HistoryEntry, HistoryStore, HistorySummary, NewHistoryEntry};
+
+mod export_utils;
+use export_utils::{
+    srt_to_audacity_labels, srt_to_csv, srt_to_json, srt_to_sbv, srt_to_transcript, write_encoded, CueRecord, EncodingOptions,
+    TranscriptFormat,
+};
+
+mod diff_utils;
+use diff_utils::{diff_srt, diff_srt_by_index, DiffHunk, TranscriptionDiff, DEFAULT_TIMING_THRESHOLD_MS};
+
+mod dictionary;
+use dictionary::{
+    apply_dictionary, dictionary_to_csv, merge_dictionary_entries, parse_dictionary_csv, validate_dictionary_csv, validate_generated_terms,
+    GeneratedTerm, LocalDictionaryResult, TopicAnalysis,
+};
+
+mod pricing;
+use pricing::{CostEstimate, SessionUsage, TokenUsage, UsageTracker};
+
+mod prompts;
+use prompts::{
+    analyze_topic_prompt, build_transcription_prompt, chunk_range_instruction, create_dictionary_prompt, enhance_with_dictionary_prompt, is_srt_model, srt_prompt, structure_dictionary_prompt,
+    word_level_prompt, DETECT_LANGUAGE_PROMPT,
+    Prompt, PromptTemplate, PromptTemplates, SubtitleRules, TranscriptionLanguage,
+};
+
+mod srt_index;
+use srt_index::{AdjacentCues, SrtIndex};
+
+mod stats;
+use stats::{find_unclear, srt_statistics, SrtStatistics, UnclearSegment};
+
+mod transcription_lock;
+use transcription_lock::TranscriptionLock;
+
+mod key_storage;
+use key_storage::{normalize_api_key, KeyStorage, KeyStorageBackend};
+
+mod progress;
+use progress::{percent_of, ProgressStage, ProgressTracker, TranscriptionProgress};
+
+mod audio_probe;
+use audio_probe::probe_duration_ms;
+
+mod upload_cache;
+use upload_cache::{key_fingerprint, sha256_file, CachedUpload, UploadCache};
+
+mod session;
+use session::{new_session_id, Session, SessionStore};
+
+mod languages;
+use languages::{DetectedLanguage, LanguageResult};
+
+mod logging;
+use logging::{LogHandle, LogLevel, LOG_FILE_NAME};
 
-const SERVICE_NAME: &str = "gemini-str-app";
 const API_KEY_ENTRY: &str = "gemini_api_key";
+const API_KEYS_ENTRY: &str = "gemini_api_keys";
+const SETTINGS_STORE: &str = "settings.json";
+const BASE_URL_SETTING: &str = "gemini_base_url";
+const PROXY_SETTING: &str = "proxy_url";
+const PROXY_USERNAME_SETTING: &str = "proxy_username";
+/// Kept in the key storage rather than the plain settings store
+const PROXY_PASSWORD_ENTRY: &str = "proxy_password";
+const CA_CERTIFICATE_SETTING: &str = "ca_certificate_path";
+const AUTH_SETTING: &str = "auth_mode";
+const SAFETY_SETTING: &str = "safety_settings";
+const GENERATION_SETTING: &str = "generation_settings";
+const FILLER_WORDS_SETTING: &str = "filler_words";
+const OUTPUT_DIR_SETTING: &str = "default_output_dir";
+const RETRY_SETTING: &str = "retry_settings";
+const CONCURRENCY_SETTING: &str = "concurrency_limits";
+const LOG_LEVEL_SETTING: &str = "log_level";
+/// Upper bounds accepted by `set_retry_settings`
+const MAX_RETRY_ATTEMPTS_LIMIT: u32 = 10;
+const MAX_RETRY_DELAY_LIMIT_MS: u64 = 5 * 60 * 1000;
+const TIMEOUT_SETTING: &str = "timeout_settings";
+/// Range accepted by `set_timeout_settings` for every timeout
+const TIMEOUT_LIMITS_SECS: std::ops::RangeInclusive<u64> = 1..=3600;
+/// Models tried in order for `create_dictionary`, `translate_srt` and
+/// `enhance_transcription_with_dictionary` when none is given
+const TEXT_MODEL_PREFERENCE: [&str; 3] = ["gemini-2.5-pro", "gemini-2.5-flash", "gemini-2.0-flash"];
+/// How often `transcribe_audio` asks for the rest of a cut-off response
+const MAX_CONTINUATIONS: usize = 3;
+/// Complete cues quoted in a continuation request so the model knows where
+/// to pick up
+const CONTINUATION_CONTEXT_CUES: usize = 3;
+/// Lines per cue when `wrap_lines` is enabled
+const MAX_CUE_LINES: usize = 2;
+/// Chunk length used by `transcribe_audio_chunked` unless one is given
+const DEFAULT_CHUNK_LENGTH_MS: u32 = 10 * 60 * 1000;
+/// Subtitle length used when `TranscriptionOptions` leave it out
+const DEFAULT_MAX_CHARS_PER_SUBTITLE: u32 = 20;
+const MIN_CHUNK_LENGTH_MS: u32 = 30 * 1000;
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
-fn greet(name: &str) -> String {
-    format!("Hello, {}! You've been greeted from Rust!", name)
+fn greet(name: &str) -> String {
+    format!("Hello, {}! You've been greeted from Rust!", name)
+}
+
+/// Outcome of `validate_api_key`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ApiKeyStatus {
+    Valid,
+    /// Rejected by the API (400, 401 or 403)
+    Invalid,
+    /// The API could not be reached
+    NetworkError,
+    /// Any other failure, such as an exhausted quota
+    Error,
+}
+
+#[derive(Debug, Serialize)]
+struct ApiKeyValidation {
+    status: ApiKeyStatus,
+    /// Why the check failed; `None` when the key is valid
+    message: Option<String>,
+}
+
+impl ApiKeyValidation {
+    fn from_result(result: Result<(), GeminiError>) -> Self {
+        let error = match result {
+            Ok(()) => return ApiKeyValidation { status: ApiKeyStatus::Valid, message: None },
+            Err(error) => error,
+        };
+        let status = match error.root() {
+            GeminiError::InvalidApiKey | GeminiError::Http { status: 400 | 403, .. } => ApiKeyStatus::Invalid,
+            GeminiError::Network(_) | GeminiError::Timeout => ApiKeyStatus::NetworkError,
+            _ => ApiKeyStatus::Error,
+        };
+        ApiKeyValidation { status, message: Some(error.to_string()) }
+    }
+}
+
+/// Checks `api_key` with a single cheap request through the configured base
+/// URL, proxy and certificates, ignoring the key pool and service account
+async fn check_api_key(app: &AppHandle, api_key: &str) -> ApiKeyValidation {
+    let api_key = match normalize_api_key(api_key) {
+        Ok(api_key) => api_key,
+        Err(e) => return ApiKeyValidation { status: ApiKeyStatus::Invalid, message: Some(e) },
+    };
+    let client = GeminiClient::with_base_url(vec![api_key], &load_base_url(app))
+        .with_timeouts(load_timeout_settings(app))
+        .with_root_certificates(load_root_certificate_setting(app))
+        .with_proxy(load_proxy(app));
+    ApiKeyValidation::from_result(client.check_api_key().await)
+}
+
+#[tauri::command]
+async fn validate_api_key(app: AppHandle, api_key: String) -> Result<ApiKeyValidation, String> {
+    Ok(check_api_key(&app, &api_key).await)
+}
+
+/// Result of `set_api_key`; the key is saved whatever the validation says
+#[derive(Serialize)]
+struct ApiKeySaved {
+    /// Set when `validate` was requested
+    validation: Option<ApiKeyValidation>,
+}
+
+#[tauri::command]
+async fn set_api_key(app: AppHandle, key_storage: State<'_, KeyStorage>, api_key: String, validate: Option<bool>) -> Result<ApiKeySaved, String> {
+    let api_key = normalize_api_key(&api_key).inspect_err(|e| warn!("Rejected API key: {}", e))?;
+
+    key_storage.set(API_KEY_ENTRY, &api_key).map_err(|e| {
+        error!("Failed to save API key: {}", e);
+        format!("Failed to store API key: {}", e)
+    })?;
+    info!("Saved API key to {:?}", key_storage.backend());
+
+    // Verify the save immediately; still report success if the read fails
+    match key_storage.get(API_KEY_ENTRY) {
+        Ok(Some(_)) => debug!("Verification read of the saved API key succeeded"),
+        Ok(None) => warn!("Verification read found no API key"),
+        Err(e) => warn!("Verification read failed: {}", e),
+    }
+
+    let validation = match validate {
+        Some(true) => {
+            let validation = check_api_key(&app, &api_key).await;
+            if validation.status != ApiKeyStatus::Valid {
+                warn!("Saved API key failed validation: {:?}", validation);
+            }
+            Some(validation)
+        }
+        _ => None,
+    };
+    Ok(ApiKeySaved { validation })
+}
+
+#[tauri::command]
+async fn get_api_key(key_storage: State<'_, KeyStorage>) -> Result<String, String> {
+    key_storage
+        .get(API_KEY_ENTRY)
+        .map(Option::unwrap_or_default)
+        .map_err(|e| format!("Failed to retrieve API key: {}", e))
+}
+
+#[tauri::command]
+async fn delete_api_key(key_storage: State<'_, KeyStorage>) -> Result<bool, String> {
+    key_storage
+        .delete(API_KEY_ENTRY)
+        .map(|_| true)
+        .map_err(|e| format!("Failed to delete API key: {}", e))
+}
+
+#[tauri::command]
+async fn get_api_key_preview(key_storage: State<'_, KeyStorage>) -> Result<String, String> {
+    match key_storage.get(API_KEY_ENTRY) {
+        Ok(Some(password)) if password.trim().is_empty() => Ok(String::new()),
+        Ok(Some(password)) => Ok(key_preview(&password)),
+        Ok(None) => Ok(String::new()),
+        Err(e) => {
+            warn!("Key storage error: {}", e);
+            Err(format!("Failed to retrieve API key: {}", e))
+        },
+    }
+}
+
+/// Whether API keys are kept in the OS keychain or, when none is available,
+/// in an encrypted file
+#[tauri::command]
+async fn get_key_storage_backend(key_storage: State<'_, KeyStorage>) -> Result<KeyStorageBackend, String> {
+    // Touch the storage so an unavailable keychain is detected before reporting
+    key_storage.get(API_KEY_ENTRY)?;
+    Ok(key_storage.backend())
+}
+
+fn key_preview(key: &str) -> String {
+    if key.len() > 4 {
+        format!("****{}", &key[key.len()-4..])
+    } else {
+        "****".to_string()
+    }
+}
+
+fn load_api_keys(key_storage: &KeyStorage) -> Result<Vec<String>, String> {
+    match key_storage.get(API_KEYS_ENTRY) {
+        Ok(Some(json)) => serde_json::from_str(&json)
+            .map_err(|e| format!("Failed to parse stored API keys: {}", e)),
+        Ok(None) => Ok(Vec::new()),
+        Err(e) => Err(format!("Failed to retrieve API keys: {}", e)),
+    }
+}
+
+fn store_api_keys(key_storage: &KeyStorage, keys: &[String]) -> Result<(), String> {
+    let json = serde_json::to_string(keys)
+        .map_err(|e| format!("Failed to serialize API keys: {}", e))?;
+    key_storage.set(API_KEYS_ENTRY, &json)
+        .map_err(|e| format!("Failed to store API keys: {}", e))
+}
+
+/// The endpoint set with `set_base_url`, or the public Gemini API
+fn load_base_url(app: &AppHandle) -> String {
+    let stored = app
+        .store(SETTINGS_STORE)
+        .ok()
+        .and_then(|store| store.get(BASE_URL_SETTING))
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default();
+    normalize_base_url(&stored)
+}
+
+/// The mode set with `set_auth_mode`, API keys by default
+fn load_auth_mode(app: &AppHandle) -> AuthMode {
+    app.store(SETTINGS_STORE)
+        .ok()
+        .and_then(|store| store.get(AUTH_SETTING))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+/// Whether a request can be authenticated: with a service account the API
+/// key is not needed
+fn has_credentials(app: &AppHandle, api_key: &str) -> bool {
+    !api_key.trim().is_empty() || matches!(load_auth_mode(app), AuthMode::ServiceAccount { .. })
+}
+
+#[tauri::command]
+async fn get_auth_mode(app: AppHandle) -> Result<AuthMode, String> {
+    Ok(load_auth_mode(&app))
+}
+
+/// Switches between the Gemini API with API keys and Vertex AI with a
+/// service account key file
+#[tauri::command]
+async fn set_auth_mode(app: AppHandle, mode: AuthMode) -> Result<AuthMode, String> {
+    mode.validate()?;
+    let store = app
+        .store(SETTINGS_STORE)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    let value = serde_json::to_value(&mode).map_err(|e| format!("Failed to serialize auth mode: {}", e))?;
+    store.set(AUTH_SETTING, value);
+    store.save().map_err(|e| format!("Failed to save settings: {}", e))?;
+    Ok(mode)
+}
+
+#[tauri::command]
+async fn get_base_url(app: AppHandle) -> Result<String, String> {
+    Ok(load_base_url(&app))
+}
+
+/// Stores a custom Gemini endpoint; an empty value restores the default
+#[tauri::command]
+async fn set_base_url(app: AppHandle, base_url: String) -> Result<String, String> {
+    let base_url = normalize_base_url(&base_url);
+    if !base_url.starts_with("https://") && !base_url.starts_with("http://") {
+        return Err("Base URL must start with http:// or https://".to_string());
+    }
+
+    let store = app
+        .store(SETTINGS_STORE)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    store.set(BASE_URL_SETTING, base_url.clone());
+    store.save().map_err(|e| format!("Failed to save settings: {}", e))?;
+    Ok(base_url)
+}
+
+/// The directory set with `set_default_output_dir`, if any
+fn load_output_dir(app: &AppHandle) -> Option<String> {
+    app.store(SETTINGS_STORE)
+        .ok()
+        .and_then(|store| store.get(OUTPUT_DIR_SETTING))
+        .and_then(|value| value.as_str().map(str::to_string))
+        .filter(|dir| !dir.is_empty())
+}
+
+#[tauri::command]
+async fn get_default_output_dir(app: AppHandle) -> Result<Option<String>, String> {
+    Ok(load_output_dir(&app))
+}
+
+/// Sets the directory `save_srt_file` and `save_dictionary_csv` write to; an
+/// empty value restores the Downloads folder
+#[tauri::command]
+async fn set_default_output_dir(app: AppHandle, dir: String) -> Result<Option<String>, String> {
+    let store = app
+        .store(SETTINGS_STORE)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    let dir = dir.trim();
+    if dir.is_empty() {
+        store.delete(OUTPUT_DIR_SETTING);
+    } else {
+        validate_output_dir(Path::new(dir))?;
+        store.set(OUTPUT_DIR_SETTING, dir);
+    }
+    store.save().map_err(|e| format!("Failed to save settings: {}", e))?;
+    Ok(load_output_dir(&app))
+}
+
+/// The proxy URL set with `set_proxy`, if any
+fn load_proxy_url(app: &AppHandle) -> Option<String> {
+    app.store(SETTINGS_STORE)
+        .ok()
+        .and_then(|store| store.get(PROXY_SETTING))
+        .and_then(|value| value.as_str().map(str::to_string))
+        .filter(|url| !url.is_empty())
+}
+
+#[tauri::command]
+async fn get_proxy(app: AppHandle) -> Result<Option<String>, String> {
+    Ok(load_proxy_url(&app))
+}
+
+/// Sets the proxy Gemini requests go through; an empty value goes back to
+/// the `HTTPS_PROXY`/`NO_PROXY` environment variables
+#[tauri::command]
+async fn set_proxy(app: AppHandle, proxy_url: String) -> Result<Option<String>, String> {
+    let store = app
+        .store(SETTINGS_STORE)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    let proxy_url = proxy_url.trim();
+    if proxy_url.is_empty() {
+        store.delete(PROXY_SETTING);
+    } else {
+        parse_proxy(proxy_url)?;
+        store.set(PROXY_SETTING, proxy_url);
+    }
+    store.save().map_err(|e| format!("Failed to save settings: {}", e))?;
+    Ok(load_proxy_url(&app))
+}
+
+/// The proxy username set with `set_proxy_credentials`, if any
+fn load_proxy_username(app: &AppHandle) -> Option<String> {
+    app.store(SETTINGS_STORE)
+        .ok()
+        .and_then(|store| store.get(PROXY_USERNAME_SETTING))
+        .and_then(|value| value.as_str().map(str::to_string))
+        .filter(|username| !username.is_empty())
+}
+
+/// The proxy from `set_proxy` with the credentials from
+/// `set_proxy_credentials`; an invalid URL is ignored
+fn load_proxy(app: &AppHandle) -> Option<Proxy> {
+    let proxy_url = load_proxy_url(app)?;
+    let username = load_proxy_username(app);
+    let password = username.as_ref().and_then(|_| {
+        app.state::<KeyStorage>().get(PROXY_PASSWORD_ENTRY).unwrap_or_else(|e| {
+            warn!("Could not load proxy password: {}", e);
+            None
+        })
+    });
+    configured_proxy(&proxy_url, username.as_deref(), password.as_deref())
+}
+
+/// Proxy for `proxy_url`, with basic authentication when `username` is set;
+/// an invalid URL is logged and ignored
+fn configured_proxy(proxy_url: &str, username: Option<&str>, password: Option<&str>) -> Option<Proxy> {
+    let proxy = match parse_proxy(proxy_url) {
+        Ok(proxy) => proxy,
+        Err(e) => {
+            warn!("Ignoring proxy setting: {}", e);
+            return None;
+        }
+    };
+    match username {
+        Some(username) => Some(proxy.basic_auth(username, password.unwrap_or(""))),
+        None => Some(proxy),
+    }
+}
+
+#[tauri::command]
+async fn get_proxy_username(app: AppHandle) -> Result<Option<String>, String> {
+    Ok(load_proxy_username(&app))
+}
+
+/// Sets the credentials sent to the proxy with basic authentication; an
+/// empty username removes them
+#[tauri::command]
+async fn set_proxy_credentials(
+    app: AppHandle,
+    key_storage: State<'_, KeyStorage>,
+    username: String,
+    password: String,
+) -> Result<Option<String>, String> {
+    let store = app
+        .store(SETTINGS_STORE)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    let username = username.trim();
+    if username.is_empty() {
+        store.delete(PROXY_USERNAME_SETTING);
+        key_storage.delete(PROXY_PASSWORD_ENTRY)?;
+    } else {
+        key_storage.set(PROXY_PASSWORD_ENTRY, &password)?;
+        store.set(PROXY_USERNAME_SETTING, username);
+    }
+    store.save().map_err(|e| format!("Failed to save settings: {}", e))?;
+    Ok(load_proxy_username(&app))
+}
+
+/// The PEM file set with `set_ca_certificate_path`, if any
+fn load_ca_certificate_path(app: &AppHandle) -> Option<String> {
+    app.store(SETTINGS_STORE)
+        .ok()
+        .and_then(|store| store.get(CA_CERTIFICATE_SETTING))
+        .and_then(|value| value.as_str().map(str::to_string))
+        .filter(|path| !path.is_empty())
+}
+
+#[tauri::command]
+async fn get_ca_certificate_path(app: AppHandle) -> Result<Option<String>, String> {
+    Ok(load_ca_certificate_path(&app))
+}
+
+/// Sets a PEM file of root certificates to trust in addition to the system
+/// ones, e.g. for a proxy that inspects TLS; an empty path removes it
+#[tauri::command]
+async fn set_ca_certificate_path(app: AppHandle, path: String) -> Result<Option<String>, String> {
+    let store = app
+        .store(SETTINGS_STORE)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    let path = path.trim();
+    if path.is_empty() {
+        store.delete(CA_CERTIFICATE_SETTING);
+    } else {
+        load_root_certificates(Path::new(path))?;
+        store.set(CA_CERTIFICATE_SETTING, path);
+    }
+    store.save().map_err(|e| format!("Failed to save settings: {}", e))?;
+    Ok(load_ca_certificate_path(&app))
+}
+
+/// The thresholds saved with `set_safety_settings`, or `BLOCK_NONE` for every
+/// category; an empty list means Gemini's defaults
+fn load_safety_settings(app: &AppHandle) -> Vec<SafetySetting> {
+    app.store(SETTINGS_STORE)
+        .ok()
+        .and_then(|store| store.get(SAFETY_SETTING))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_else(default_safety_settings)
+}
+
+#[tauri::command]
+async fn get_safety_settings(app: AppHandle) -> Result<Vec<SafetySetting>, String> {
+    Ok(load_safety_settings(&app))
+}
+
+/// Sets the blocking threshold per harm category sent with every generation,
+/// e.g. `BLOCK_MEDIUM_AND_ABOVE` for `HARM_CATEGORY_DANGEROUS_CONTENT`; an
+/// empty list sends none, so Gemini's defaults apply
+#[tauri::command]
+async fn set_safety_settings(app: AppHandle, settings: Vec<SafetySetting>) -> Result<Vec<SafetySetting>, String> {
+    for (i, setting) in settings.iter().enumerate() {
+        setting.validate()?;
+        if settings[..i].iter().any(|other| other.category == setting.category) {
+            return Err(format!("Duplicate safety setting for {}", setting.category));
+        }
+    }
+
+    let store = app
+        .store(SETTINGS_STORE)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    let value = serde_json::to_value(&settings).map_err(|e| format!("Failed to serialize safety settings: {}", e))?;
+    store.set(SAFETY_SETTING, value);
+    store.save().map_err(|e| format!("Failed to save settings: {}", e))?;
+    Ok(settings)
+}
+
+/// The overrides saved with `set_generation_settings`, or none
+fn load_generation_settings(app: &AppHandle) -> GenerationSettings {
+    app.store(SETTINGS_STORE)
+        .ok()
+        .and_then(|store| store.get(GENERATION_SETTING))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+async fn get_generation_settings(app: AppHandle) -> Result<GenerationSettings, String> {
+    Ok(load_generation_settings(&app))
+}
+
+/// Overrides the temperature, topP, topK and maxOutputTokens used for
+/// transcription and for dictionary creation; unset fields keep the per-task
+/// defaults
+#[tauri::command]
+async fn set_generation_settings(app: AppHandle, settings: GenerationSettings) -> Result<GenerationSettings, String> {
+    settings.validate()?;
+
+    let store = app
+        .store(SETTINGS_STORE)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    let value = serde_json::to_value(&settings).map_err(|e| format!("Failed to serialize generation settings: {}", e))?;
+    store.set(GENERATION_SETTING, value);
+    store.save().map_err(|e| format!("Failed to save settings: {}", e))?;
+    Ok(settings)
+}
+
+/// Filler word lists saved with `set_filler_words`, keyed by language code
+fn load_filler_word_lists(app: &AppHandle) -> HashMap<String, Vec<String>> {
+    app.store(SETTINGS_STORE)
+        .ok()
+        .and_then(|store| store.get(FILLER_WORDS_SETTING))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+/// `filler_words` when given, otherwise the list saved for `language`;
+/// `None` leaves the language's defaults
+fn resolve_filler_words(app: &AppHandle, language: &TranscriptionLanguage, filler_words: Option<Vec<String>>) -> Option<Vec<String>> {
+    filler_words
+        .map(clean_filler_words)
+        .filter(|words| !words.is_empty())
+        .or_else(|| load_filler_word_lists(app).remove(language.code()))
+}
+
+/// [`resolve_filler_words`], falling back to the language's defaults
+fn filler_words_or_defaults(app: &AppHandle, language: &TranscriptionLanguage, filler_words: Option<Vec<String>>) -> Vec<String> {
+    resolve_filler_words(app, language, filler_words)
+        .unwrap_or_else(|| language.default_filler_words().iter().map(|word| word.to_string()).collect())
+}
+
+/// Trimmed, without blanks and duplicates, in the given order
+fn clean_filler_words(words: Vec<String>) -> Vec<String> {
+    let mut cleaned: Vec<String> = Vec::with_capacity(words.len());
+    for word in words {
+        let word = word.trim();
+        if !word.is_empty() && !cleaned.iter().any(|known| known == word) {
+            cleaned.push(word.to_string());
+        }
+    }
+    cleaned
+}
+
+/// The filler words named in prompts for `language`: the saved list, or the
+/// language's defaults
+#[tauri::command]
+async fn get_filler_words(app: AppHandle, language: String) -> Result<Vec<String>, String> {
+    let language = TranscriptionLanguage::from_code(Some(&language));
+    Ok(filler_words_or_defaults(&app, &language, None))
+}
+
+/// Saves the filler words for `language`; an empty or missing list restores
+/// the defaults
+#[tauri::command]
+async fn set_filler_words(app: AppHandle, language: String, filler_words: Option<Vec<String>>) -> Result<Vec<String>, String> {
+    let language = TranscriptionLanguage::from_code(Some(&language));
+    let filler_words = clean_filler_words(filler_words.unwrap_or_default());
+
+    let store = app
+        .store(SETTINGS_STORE)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    let mut lists = load_filler_word_lists(&app);
+    if filler_words.is_empty() {
+        lists.remove(language.code());
+    } else {
+        lists.insert(language.code().to_string(), filler_words);
+    }
+    let value = serde_json::to_value(&lists).map_err(|e| format!("Failed to serialize filler words: {}", e))?;
+    store.set(FILLER_WORDS_SETTING, value);
+    store.save().map_err(|e| format!("Failed to save settings: {}", e))?;
+    get_filler_words(app, language.code().to_string()).await
+}
+
+/// `base` with the fields set in `overrides`, a command's own
+/// `generation_config` parameter, replaced
+fn with_overrides(base: GenerationConfig, overrides: Option<GenerationConfig>) -> Result<GenerationConfig, String> {
+    let config = base.merged(overrides.unwrap_or_default());
+    config.validate()?;
+    Ok(config)
+}
+
+/// The limits saved with `set_concurrency_limits`, or the defaults
+fn load_concurrency_limits(app: &AppHandle) -> ConcurrencyLimits {
+    app.store(SETTINGS_STORE)
+        .ok()
+        .and_then(|store| store.get(CONCURRENCY_SETTING))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+async fn get_concurrency_limits(app: AppHandle) -> Result<ConcurrencyLimits, String> {
+    Ok(load_concurrency_limits(&app))
+}
+
+/// Sets how many generations and uploads may run at once; requests beyond
+/// that wait for a free slot. The new limits apply right away: a raised limit
+/// lets waiting requests start, and after a lowered one requests already
+/// running finish before new ones start.
+#[tauri::command]
+async fn set_concurrency_limits(
+    app: AppHandle,
+    client_cache: State<'_, ClientCache>,
+    limits: ConcurrencyLimits,
+) -> Result<ConcurrencyLimits, String> {
+    limits.validate()?;
+
+    let store = app
+        .store(SETTINGS_STORE)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    let value = serde_json::to_value(limits).map_err(|e| format!("Failed to serialize concurrency limits: {}", e))?;
+    store.set(CONCURRENCY_SETTING, value);
+    store.save().map_err(|e| format!("Failed to save settings: {}", e))?;
+    client_cache.resize_slots(limits);
+    Ok(limits)
+}
+
+/// The settings saved with `set_retry_settings`, or the defaults
+fn load_retry_settings(app: &AppHandle) -> RetrySettings {
+    app.store(SETTINGS_STORE)
+        .ok()
+        .and_then(|store| store.get(RETRY_SETTING))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+async fn get_retry_settings(app: AppHandle) -> Result<RetrySettings, String> {
+    Ok(load_retry_settings(&app))
+}
+
+/// Sets how often and how patiently Gemini requests failing with 429 or 5xx
+/// are retried; `max_attempts` of 1 disables retries
+#[tauri::command]
+async fn set_retry_settings(app: AppHandle, settings: RetrySettings) -> Result<RetrySettings, String> {
+    if !(1..=MAX_RETRY_ATTEMPTS_LIMIT).contains(&settings.max_attempts) {
+        return Err(format!("Retry attempts must be between 1 and {}", MAX_RETRY_ATTEMPTS_LIMIT));
+    }
+    if settings.max_delay_ms > MAX_RETRY_DELAY_LIMIT_MS {
+        return Err(format!("Maximum retry delay must be at most {} ms", MAX_RETRY_DELAY_LIMIT_MS));
+    }
+    if settings.base_delay_ms > settings.max_delay_ms {
+        return Err("Base retry delay must not exceed the maximum delay".to_string());
+    }
+
+    let store = app
+        .store(SETTINGS_STORE)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    let value = serde_json::to_value(settings).map_err(|e| format!("Failed to serialize retry settings: {}", e))?;
+    store.set(RETRY_SETTING, value);
+    store.save().map_err(|e| format!("Failed to save settings: {}", e))?;
+    Ok(settings)
+}
+
+/// The settings saved with `set_timeout_settings`, or the defaults
+fn load_timeout_settings(app: &AppHandle) -> TimeoutSettings {
+    app.store(SETTINGS_STORE)
+        .ok()
+        .and_then(|store| store.get(TIMEOUT_SETTING))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+async fn get_timeout_settings(app: AppHandle) -> Result<TimeoutSettings, String> {
+    Ok(load_timeout_settings(&app))
+}
+
+/// Sets the connect timeout and the overall limits for Gemini requests, with
+/// a separate limit for generations with Pro models, a shorter one for file
+/// status polls and an optional one for uploaded files to finish processing
+#[tauri::command]
+async fn set_timeout_settings(app: AppHandle, settings: TimeoutSettings) -> Result<TimeoutSettings, String> {
+    let timeouts = [
+        settings.connect_timeout_secs,
+        settings.request_timeout_secs,
+        settings.generation_timeout_secs,
+        settings.pro_generation_timeout_secs,
+        settings.poll_timeout_secs,
+        settings.processing_timeout_secs.unwrap_or(*TIMEOUT_LIMITS_SECS.start()),
+    ];
+    if !timeouts.iter().all(|secs| TIMEOUT_LIMITS_SECS.contains(secs)) {
+        return Err(format!(
+            "Timeouts must be between {} and {} seconds",
+            TIMEOUT_LIMITS_SECS.start(),
+            TIMEOUT_LIMITS_SECS.end()
+        ));
+    }
+
+    let store = app
+        .store(SETTINGS_STORE)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    let value = serde_json::to_value(settings).map_err(|e| format!("Failed to serialize timeout settings: {}", e))?;
+    store.set(TIMEOUT_SETTING, value);
+    store.save().map_err(|e| format!("Failed to save settings: {}", e))?;
+    Ok(settings)
+}
+
+/// The level saved with `set_log_level`, or `info`
+fn load_log_level(app: &AppHandle) -> LogLevel {
+    app.store(SETTINGS_STORE)
+        .ok()
+        .and_then(|store| store.get(LOG_LEVEL_SETTING))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+/// Where the log is written; rotated files sit next to it as `.1`, `.2`
+/// and `.3`
+#[tauri::command]
+async fn get_log_file_path(log: State<'_, LogHandle>) -> Result<String, String> {
+    Ok(log.path().to_string_lossy().into_owned())
+}
+
+#[tauri::command]
+async fn get_log_level(log: State<'_, LogHandle>) -> Result<LogLevel, String> {
+    Ok(log.level())
+}
+
+/// Sets the most verbose level written to the log file, effective immediately.
+/// `trace` includes full API responses, with keys and file URIs redacted.
+#[tauri::command]
+async fn set_log_level(app: AppHandle, log: State<'_, LogHandle>, level: LogLevel) -> Result<LogLevel, String> {
+    let store = app
+        .store(SETTINGS_STORE)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    let value = serde_json::to_value(level).map_err(|e| format!("Failed to serialize log level: {}", e))?;
+    store.set(LOG_LEVEL_SETTING, value);
+    store.save().map_err(|e| format!("Failed to save settings: {}", e))?;
+    log.set_level(level);
+    info!("Log level set to {:?}", level);
+    Ok(level)
+}
+
+/// Certificates from the file saved with `set_ca_certificate_path`; an
+/// unreadable file is logged and ignored
+fn load_root_certificate_setting(app: &AppHandle) -> Vec<Certificate> {
+    root_certificates_at(load_ca_certificate_path(app).as_deref())
+}
+
+/// Certificates from the file at `path`; an unreadable file is logged and
+/// ignored
+fn root_certificates_at(path: Option<&str>) -> Vec<Certificate> {
+    path
+        .and_then(|path| match load_root_certificates(Path::new(path)) {
+            Ok(certificates) => Some(certificates),
+            Err(e) => {
+                warn!("Ignoring CA certificate setting: {}", e);
+                None
+            }
+        })
+        .unwrap_or_default()
+}
+
+/// Everything [`build_client`] configures the client with. The cached client
+/// is rebuilt when any of it changes.
+#[derive(PartialEq)]
+struct ClientSettings {
+    api_keys: Vec<String>,
+    base_url: String,
+    auth: AuthMode,
+    timeouts: TimeoutSettings,
+    proxy_url: Option<String>,
+    proxy_username: Option<String>,
+    proxy_password: Option<String>,
+    ca_certificate_path: Option<String>,
+    safety_settings: Vec<SafetySetting>,
+    retry_settings: RetrySettings,
+    concurrency_limits: ConcurrencyLimits,
+}
+
+impl ClientSettings {
+    fn load(app: &AppHandle, api_key: String) -> Self {
+        let mut api_keys = vec![api_key];
+        match load_api_keys(&app.state::<KeyStorage>()) {
+            Ok(pool) => {
+                for key in pool {
+                    if !api_keys.contains(&key) {
+                        api_keys.push(key);
+                    }
+                }
+            }
+            Err(e) => warn!("Could not load API key pool: {}", e),
+        }
+        Self {
+            api_keys,
+            base_url: load_base_url(app),
+            auth: load_auth_mode(app),
+            timeouts: load_timeout_settings(app),
+            proxy_url: load_proxy_url(app),
+            proxy_username: load_proxy_username(app),
+            proxy_password: app.state::<KeyStorage>().get(PROXY_PASSWORD_ENTRY).ok().flatten(),
+            ca_certificate_path: load_ca_certificate_path(app),
+            safety_settings: load_safety_settings(app),
+            retry_settings: load_retry_settings(app),
+            concurrency_limits: load_concurrency_limits(app),
+        }
+    }
+
+    /// Builds the client from these settings alone, limited by the shared `slots`
+    fn build(&self, app: &AppHandle, slots: Arc<RequestSlots>) -> GeminiClient {
+        let keys = self.api_keys.clone();
+        let client = match &self.auth {
+            AuthMode::ApiKey => GeminiClient::with_base_url(keys, &self.base_url),
+            AuthMode::ServiceAccount { key_file, location, project_id } => {
+                let base_url = if self.base_url == DEFAULT_BASE_URL { vertex_base_url(location) } else { self.base_url.clone() };
+                let client = GeminiClient::with_base_url(keys, &base_url);
+                match ServiceAccountAuth::load(Path::new(key_file)) {
+                    Ok(auth) => client.with_service_account(auth, location, project_id.clone()),
+                    Err(e) => {
+                        warn!("Ignoring service account setting: {}", e);
+                        client
+                    }
+                }
+            }
+        };
+        let emitter = app.clone();
+        let slot_emitter = app.clone();
+        client
+            .with_timeouts(self.timeouts)
+            .with_root_certificates(root_certificates_at(self.ca_certificate_path.as_deref()))
+            .with_proxy(self.proxy_url.as_deref().and_then(|proxy_url| {
+                configured_proxy(proxy_url, self.proxy_username.as_deref(), self.proxy_password.as_deref())
+            }))
+            .with_safety_settings(self.safety_settings.clone())
+            .with_retry_settings(self.retry_settings)
+            .with_request_slots(slots)
+            .on_retry(Arc::new(move |event: &RetryEvent| {
+                let _ = emitter.emit("gemini-retry", event);
+            }))
+            .on_slot_wait(Arc::new(move |kind| {
+                let _ = slot_emitter.emit("gemini-queued", kind);
+            }))
+    }
+}
+
+/// The configured client shared by all commands, kept in Tauri managed state
+/// so that they reuse its connection pool and request slots
+#[derive(Default)]
+struct ClientCache {
+    cached: Mutex<Option<(ClientSettings, GeminiClient)>>,
+    /// Created with the first client and resized when the limits change, so
+    /// requests still running on a replaced client count against them too
+    slots: OnceLock<Arc<RequestSlots>>,
+}
+
+impl ClientCache {
+    /// The shared request slots, set to `limits`
+    fn resize_slots(&self, limits: ConcurrencyLimits) -> Arc<RequestSlots> {
+        let slots = self.slots.get_or_init(|| Arc::new(RequestSlots::new(limits)));
+        slots.resize(limits);
+        slots.clone()
+    }
+}
+
+/// Builds a client that uses `api_key` first and rotates through the
+/// additional keys stored with `add_api_key` when a quota is exceeded.
+/// With a service account from `set_auth_mode` requests go to Vertex AI
+/// instead, at its regional endpoint unless a base URL is set. Requests go
+/// to the endpoint configured with `set_base_url` through the
+/// proxy from `set_proxy`, trusting the certificates from
+/// `set_ca_certificate_path`, with the limits from `set_timeout_settings` and
+/// the thresholds from `set_safety_settings`, and each retry is reported through a
+/// `gemini-retry` event.
+///
+/// Returns a clone of the shared client, built anew when the key or a client
+/// setting changed since the last command
+fn build_client(app: &AppHandle, api_key: String) -> GeminiClient {
+    let settings = ClientSettings::load(app, api_key);
+    let cache = app.state::<ClientCache>();
+    let mut cached = cache.cached.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    match cached.as_ref() {
+        Some((current, client)) if *current == settings => client.clone(),
+        _ => {
+            let slots = cache.resize_slots(settings.concurrency_limits);
+            let client = settings.build(app, slots);
+            *cached = Some((settings, client.clone()));
+            client
+        }
+    }
+}
+
+#[tauri::command]
+async fn add_api_key(key_storage: State<'_, KeyStorage>, api_key: String) -> Result<bool, String> {
+    let api_key = normalize_api_key(&api_key)?;
+    
+    let mut keys = load_api_keys(&key_storage)?;
+    if !keys.contains(&api_key) {
+        keys.push(api_key);
+        store_api_keys(&key_storage, &keys)?;
+    }
+    Ok(true)
+}
+
+#[tauri::command]
+async fn list_api_keys(key_storage: State<'_, KeyStorage>) -> Result<Vec<String>, String> {
+    Ok(load_api_keys(&key_storage)?.iter().map(|key| key_preview(key)).collect())
+}
+
+#[tauri::command]
+async fn remove_api_key(key_storage: State<'_, KeyStorage>, index: usize) -> Result<bool, String> {
+    let mut keys = load_api_keys(&key_storage)?;
+    if index >= keys.len() {
+        return Err(format!("No API key at position {}", index));
+    }
+    keys.remove(index);
+    store_api_keys(&key_storage, &keys)?;
+    Ok(true)
+}
+
+#[tauri::command]
+async fn debug_keyring(key_storage: State<'_, KeyStorage>) -> Result<String, String> {
+    match key_storage.get(API_KEY_ENTRY) {
+        Ok(Some(password)) => {
+            Ok(format!("Found API key in {:?}, length: {}, ending in {}",
+                key_storage.backend(),
+                password.len(),
+                key_preview(&password)))
+        },
+        Ok(None) => Ok(format!("No API key found in {:?}", key_storage.backend())),
+        Err(e) => Err(format!("Key storage error: {}", e)),
+    }
+}
+
+/// Returns the current text of a prompt template, with its `{placeholders}`
+#[tauri::command]
+fn get_prompt_template(prompt_templates: State<'_, PromptTemplates>, name: String) -> Result<String, String> {
+    Ok(prompt_templates.get(PromptTemplate::from_name(&name)?))
+}
+
+/// Overrides a prompt template; empty content restores the built-in one
+#[tauri::command]
+fn set_prompt_template(prompt_templates: State<'_, PromptTemplates>, name: String, content: String) -> Result<(), String> {
+    prompt_templates.set(PromptTemplate::from_name(&name)?, &content)
+}
+
+/// Returns the prompt `transcribe_audio` would send with the same settings,
+/// system instruction first, without calling the API
+#[tauri::command]
+fn preview_transcription_prompt(app: AppHandle, options: TranscriptionOptions) -> String {
+    let selected_model = options.model_or("gemini-2.0-flash");
+    options.prompt(&app, &selected_model, options.duration_ms).combined()
+}
+
+/// Model used by `detect_language` unless another one is given
+const LANGUAGE_DETECTION_MODEL: &str = "gemini-2.0-flash-lite";
+
+/// Uploads `file_path`, or reuses an earlier upload, and asks a cheap model
+/// which language is spoken, e.g. to pick the `language` for
+/// `transcribe_audio`
+#[tauri::command]
+async fn detect_language(
+    app: AppHandle,
+    upload_cache: State<'_, UploadCache>,
+    file_path: String,
+    model: Option<String>,
+    mime_override: Option<String>,
+    api_key: String,
+) -> Result<LanguageResult, AppError> {
+    if !has_credentials(&app, &api_key) {
+        return Err("API key is empty. Please set your Gemini API key in settings.".into());
+    }
+    if !Path::new(&file_path).exists() {
+        return Err("Audio file not found".into());
+    }
+
+    let mime_type = resolve_mime_type(&file_path, mime_override.as_deref())?;
+    let generation_config = GenerationConfig {
+        temperature: Some(0.0),
+        max_output_tokens: Some(64),
+        ..GenerationConfig::default()
+    }
+    .with_response_schema(DetectedLanguage::schema());
+    let progress = ProgressReporter::new(&app, None);
+    let client = build_client(&app, api_key).with_generation_config(generation_config).on_slot_wait(progress.slot_wait());
+    let upload = upload_or_reuse(&client, &upload_cache, &file_path, &mime_type, &progress, &CancellationToken::new()).await?;
+
+    let selected_model = model.unwrap_or_else(|| LANGUAGE_DETECTION_MODEL.to_string());
+    let generation = client
+        .generate_content(&upload.file.uri, &upload.file.mime_type, DETECT_LANGUAGE_PROMPT, &selected_model)
+        .await
+        .map_err(|e| e.context("Failed to detect language"))?;
+    track_usage(&app, &selected_model, generation.usage.as_ref());
+    let detected: DetectedLanguage = serde_json::from_str(&generation.text)
+        .map_err(|e| format!("Language detection returned invalid JSON: {}", e))?;
+    Ok(LanguageResult::try_from(detected)?)
+}
+
+/// Uploads `file_path`, or reuses an earlier upload, and counts the tokens
+/// `transcribe_audio` would send with the same settings, with the expected
+/// output and price
+#[tauri::command]
+async fn estimate_transcription_cost(
+    app: AppHandle,
+    upload_cache: State<'_, UploadCache>,
+    file_path: String,
+    options: TranscriptionOptions,
+    api_key: String,
+) -> Result<CostEstimate, AppError> {
+    if !has_credentials(&app, &api_key) {
+        return Err("API key is empty. Please set your Gemini API key in settings.".into());
+    }
+    if !Path::new(&file_path).exists() {
+        return Err("Audio file not found".into());
+    }
+
+    let mime_type = resolve_mime_type(&file_path, options.mime_override.as_deref())?;
+    let duration_ms = options.resolve_duration_ms(&file_path);
+    let progress = ProgressReporter::new(&app, None);
+    let client = build_client(&app, api_key).on_slot_wait(progress.slot_wait());
+    let upload = upload_or_reuse(&client, &upload_cache, &file_path, &mime_type, &progress, &CancellationToken::new()).await?;
+
+    let selected_model = options.model_or("gemini-2.0-flash");
+    let prompt = options.prompt(&app, &selected_model, duration_ms);
+    let token_count = client
+        .with_system_instruction(prompt.system_instruction)
+        .count_tokens(&upload.file.uri, &upload.file.mime_type, &prompt.text, &selected_model)
+        .await
+        .map_err(|e| e.context("Failed to count tokens"))?;
+    Ok(CostEstimate::from_token_count(&selected_model, &token_count))
+}
+
+#[tauri::command]
+async fn transcribe_audio(
+    app: AppHandle,
+    file_path: String,
+    options: TranscriptionOptions,
+    job_id: Option<String>,
+    api_key: String
+) -> Result<TranscriptionResult, AppError> {
+    let upload_cache = app.state::<UploadCache>();
+    let transcription_lock = app.state::<TranscriptionLock>();
+    // Held until the function returns, so a second click fails fast instead of uploading again
+    let running = transcription_lock.try_acquire()?;
+    let cancel = running.cancellation();
+
+    if !has_credentials(&app, &api_key) {
+        return Err("API key is empty. Please set your Gemini API key in settings.".into());
+    }
+
+    // Validate file exists
+    if !Path::new(&file_path).exists() {
+        return Err("Audio file not found".into());
+    }
+
+    // Resolve MIME type from the override or the file extension
+    let mime_type = resolve_mime_type(&file_path, options.mime_override.as_deref())?;
+
+    // Read the duration from the file when the frontend could not provide it
+    let duration_ms = options.resolve_duration_ms(&file_path);
+
+    // Create Gemini client; transcription defaults to temperature 0
+    let generation_config = with_overrides(load_generation_settings(&app).transcription(), options.generation_config.clone())?;
+    let progress = ProgressReporter::new(&app, job_id);
+    let client = build_client(&app, api_key).with_generation_config(generation_config).on_slot_wait(progress.slot_wait());
+
+    // Upload file to Gemini Files API, reusing an earlier upload of the same content
+    let upload = upload_or_reuse(&client, &upload_cache, &file_path, &mime_type, &progress, &cancel).await?;
+
+    // Use provided model or default to gemini-2.0-flash
+    let selected_model = options.model_or("gemini-2.0-flash");
+
+    // Generate prompt based on model type
+    let format = ResponseFormat {
+        first_block_only: options.first_block_only,
+        srt_output: is_srt_model(&selected_model),
+    };
+    let prompt = options.prompt(&app, &selected_model, duration_ms);
+    let client = client.with_system_instruction(prompt.system_instruction);
+
+    // Generate transcription
+    progress.report(ProgressStage::Generating, None, selected_model.as_str());
+    let on_chunk = options.stream.then(|| progress.stream_chunks());
+    let generated = cancellable(
+        &cancel,
+        &client,
+        &upload_cache,
+        &upload,
+        generate_srt(&client, &upload.file, &prompt.text, &selected_model, format, &progress, on_chunk),
+    )
+    .await?;
+    if options.delete_upload {
+        discard_upload(&client, &upload_cache, &upload).await;
+    }
+    let mut warnings = Vec::new();
+    if generated.truncated {
+        warnings.push("The transcription was cut off by the output token limit and is likely incomplete. Splitting the audio into shorter files should help.".to_string());
+    }
+
+    progress.report(ProgressStage::Finalizing, None, "");
+    let (transcription, duration_adjustments) = post_process_transcription(&app, &options, normalize_srt(&generated.srt), &mut warnings);
+
+    // Make the result available to the preview player
+    if let Ok(cues) = parse_srt(&transcription) {
+        app.state::<SrtIndex>().load(cues);
+    }
+
+    let usage = track_usage(&app, &selected_model, generated.usage.as_ref());
+
+    record_history(&app.state::<HistoryStore>(), NewHistoryEntry {
+        source_filename: source_file_name(&file_path).as_deref(),
+        model: &selected_model,
+        max_chars_per_subtitle: options.max_chars_per_subtitle,
+        srt: &transcription,
+        prompt_tokens: usage.as_ref().map(|usage| usage.prompt_tokens as i64),
+        output_tokens: usage.as_ref().map(|usage| usage.output_tokens as i64),
+    });
+    let session_id = start_session(
+        &app.state::<SessionStore>(),
+        &file_path,
+        &selected_model,
+        options.max_chars_per_subtitle,
+        options.enable_speaker_detection,
+        duration_ms,
+        &transcription,
+    );
+
+    progress.report(ProgressStage::Done, Some(100), "");
+    Ok(TranscriptionResult {
+        srt: transcription,
+        usage,
+        warnings,
+        duration_adjustments,
+        continuations: generated.continuations,
+        session_id,
+    })
+}
+
+/// Applies the clean-up steps selected in `options` to a generated
+/// transcription: collapsing repeated cues, sanitizing markup, removing
+/// fillers, enforcing duration limits and wrapping lines
+fn post_process_transcription(
+    app: &AppHandle,
+    options: &TranscriptionOptions,
+    mut transcription: String,
+    warnings: &mut Vec<String>,
+) -> (String, Option<DurationAdjustments>) {
+    // Collapse runs of identical cues produced when the model loops
+    if let Ok(cues) = parse_srt(&transcription) {
+        let (cues, collapse_warnings) = collapse_repeated_cues(cues, options.repeat_collapse_threshold.unwrap_or(MIN_REPEAT_RUN));
+        if !collapse_warnings.is_empty() {
+            transcription = serialize_srt(&cues);
+            warnings.extend(collapse_warnings);
+        }
+    }
+
+    // Plain-text transcriptions do not parse as SRT and are left untouched
+    if !options.keep_markup {
+        if let Ok(sanitized) = sanitize_markup(&transcription, &DEFAULT_ALLOWED_TAGS) {
+            transcription = sanitized;
+        }
+    }
+
+    // Verbatim transcriptions keep their fillers on purpose
+    if options.remove_fillers && !options.verbatim {
+        let filler_words = filler_words_or_defaults(app, &options.language(), options.filler_words.clone());
+        let fillers: Vec<&str> = filler_words.iter().map(String::as_str).collect();
+        if let Ok(cleaned) = remove_filler_words(&transcription, &fillers) {
+            transcription = cleaned;
+        }
+    }
+
+    let mut duration_adjustments = None;
+    if let Some(limits) = options.duration_limits {
+        if let Ok(cues) = parse_srt(&transcription) {
+            let (cues, adjustments) = srt_utils::enforce_duration_limits(cues, limits);
+            transcription = serialize_srt(&cues);
+            duration_adjustments = Some(adjustments);
+        }
+    }
+
+    if options.wrap_lines {
+        transcription = wrap_srt_lines(transcription, options.max_chars_per_subtitle);
+    }
+
+    (transcription, duration_adjustments)
+}
+
+/// Transcribes with speaker detection and returns the cues in structured form
+/// alongside the SRT. Defaults to gemini-2.5-pro since the plain-text output
+/// of gemini-2.0-flash has no cues.
+#[tauri::command]
+async fn transcribe_with_speakers(
+    app: AppHandle,
+    file_path: String,
+    options: TranscriptionOptions,
+    job_id: Option<String>,
+    api_key: String
+) -> Result<SpeakerTranscription, AppError> {
+    let model = options.model_or("gemini-2.5-pro");
+    if !is_srt_model(&model) {
+        return Err(format!("{} produces plain text without subtitle cues; choose another model", model).into());
+    }
+
+    let options = TranscriptionOptions {
+        model: Some(model),
+        enable_speaker_detection: true,
+        ..options
+    };
+    let result = transcribe_audio(app, file_path, options, job_id, api_key).await?;
+
+    let cues = parse_srt(&result.srt)
+        .map_err(|e| format!("Transcription is not valid SRT: {}", e))?
+        .iter()
+        .map(CueRecord::from)
+        .collect();
+
+    Ok(SpeakerTranscription {
+        srt: result.srt,
+        cues,
+        usage: result.usage,
+        warnings: result.warnings,
+    })
+}
+
+/// Transcribes with a timing per word, e.g. for karaoke-style highlighting.
+/// The JSON is far longer than SRT, so long recordings can hit the output
+/// limit; that is reported as an error rather than returning part of it.
+#[tauri::command]
+async fn transcribe_word_level(
+    app: AppHandle,
+    upload_cache: State<'_, UploadCache>,
+    transcription_lock: State<'_, TranscriptionLock>,
+    file_path: String,
+    options: TranscriptionOptions,
+    job_id: Option<String>,
+    api_key: String
+) -> Result<Vec<WordTiming>, AppError> {
+    let running = transcription_lock.try_acquire()?;
+    let cancel = running.cancellation();
+
+    if !has_credentials(&app, &api_key) {
+        return Err("API key is empty. Please set your Gemini API key in settings.".into());
+    }
+    if !Path::new(&file_path).exists() {
+        return Err("Audio file not found".into());
+    }
+
+    let mime_type = resolve_mime_type(&file_path, options.mime_override.as_deref())?;
+    let duration_ms = options.duration_ms.or_else(|| probe_duration_ms(Path::new(&file_path)).ok());
+    let generation_config = with_overrides(load_generation_settings(&app).transcription(), options.generation_config.clone())?
+        .with_response_schema(WordTiming::list_schema());
+    let progress = ProgressReporter::new(&app, job_id);
+    let client = build_client(&app, api_key).with_generation_config(generation_config).on_slot_wait(progress.slot_wait());
+    let upload = upload_or_reuse(&client, &upload_cache, &file_path, &mime_type, &progress, &cancel).await?;
+
+    let selected_model = options.model_or("gemini-2.5-pro");
+    let prompt = word_level_prompt(duration_ms, &options.language());
+    progress.report(ProgressStage::Generating, None, selected_model.as_str());
+    let generation = async {
+        Ok(client
+            .generate_content(&upload.file.uri, &upload.file.mime_type, &prompt, &selected_model)
+            .await
+            .map_err(|e| e.context("Failed to generate word timings"))?)
+    };
+    let generation = cancellable(&cancel, &client, &upload_cache, &upload, generation).await?;
+    track_usage(&app, &selected_model, generation.usage.as_ref());
+    if generation.hit_max_tokens() {
+        return Err("The word timings were cut off by the output token limit; split the audio into shorter files".into());
+    }
+
+    progress.report(ProgressStage::Finalizing, None, "");
+    let words: Vec<WordTiming> = serde_json::from_str(&generation.text)
+        .map_err(|e| format!("Word timings are not valid JSON: {}", e))?;
+    let words: Vec<WordTiming> = words.into_iter().filter(|word| !word.word.trim().is_empty()).collect();
+    validate_word_timings(&words).map_err(|e| format!("Gemini returned invalid word timings: {}", e))?;
+
+    progress.report(ProgressStage::Done, Some(100), "");
+    Ok(words)
+}
+
+/// Groups the output of `transcribe_word_level` into cues of at most
+/// `max_chars` characters
+#[tauri::command]
+async fn convert_word_timings_to_srt(words: Vec<WordTiming>, max_chars: u32) -> Result<String, String> {
+    word_timings_to_srt(&words, max_chars)
+}
+
+/// Transcribes each file in turn with the same settings, emitting a
+/// `batch-progress` event before and after each one. A failed file is
+/// reported in its result and the batch moves on; cancelling stops the batch.
+#[tauri::command]
+async fn transcribe_batch(
+    app: AppHandle,
+    file_paths: Vec<String>,
+    options: TranscriptionOptions,
+    api_key: String
+) -> Result<Vec<BatchResult>, AppError> {
+    let total = file_paths.len();
+    let mut results = Vec::with_capacity(total);
+    for (index, file_path) in file_paths.into_iter().enumerate() {
+        let filename = source_file_name(&file_path).unwrap_or_else(|| file_path.clone());
+        emit_batch_progress(&app, index, total, &filename, BatchStatus::Processing);
+
+        // The duration and type differ between files, so they are read from each one
+        let file_options = TranscriptionOptions {
+            duration_ms: None,
+            mime_override: None,
+            ..options.clone()
+        };
+        let result = transcribe_audio(app.clone(), file_path, file_options, None, api_key.clone()).await;
+
+        let cancelled = matches!(result, Err(AppError::Cancelled));
+        let (srt, error, status) = match result {
+            Ok(result) => (Some(result.srt), None, BatchStatus::Completed),
+            Err(e) => (None, Some(e.to_string()), BatchStatus::Failed),
+        };
+        emit_batch_progress(&app, index, total, &filename, status);
+        results.push(BatchResult { filename, srt, error });
+        if cancelled {
+            break;
+        }
+    }
+    Ok(results)
+}
+
+fn emit_batch_progress(app: &AppHandle, index: usize, total: usize, filename: &str, status: BatchStatus) {
+    let payload = BatchProgressEvent {
+        index,
+        total,
+        filename: filename.to_string(),
+        status,
+    };
+    if let Err(e) = app.emit("batch-progress", payload) {
+        warn!("Failed to emit batch progress: {}", e);
+    }
+}
+
+/// Transcribes a long recording in chunks of `chunk_length_ms` (10 minutes by
+/// default). The file is uploaded once and each request covers one time range
+/// of it; the per-chunk SRT is offset by the chunk start and joined, then
+/// post-processed like the output of `transcribe_audio`.
+#[tauri::command]
+async fn transcribe_audio_chunked(
+    app: AppHandle,
+    file_path: String,
+    options: TranscriptionOptions,
+    chunk_length_ms: Option<u32>,
+    api_key: String
+) -> Result<TranscriptionResult, AppError> {
+    let upload_cache = app.state::<UploadCache>();
+    let transcription_lock = app.state::<TranscriptionLock>();
+    let running = transcription_lock.try_acquire()?;
+    let cancel = running.cancellation();
+
+    if !has_credentials(&app, &api_key) {
+        return Err("API key is empty. Please set your Gemini API key in settings.".into());
+    }
+    if !Path::new(&file_path).exists() {
+        return Err("Audio file not found".into());
+    }
+    let chunk_length_ms = chunk_length_ms.unwrap_or(DEFAULT_CHUNK_LENGTH_MS);
+    if chunk_length_ms < MIN_CHUNK_LENGTH_MS {
+        return Err(format!("Chunk length must be at least {} seconds", MIN_CHUNK_LENGTH_MS / 1000).into());
+    }
+    let duration_ms = match options.duration_ms {
+        Some(duration_ms) => duration_ms,
+        None => probe_duration_ms(Path::new(&file_path))
+            .map_err(|e| format!("Could not determine the audio duration; pass durationMs: {}", e))?,
+    };
+
+    // Chunks are joined by their cue timestamps, so the model has to produce SRT
+    let selected_model = options.model_or("gemini-2.5-pro");
+    if !is_srt_model(&selected_model) {
+        return Err(format!("{} produces plain text without subtitle cues; choose another model", selected_model).into());
+    }
+
+    let mime_type = resolve_mime_type(&file_path, options.mime_override.as_deref())?;
+    let generation_config = with_overrides(load_generation_settings(&app).transcription(), options.generation_config.clone())?;
+    let progress = ProgressReporter::new(&app, None);
+    let client = build_client(&app, api_key)
+        .with_generation_config(generation_config)
+        .on_slot_wait(progress.slot_wait());
+    let upload = upload_or_reuse(&client, &upload_cache, &file_path, &mime_type, &progress, &cancel).await?;
+
+    let language = options.language();
+    let base_prompt = options
+        .with_rules(&app, None, |rules| srt_prompt(&app.state::<PromptTemplates>(), rules))
+        .for_model(&selected_model);
+    let client = client.with_system_instruction(base_prompt.system_instruction);
+    let format = ResponseFormat { first_block_only: options.first_block_only, srt_output: true };
+
+    let ranges = chunk_ranges(duration_ms as u64, chunk_length_ms as u64);
+    let mut chunks = Vec::with_capacity(ranges.len());
+    let mut usage_metadata = None;
+    let mut continuations = 0;
+    let mut warnings = Vec::new();
+
+    for (i, (start_ms, end_ms)) in ranges.iter().copied().enumerate() {
+        info!("Transcribing chunk {} of {} ({} - {})", i + 1, ranges.len(), format_timestamp(start_ms), format_timestamp(end_ms));
+        let prompt = format!("{}{}", base_prompt.text, chunk_range_instruction(&language, start_ms, end_ms));
+
+        let on_chunk = options.stream.then(|| progress.stream_chunks());
+        let generation = async {
+            generate_srt(&client, &upload.file, &prompt, &selected_model, format, &progress, on_chunk).await
+                .map_err(|e| e.context(&format!("Chunk {} of {}", i + 1, ranges.len())))
+        };
+        let generated = cancellable(&cancel, &client, &upload_cache, &upload, generation).await?;
+        if generated.truncated {
+            warnings.push(format!(
+                "Chunk {} ({} - {}) was cut off by the output token limit; try a shorter chunk length",
+                i + 1,
+                format_timestamp(start_ms),
+                format_timestamp(end_ms)
+            ));
+        }
+        usage_metadata = combine_usage(usage_metadata, generated.usage.as_ref());
+        continuations += generated.continuations;
+        chunks.push((start_ms, normalize_srt(&generated.srt)));
+    }
+
+    if options.delete_upload {
+        discard_upload(&client, &upload_cache, &upload).await;
+    }
+
+    let stitched = stitch_chunks(&chunks)?;
+    let (transcription, duration_adjustments) = post_process_transcription(&app, &options, stitched, &mut warnings);
+
+    // Make the result available to the preview player
+    if let Ok(cues) = parse_srt(&transcription) {
+        app.state::<SrtIndex>().load(cues);
+    }
+
+    let usage = track_usage(&app, &selected_model, usage_metadata.as_ref());
+
+    record_history(&app.state::<HistoryStore>(), NewHistoryEntry {
+        source_filename: source_file_name(&file_path).as_deref(),
+        model: &selected_model,
+        max_chars_per_subtitle: options.max_chars_per_subtitle,
+        srt: &transcription,
+        prompt_tokens: usage.as_ref().map(|usage| usage.prompt_tokens as i64),
+        output_tokens: usage.as_ref().map(|usage| usage.output_tokens as i64),
+    });
+    let session_id = start_session(
+        &app.state::<SessionStore>(),
+        &file_path,
+        &selected_model,
+        options.max_chars_per_subtitle,
+        options.enable_speaker_detection,
+        Some(duration_ms),
+        &transcription,
+    );
+
+    Ok(TranscriptionResult {
+        srt: transcription,
+        usage,
+        warnings,
+        duration_adjustments,
+        continuations,
+        session_id,
+    })
+}
+
+/// Reports the progress of one transcription job through
+/// `transcription-progress` events and the [`ProgressTracker`]
+#[derive(Clone)]
+struct ProgressReporter {
+    app: AppHandle,
+    job_id: String,
+}
+
+impl ProgressReporter {
+    /// Reports for `job_id`, or for a new random id when none is given
+    fn new(app: &AppHandle, job_id: Option<String>) -> Self {
+        Self {
+            app: app.clone(),
+            job_id: job_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        }
+    }
+
+    fn report(&self, stage: ProgressStage, percent: Option<u8>, detail: impl Into<String>) {
+        let progress = TranscriptionProgress {
+            job_id: self.job_id.clone(),
+            stage,
+            percent,
+            detail: detail.into(),
+        };
+        self.app.state::<ProgressTracker>().update(progress.clone());
+        if let Err(e) = self.app.emit("transcription-progress", progress) {
+            warn!("Failed to emit transcription progress: {}", e);
+        }
+    }
+
+    /// Reports upload progress, and emits an `upload-progress` event for
+    /// `file_path` on every update
+    fn upload_progress(&self, file_path: String) -> UploadProgress {
+        let reporter = self.clone();
+        Arc::new(move |bytes_sent, total_bytes| {
+            let percent = percent_of(bytes_sent, total_bytes);
+            reporter.report(ProgressStage::Uploading, Some(percent), format!("{} / {} bytes", bytes_sent, total_bytes));
+            let payload = UploadProgressEvent {
+                file_path: file_path.clone(),
+                bytes_sent,
+                total_bytes,
+            };
+            if let Err(e) = reporter.app.emit("upload-progress", payload) {
+                warn!("Failed to emit upload progress: {}", e);
+            }
+        })
+    }
+
+    /// Reports the Files API state while the uploaded file is processed, and
+    /// emits a `file-processing-status` event on every poll
+    fn processing_progress(&self) -> ProcessingProgress {
+        let reporter = self.clone();
+        Arc::new(move |state, elapsed_secs| {
+            reporter.report(ProgressStage::Processing, None, state);
+            let payload = FileProcessingStatusEvent {
+                job_id: reporter.job_id.clone(),
+                state: state.to_string(),
+                elapsed_secs,
+            };
+            if let Err(e) = reporter.app.emit("file-processing-status", payload) {
+                warn!("Failed to emit file processing status: {}", e);
+            }
+        })
+    }
+
+    /// Reports that the job waits for a free upload or generation slot
+    fn slot_wait(&self) -> SlotNotifier {
+        let reporter = self.clone();
+        Arc::new(move |kind| {
+            let detail = match kind {
+                SlotKind::Generation => "Waiting for a free generation slot",
+                SlotKind::Upload => "Waiting for a free upload slot",
+            };
+            reporter.report(ProgressStage::Queued, None, detail);
+        })
+    }
+
+    /// Emits each piece of streamed response text as a `transcription-chunk`
+    /// event
+    fn stream_chunks(&self) -> StreamChunk {
+        let reporter = self.clone();
+        Arc::new(move |text| {
+            let payload = TranscriptionChunkEvent {
+                job_id: reporter.job_id.clone(),
+                text: text.to_string(),
+            };
+            if let Err(e) = reporter.app.emit("transcription-chunk", payload) {
+                warn!("Failed to emit transcription chunk: {}", e);
+            }
+        })
+    }
+}
+
+/// How the SRT is taken out of a transcription response
+#[derive(Clone, Copy)]
+struct ResponseFormat {
+    /// Keep only the first SRT code block
+    first_block_only: bool,
+    /// The model answers in SRT rather than plain text
+    srt_output: bool,
+}
+
+/// SRT extracted from one or more responses to the same prompt
+struct GeneratedSrt {
+    srt: String,
+    usage: Option<UsageMetadata>,
+    /// Still cut off after all continuation attempts
+    truncated: bool,
+    /// Follow-up requests made because the output limit was hit
+    continuations: usize,
+}
+
+/// Runs `prompt` against the uploaded file. When the output limit cuts the
+/// response off, asks for the rest starting after the last complete cues and
+/// stitches it on, up to [`MAX_CONTINUATIONS`] times, reporting each round
+/// to `progress`.
+///
+/// With `on_chunk`, the first response is streamed to it as it is generated;
+/// when the stream cannot be parsed the request is sent again without
+/// streaming. Continuations are never streamed.
+async fn generate_srt(
+    client: &GeminiClient,
+    file_info: &CachedUpload,
+    prompt: &str,
+    model: &str,
+    format: ResponseFormat,
+    progress: &ProgressReporter,
+    on_chunk: Option<StreamChunk>,
+) -> Result<GeneratedSrt, AppError> {
+    let streamed = match on_chunk {
+        Some(on_chunk) => match client.generate_content_streaming(&file_info.uri, &file_info.mime_type, prompt, model, on_chunk).await {
+            Err(e) if matches!(e.root(), GeminiError::Parse(_)) => {
+                warn!("Streamed response could not be parsed ({}), generating without streaming", e);
+                None
+            }
+            result => Some(result),
+        },
+        None => None,
+    };
+    let generation = match streamed {
+        Some(result) => result,
+        None => client.generate_content(&file_info.uri, &file_info.mime_type, prompt, model).await,
+    }
+    .map_err(|e| e.context("Failed to generate transcription"))?;
+    let mut usage_metadata = generation.usage.clone();
+    let (mut extracted, mut truncated) = extract_response_srt(&generation, format)?;
+
+    let mut continuations = 0;
+    while truncated && continuations < MAX_CONTINUATIONS {
+        let cues = parse_srt(&extracted).unwrap_or_default();
+        let Some(last_cue) = cues.last() else {
+            break;
+        };
+        continuations += 1;
+        info!("Transcription was cut off after {}, requesting continuation {}", format_timestamp(last_cue.end_ms), continuations);
+        progress.report(
+            ProgressStage::Generating,
+            None,
+            format!("Continuing after {} ({} of up to {})", format_timestamp(last_cue.end_ms), continuations, MAX_CONTINUATIONS),
+        );
+
+        let context = &cues[cues.len().saturating_sub(CONTINUATION_CONTEXT_CUES)..];
+        let continuation_prompt = format!(
+            "{}\n\n# 続き\n前回の出力は途中で途切れました。最後に出力された完全な字幕は以下です。\n\n{}\n続きを出力してください。これらの字幕より後の字幕のみを、通し番号{}から始めてSRT形式で出力してください。",
+            prompt,
+            serialize_srt(context),
+            last_cue.index + 1
+        );
+        let continuation = client.generate_content(&file_info.uri, &file_info.mime_type, &continuation_prompt, model).await
+            .map_err(|e| e.context("Failed to generate transcription continuation"))?;
+        usage_metadata = combine_usage(usage_metadata, continuation.usage.as_ref());
+
+        let (continuation_srt, continuation_truncated) = extract_response_srt(&continuation, format)?;
+        extracted = stitch_continuation(&extracted, &continuation_srt)?;
+        truncated = continuation_truncated;
+    }
+
+    Ok(GeneratedSrt {
+        srt: extracted,
+        usage: usage_metadata,
+        truncated,
+        continuations,
+    })
+}
+
+/// Usage of a generation as reported to the frontend, also added to the
+/// running total returned by `get_session_usage`
+fn track_usage(app: &AppHandle, model: &str, usage: Option<&UsageMetadata>) -> Option<TokenUsage> {
+    let usage = TokenUsage::from_metadata(model, usage?);
+    app.state::<UsageTracker>().record(&usage);
+    Some(usage)
+}
+
+/// Token usage of every generation since the app started
+#[tauri::command]
+async fn get_session_usage(usage_tracker: State<'_, UsageTracker>) -> Result<SessionUsage, String> {
+    Ok(usage_tracker.totals())
+}
+
+fn combine_usage(usage: Option<UsageMetadata>, next: Option<&UsageMetadata>) -> Option<UsageMetadata> {
+    match (usage, next) {
+        (Some(usage), Some(next)) => Some(usage.combined(next)),
+        (usage, next) => usage.or_else(|| next.cloned()),
+    }
+}
+
+/// Extracts the SRT from a transcription response. A response cut off by the
+/// output token limit, or ending in an incomplete cue, loses its final cue;
+/// the returned flag tells whether that happened.
+fn extract_response_srt(generation: &GenerationResult, format: ResponseFormat) -> Result<(String, bool), String> {
+    let text = close_truncated_fence(&generation.text);
+
+    // Extract SRT content, removing any code block markers. By default every
+    // SRT block is kept, since long answers may be split across several.
+    let extracted = if format.first_block_only {
+        extract_srt_block(&text).to_string()
+    } else {
+        extract_all_srt_content(&text)?
+    };
+
+    if !format.srt_output {
+        return Ok((extracted, generation.hit_max_tokens()));
+    }
+    // Incomplete cues are detected before the repair would silently drop them
+    let (srt, dropped) = drop_incomplete_final_cue(&extracted, generation.hit_max_tokens());
+    Ok((repair_srt(&srt), dropped || generation.hit_max_tokens()))
+}
+
+/// A file ready for generation, and whether this transcription uploaded it
+struct PreparedUpload {
+    file: CachedUpload,
+    file_hash: String,
+    reused: bool,
+}
+
+/// Uploads `file_path` unless the cache holds a still-valid upload of the
+/// same content for the current key, then waits until the file is active
+async fn upload_or_reuse(
+    client: &GeminiClient,
+    upload_cache: &UploadCache,
+    file_path: &str,
+    mime_type: &str,
+    progress: &ProgressReporter,
+    cancel: &CancellationToken,
+) -> Result<PreparedUpload, AppError> {
+    let on_progress = progress.upload_progress(file_path.to_string());
+    let on_state = progress.processing_progress();
+    let file_size = fs::metadata(file_path).await
+        .map_err(|e| format!("Failed to read audio file: {}", e))?
+        .len();
+    let file_hash = sha256_file(Path::new(file_path)).await
+        .map_err(|e| format!("Failed to read audio file: {}", e))?;
+
+    // Inline files of the Vertex AI mode only live as long as the client
+    let cached = client
+        .uses_files_api()
+        .then(|| upload_cache.get(&file_hash, mime_type, &key_fingerprint(client.api_key())))
+        .flatten();
+    if let Some(cached) = cached {
+        if client.wait_for_file_processing(&cached.name, Some(cancel), Some(&on_state)).await.is_ok() {
+            info!("Reusing uploaded file {} for {}", cached.name, file_path);
+            on_progress(file_size, file_size);
+            return Ok(PreparedUpload { file: cached, file_hash, reused: true });
+        }
+        if cancel.is_cancelled() {
+            return Err(AppError::Cancelled);
+        }
+        // The file was deleted or failed on the server; upload it again
+        upload_cache.remove(&file_hash);
+    }
+
+    let file_info = tokio::select! {
+        result = client.upload_file(file_path, mime_type, Some(on_progress)) => {
+            result.map_err(|e| e.context("Failed to upload file"))?
+        }
+        _ = cancel.cancelled() => return Err(AppError::Cancelled),
+    };
+
+    // Wait for file processing
+    if let Err(e) = client.wait_for_file_processing(&file_info.name, Some(cancel), Some(&on_state)).await {
+        if cancel.is_cancelled() {
+            delete_uploaded_file(client, &file_info.name).await;
+            return Err(AppError::Cancelled);
+        }
+        return Err(e.context("File processing failed").into());
+    }
+
+    // Rotation may have switched keys during the upload, so fingerprint afterwards
+    let upload = CachedUpload {
+        name: file_info.name,
+        uri: file_info.uri,
+        mime_type: mime_type.to_string(),
+        expiration_time: file_info.expiration_time,
+        key_fingerprint: key_fingerprint(client.api_key()),
+    };
+    if client.uses_files_api() {
+        upload_cache.insert(file_hash.clone(), upload.clone());
+    }
+    Ok(PreparedUpload { file: upload, file_hash, reused: false })
+}
+
+async fn delete_uploaded_file(client: &GeminiClient, file_name: &str) {
+    match client.delete_file(file_name).await {
+        Ok(()) => info!("Deleted uploaded file {}", file_name),
+        Err(e) => warn!("Failed to delete uploaded file {}: {}", file_name, e),
+    }
+}
+
+/// Deletes the uploaded file and forgets it in the cache
+async fn discard_upload(client: &GeminiClient, upload_cache: &UploadCache, upload: &PreparedUpload) {
+    upload_cache.remove(&upload.file_hash);
+    delete_uploaded_file(client, &upload.file.name).await;
+}
+
+/// Runs `work` unless the transcription is cancelled first. When it is
+/// cancelled or fails, a file uploaded by this transcription is deleted
+/// rather than left on the Files API.
+async fn cancellable<T>(
+    cancel: &CancellationToken,
+    client: &GeminiClient,
+    upload_cache: &UploadCache,
+    upload: &PreparedUpload,
+    work: impl Future<Output = Result<T, AppError>>,
+) -> Result<T, AppError> {
+    let result = tokio::select! {
+        result = work => result,
+        _ = cancel.cancelled() => Err(AppError::Cancelled),
+    };
+    if result.is_err() && !upload.reused {
+        discard_upload(client, upload_cache, upload).await;
+    }
+    result
+}
+
+/// Files uploaded to the Gemini Files API with the current key
+#[tauri::command]
+async fn list_remote_files(app: AppHandle, api_key: String) -> Result<Vec<FileInfo>, AppError> {
+    if !has_credentials(&app, &api_key) {
+        return Err("API key is empty".into());
+    }
+    let client = build_client(&app, api_key);
+    Ok(client.list_files().await.map_err(|e| e.context("Failed to list uploaded files"))?)
+}
+
+/// Deletes an uploaded file by its `files/...` name, so it is no longer
+/// reused for later transcriptions
+#[tauri::command]
+async fn delete_remote_file(
+    app: AppHandle,
+    upload_cache: State<'_, UploadCache>,
+    name: String,
+    api_key: String,
+) -> Result<(), AppError> {
+    if !has_credentials(&app, &api_key) {
+        return Err("API key is empty".into());
+    }
+    if !name.starts_with("files/") {
+        return Err(format!("Not an uploaded file name: {}", name).into());
+    }
+    let client = build_client(&app, api_key);
+    client.delete_file(&name).await.map_err(|e| e.context("Failed to delete uploaded file"))?;
+    upload_cache.remove_file(&name);
+    Ok(())
+}
+
+/// Payload of the `upload-progress` event emitted while a file is uploaded
+#[derive(Clone, Serialize)]
+struct UploadProgressEvent {
+    file_path: String,
+    bytes_sent: u64,
+    total_bytes: u64,
+}
+
+/// Payload of the `file-processing-status` event; `state` is the Files API
+/// state, e.g. `PROCESSING` or `ACTIVE`
+#[derive(Clone, Serialize)]
+struct FileProcessingStatusEvent {
+    job_id: String,
+    state: String,
+    elapsed_secs: u64,
+}
+
+/// Payload of the `transcription-chunk` event; `text` is the part of the
+/// response received since the previous chunk
+#[derive(Clone, Serialize)]
+struct TranscriptionChunkEvent {
+    job_id: String,
+    text: String,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum BatchStatus {
+    Processing,
+    Completed,
+    Failed,
+}
+
+/// Payload of the `batch-progress` event; `index` is zero-based
+#[derive(Clone, Serialize)]
+struct BatchProgressEvent {
+    index: usize,
+    total: usize,
+    filename: String,
+    status: BatchStatus,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum PipelineStage {
+    Transcription,
+    TopicAnalysis,
+    Dictionary,
+    Enhancement,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum PipelineStatus {
+    Started,
+    Completed,
+    Skipped,
+    Failed,
+}
+
+/// Payload of the `pipeline-stage` event sent by `run_full_pipeline`
+#[derive(Clone, Serialize)]
+struct PipelineStageEvent {
+    stage: PipelineStage,
+    status: PipelineStatus,
+    detail: String,
+}
+
+/// Outcome of one file of `transcribe_batch`: either `srt` or `error` is set
+#[derive(Serialize)]
+struct BatchResult {
+    filename: String,
+    srt: Option<String>,
+    error: Option<String>,
+}
+
+/// Settings of `transcribe_audio` and the commands built on it; every field
+/// may be left out
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct TranscriptionOptions {
+    max_chars_per_subtitle: u32,
+    enable_speaker_detection: bool,
+    /// Read from the file when not given
+    duration_ms: Option<u32>,
+    model: Option<String>,
+    mime_override: Option<String>,
+    /// ISO 639-1 code of the spoken language; Japanese when not given
+    language: Option<String>,
+    /// Keep only the first SRT code block of the response
+    first_block_only: bool,
+    repeat_collapse_threshold: Option<usize>,
+    wrap_lines: bool,
+    /// Keep fillers, false starts and repetitions
+    verbatim: bool,
+    /// Strip filler words from the generated subtitles; ignored with `verbatim`
+    remove_fillers: bool,
+    /// Replaces the list saved with `set_filler_words`
+    filler_words: Option<Vec<String>>,
+    keep_markup: bool,
+    duration_limits: Option<DurationLimits>,
+    /// Emit `transcription-chunk` events while the response is generated
+    stream: bool,
+    /// Delete the uploaded file once the transcription is done
+    delete_upload: bool,
+    generation_config: Option<GenerationConfig>,
+}
+
+impl Default for TranscriptionOptions {
+    fn default() -> Self {
+        Self {
+            max_chars_per_subtitle: DEFAULT_MAX_CHARS_PER_SUBTITLE,
+            enable_speaker_detection: false,
+            duration_ms: None,
+            model: None,
+            mime_override: None,
+            language: None,
+            first_block_only: false,
+            repeat_collapse_threshold: None,
+            wrap_lines: false,
+            verbatim: false,
+            remove_fillers: false,
+            filler_words: None,
+            keep_markup: false,
+            duration_limits: None,
+            stream: false,
+            delete_upload: false,
+            generation_config: None,
+        }
+    }
+}
+
+impl TranscriptionOptions {
+    fn language(&self) -> TranscriptionLanguage {
+        TranscriptionLanguage::from_code(self.language.as_deref())
+    }
+
+    /// The requested model, or `default`
+    fn model_or(&self, default: &str) -> String {
+        self.model.clone().unwrap_or_else(|| default.to_string())
+    }
+
+    /// `duration_ms`, or the duration read from `file_path` when not given
+    fn resolve_duration_ms(&self, file_path: &str) -> Option<u32> {
+        self.duration_ms.or_else(|| match probe_duration_ms(Path::new(file_path)) {
+            Ok(duration_ms) => Some(duration_ms),
+            Err(e) => {
+                warn!("Could not determine audio duration: {}", e);
+                None
+            }
+        })
+    }
+
+    /// Calls `f` with the subtitle rules of these settings, using the filler
+    /// words resolved for the language
+    fn with_rules<T>(&self, app: &AppHandle, duration_ms: Option<u32>, f: impl FnOnce(&SubtitleRules) -> T) -> T {
+        let language = self.language();
+        let filler_words = resolve_filler_words(app, &language, self.filler_words.clone());
+        f(&SubtitleRules {
+            max_chars_per_subtitle: self.max_chars_per_subtitle,
+            enable_speaker_detection: self.enable_speaker_detection,
+            duration_ms,
+            language: &language,
+            verbatim: self.verbatim,
+            filler_words: filler_words.as_deref(),
+        })
+    }
+
+    /// The prompt `transcribe_audio` sends to `model` with these settings
+    fn prompt(&self, app: &AppHandle, model: &str, duration_ms: Option<u32>) -> Prompt {
+        self.with_rules(app, duration_ms, |rules| build_transcription_prompt(&app.state::<PromptTemplates>(), model, rules))
+    }
+}
+
+/// SRT output of `transcribe_audio` with the tokens the request consumed
+/// and any post-processing warnings
+#[derive(Serialize)]
+struct TranscriptionResult {
+    srt: String,
+    usage: Option<TokenUsage>,
+    warnings: Vec<String>,
+    /// Present when `duration_limits` were requested
+    duration_adjustments: Option<DurationAdjustments>,
+    /// Follow-up requests made because the output token limit was hit
+    continuations: usize,
+    /// Session for retrying the later pipeline steps with
+    /// `enhance_from_session`; unset when it could not be saved
+    session_id: Option<String>,
+}
+
+/// Result of `test_connection`
+#[derive(Serialize)]
+struct ConnectionTest {
+    /// Models listed for the key
+    models: usize,
+    elapsed_ms: u64,
+}
+
+/// `create_dictionary` output: the dictionary CSV and the web sources the
+/// search-grounded generation drew on
+#[derive(Serialize)]
+struct DictionaryResult {
+    csv: String,
+    citations: Vec<Citation>,
+    /// Both requests: the grounded answer and its structuring
+    usage: Option<TokenUsage>,
+}
+
+/// `analyze_topic` output: the `メイントピック:` / `キーワード:` lines
+#[derive(Serialize)]
+struct TopicResult {
+    topic: String,
+    usage: Option<TokenUsage>,
+}
+
+/// `enhance_transcription_with_dictionary` output
+#[derive(Serialize)]
+struct EnhancementResult {
+    srt: String,
+    usage: Option<TokenUsage>,
+}
+
+/// `run_full_pipeline` output. `srt` is the enhanced transcription, or the
+/// initial one when enhancement was skipped
+#[derive(Serialize)]
+struct PipelineResult {
+    srt: String,
+    topic: Option<String>,
+    dictionary: Option<String>,
+    citations: Vec<Citation>,
+    session_id: Option<String>,
+    /// One entry per step that called the API
+    usage: Vec<TokenUsage>,
+}
+
+/// `score_subtitles` output for one cue
+#[derive(Serialize)]
+struct CueQuality {
+    index: u32,
+    start_ms: u64,
+    end_ms: u64,
+    score: f32,
+}
+
+/// `enforce_duration_limits` output
+#[derive(Serialize)]
+struct DurationLimitResult {
+    srt: String,
+    adjustments: DurationAdjustments,
+}
+
+/// `transcribe_with_speakers` output: the SRT plus each cue with its speaker
+/// label split into its own field
+#[derive(Serialize)]
+struct SpeakerTranscription {
+    srt: String,
+    cues: Vec<CueRecord>,
+    usage: Option<TokenUsage>,
+    warnings: Vec<String>,
+}
+
+/// Stops the running transcription; returns false when none is running
+#[tauri::command]
+async fn cancel_transcription(transcription_lock: State<'_, TranscriptionLock>) -> Result<bool, String> {
+    Ok(transcription_lock.cancel())
+}
+
+/// The last progress reported for `job_id`, or for the most recent job
+#[tauri::command]
+async fn get_transcription_progress(
+    progress: State<'_, ProgressTracker>,
+    job_id: Option<String>,
+) -> Result<Option<TranscriptionProgress>, String> {
+    Ok(progress.get(job_id.as_deref()))
+}
+
+/// Models the API key can use for generation, with whether they take audio
+#[tauri::command]
+async fn get_available_models(app: AppHandle, api_key: String) -> Result<Vec<AvailableModel>, AppError> {
+    if !has_credentials(&app, &api_key) {
+        return Err("API key is empty".into());
+    }
+    let client = build_client(&app, api_key);
+    Ok(client.list_models().await.map_err(|e| e.context("Failed to list models"))?)
+}
+
+/// Lists the models once, without retries, to check the API key, proxy and
+/// certificate settings together
+#[tauri::command]
+async fn test_connection(app: AppHandle, api_key: String) -> Result<ConnectionTest, AppError> {
+    if !has_credentials(&app, &api_key) {
+        return Err("API key is empty".into());
+    }
+    let client = build_client(&app, api_key).with_retry_settings(RetrySettings {
+        max_attempts: 1,
+        ..RetrySettings::default()
+    });
+    let started = std::time::Instant::now();
+    let models = client.list_models().await.map_err(|e| e.context("Connection test failed"))?;
+    Ok(ConnectionTest {
+        models: models.len(),
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+/// `model` when given, otherwise the first of [`TEXT_MODEL_PREFERENCE`] the
+/// API still offers. Falls back to the first preference when the list cannot
+/// be fetched.
+async fn resolve_text_model(client: &GeminiClient, model: Option<String>) -> String {
+    if let Some(model) = model.filter(|model| !model.trim().is_empty()) {
+        return model;
+    }
+    match client.list_models().await {
+        Ok(models) => pick_model(&models, &TEXT_MODEL_PREFERENCE),
+        Err(e) => {
+            warn!("Could not list models, using the default: {}", e);
+            None
+        }
+    }
+    .unwrap_or_else(|| TEXT_MODEL_PREFERENCE[0].to_string())
+}
+
+async fn generate_topic_analysis(
+    app: &AppHandle,
+    prompt_templates: &PromptTemplates,
+    transcription: &str,
+    api_key: String,
+) -> Result<(TopicAnalysis, Option<TokenUsage>), AppError> {
+    let client = build_client(app, api_key).with_generation_config(load_generation_settings(app).dictionary());
+    
+    // トピック分析用プロンプト
+    let prompt = analyze_topic_prompt(prompt_templates, transcription);
+    
+    let model = "gemini-2.0-flash";
+    let (analysis, usage) = client
+        .generate_structured(&prompt, model, TopicAnalysis::schema())
+        .await
+        .map_err(|e| e.context("Failed to analyze topic"))?;
+    Ok((analysis, track_usage(app, model, usage.as_ref())))
+}
+
+#[tauri::command]
+async fn analyze_topic(
+    app: AppHandle,
+    prompt_templates: State<'_, PromptTemplates>,
+    session_store: State<'_, SessionStore>,
+    transcription: String,
+    session_id: Option<String>,
+    api_key: String,
+) -> Result<TopicResult, AppError> {
+    if !has_credentials(&app, &api_key) {
+        return Err("API key is empty".into());
+    }
+
+    let (analysis, usage) = generate_topic_analysis(&app, &prompt_templates, &transcription, api_key).await?;
+    let topic = analysis.to_text();
+    update_session(&session_store, session_id.as_deref(), |session| session.topic = Some(topic.clone()));
+    Ok(TopicResult { topic, usage })
+}
+
+#[tauri::command]
+async fn create_dictionary(
+    app: AppHandle,
+    session_store: State<'_, SessionStore>,
+    topic: String,
+    model: Option<String>,
+    generation_config: Option<GenerationConfig>,
+    session_id: Option<String>,
+    api_key: String,
+) -> Result<DictionaryResult, AppError> {
+    if !has_credentials(&app, &api_key) {
+        return Err("API key is empty".into());
+    }
+
+    // Dictionary creation defaults to temperature 0.7
+    let generation_config = with_overrides(load_generation_settings(&app).dictionary(), generation_config)?;
+    let client = build_client(&app, api_key).with_generation_config(generation_config);
+    let model = resolve_text_model(&client, model).await;
+    
+    // Google検索を使って正確な情報を取得した辞書作成用プロンプト
+    let prompt = create_dictionary_prompt(&app.state::<PromptTemplates>(), &topic);
+    
+    let (dictionary, citations) = client.generate_text_content_with_search(&prompt, &model).await
+        .map_err(|e| e.context("Failed to create dictionary with search"))?;
+    info!("Dictionary grounded on {} sources", citations.len());
+
+    // The search tool rules out a response schema, so the grounded answer is
+    // structured in a second request
+    let (terms, structure_usage): (Vec<GeneratedTerm>, _) = client
+        .generate_structured(&structure_dictionary_prompt(&dictionary.text), &model, GeneratedTerm::list_schema())
+        .await
+        .map_err(|e| e.context("Failed to structure dictionary"))?;
+    let usage = track_usage(&app, &model, combine_usage(dictionary.usage, structure_usage.as_ref()).as_ref());
+    let entries = validate_generated_terms(terms)?;
+    let csv = dictionary_to_csv(&entries)?;
+    update_session(&session_store, session_id.as_deref(), |session| session.dictionary = Some(csv.clone()));
+    Ok(DictionaryResult { csv, citations, usage })
+}
+
+/// Translates every cue and returns bilingual subtitles. Uses `model`, or the
+/// first available model of [`TEXT_MODEL_PREFERENCE`].
+#[tauri::command]
+async fn translate_srt(app: AppHandle, srt: String, target_language: String, model: Option<String>, api_key: String) -> Result<String, AppError> {
+    if !has_credentials(&app, &api_key) {
+        return Err("API key is empty".into());
+    }
+    if target_language.trim().is_empty() {
+        return Err("Target language is empty".into());
+    }
+
+    let cues = parse_srt(&srt)?;
+    if cues.is_empty() {
+        return Err("No subtitles to translate".into());
+    }
+
+    let client = build_client(&app, api_key).with_generation_config(load_generation_settings(&app).transcription());
+    let model = resolve_text_model(&client, model).await;
+
+    // 字幕翻訳用プロンプト（タイムスタンプと字幕数を維持させる）
+    let prompt = format!(
+        "以下のSRT字幕の各字幕テキストを{}に翻訳してください。\n\n# 要求事項\n- 字幕の数（{}個）、通し番号、タイムスタンプは一切変更しないでください\n- 字幕の結合・分割・省略・追加は禁止です\n- 各字幕のテキストは翻訳文のみに置き換えてください\n- 話者名（例: `アオイ: `）がある場合はそのまま残してください\n\n# SRT字幕\n{}\n\n**説明や前置きは不要です。翻訳後のSRT形式のテキストのみを出力してください。**",
+        target_language.trim(),
+        cues.len(),
+        serialize_srt(&cues)
+    );
+
+    let generation = client.generate_text_content(&prompt, &model).await
+        .map_err(|e| e.context("Failed to translate subtitles"))?;
+    track_usage(&app, &model, generation.usage.as_ref());
+
+    let translated = parse_srt(&extract_srt_content(&generation.text))
+        .map_err(|e| format!("Failed to parse translated subtitles: {}", e))?;
+    let bilingual = merge_translation(&cues, &translated)?;
+
+    Ok(serialize_srt(&bilingual))
+}
+
+/// Turns a plain-text first pass into SRT using the dictionary. Of `options`,
+/// the subtitle settings, `language`, `verbatim`, `filler_words`,
+/// `wrap_lines`, `model` and `generation_config` apply.
+#[tauri::command]
+async fn enhance_transcription_with_dictionary(
+    app: AppHandle,
+    initial_transcription: String, 
+    dictionary: String, 
+    options: TranscriptionOptions,
+    source_filename: Option<String>,
+    api_key: String
+) -> Result<EnhancementResult, AppError> {
+    if !has_credentials(&app, &api_key) {
+        return Err("API key is empty".into());
+    }
+
+    // Enhancement is a transcription task and defaults to temperature 0
+    let generation_config = with_overrides(load_generation_settings(&app).transcription(), options.generation_config.clone())?;
+    let client = build_client(&app, api_key).with_generation_config(generation_config);
+    let model = resolve_text_model(&client, options.model.clone()).await;
+    
+    // 既存の文字起こしを辞書を使ってSRT形式に変換するプロンプト
+    let prompt = options
+        .with_rules(&app, options.duration_ms, |rules| {
+            enhance_with_dictionary_prompt(&app.state::<PromptTemplates>(), &initial_transcription, &dictionary, rules)
+        })
+        .for_model(&model);
+    
+    let generation = client
+        .with_system_instruction(prompt.system_instruction)
+        .generate_text_content(&prompt.text, &model)
+        .await
+        .map_err(|e| e.context("Failed to enhance transcription"))?;
+
+    // Extract SRT content, removing any code block markers
+    let mut enhanced_result = normalize_srt(&extract_srt_content(&generation.text));
+    if options.wrap_lines {
+        enhanced_result = wrap_srt_lines(enhanced_result, options.max_chars_per_subtitle);
+    }
+    let usage = track_usage(&app, &model, generation.usage.as_ref());
+
+    record_history(&app.state::<HistoryStore>(), NewHistoryEntry {
+        source_filename: source_filename.as_deref(),
+        model: &model,
+        max_chars_per_subtitle: options.max_chars_per_subtitle,
+        srt: &enhanced_result,
+        prompt_tokens: usage.as_ref().map(|usage| usage.prompt_tokens as i64),
+        output_tokens: usage.as_ref().map(|usage| usage.output_tokens as i64),
+    });
+
+    Ok(EnhancementResult { srt: enhanced_result, usage })
+}
+
+/// Retries the enhancement of a transcription saved by `transcribe_audio`,
+/// using the dictionary recorded by `create_dictionary` unless `dictionary`
+/// is given. The subtitle length, speaker detection and duration recorded in
+/// the session replace those in `options`.
+#[tauri::command]
+async fn enhance_from_session(
+    app: AppHandle,
+    session_store: State<'_, SessionStore>,
+    session_id: String,
+    dictionary: Option<String>,
+    options: Option<TranscriptionOptions>,
+    api_key: String
+) -> Result<EnhancementResult, AppError> {
+    let session = session_store.get(&session_id)?;
+    let dictionary = dictionary
+        .or(session.dictionary)
+        .ok_or_else(|| format!("Session {} has no dictionary yet; run create_dictionary first", session_id))?;
+    let options = TranscriptionOptions {
+        max_chars_per_subtitle: session.max_chars_per_subtitle,
+        enable_speaker_detection: session.enable_speaker_detection,
+        duration_ms: session.duration_ms,
+        ..options.unwrap_or_default()
+    };
+
+    enhance_transcription_with_dictionary(app.clone(), session.transcription, dictionary, options, session.source_filename, api_key).await
 }
 
+/// Runs transcription, topic analysis, dictionary creation and enhancement in
+/// one call, emitting a `pipeline-stage` event as each step starts and ends.
+/// A dictionary loaded from `dictionary_path` replaces the topic analysis and
+/// dictionary steps; when no keywords are found, or the dictionary is empty,
+/// the remaining steps are skipped and the initial transcription is returned.
 #[tauri::command]
-async fn set_api_key(api_key: String) -> Result<bool, String> {
-    println!("DEBUG: Attempting to save API key, length: {}", api_key.len());
-    
-    if api_key.trim().is_empty() {
-        println!("DEBUG: API key is empty");
-        return Err("API key cannot be empty".to_string());
-    }
+async fn run_full_pipeline(
+    app: AppHandle,
+    file_path: String,
+    options: TranscriptionOptions,
+    dictionary_path: Option<String>,
+    job_id: Option<String>,
+    api_key: String
+) -> Result<PipelineResult, AppError> {
+    let prompt_templates = app.state::<PromptTemplates>();
+    let session_store = app.state::<SessionStore>();
+
+    emit_pipeline_stage(&app, PipelineStage::Transcription, PipelineStatus::Started, "");
+    let transcription_options = TranscriptionOptions {
+        model: Some(options.model_or("gemini-2.5-pro")),
+        ..options.clone()
+    };
+    let transcription = transcribe_audio(app.clone(), file_path.clone(), transcription_options, job_id, api_key.clone()).await;
+    let transcription = pipeline_step(&app, PipelineStage::Transcription, transcription)?;
+    emit_pipeline_stage(&app, PipelineStage::Transcription, PipelineStatus::Completed, "");
+    let session_id = transcription.session_id;
+    let mut result = PipelineResult {
+        srt: transcription.srt,
+        topic: None,
+        dictionary: None,
+        citations: Vec::new(),
+        session_id: session_id.clone(),
+        usage: transcription.usage.into_iter().collect(),
+    };
 
-    let entry = Entry::new(SERVICE_NAME, API_KEY_ENTRY)
-        .map_err(|e| {
-            println!("DEBUG: Failed to create keyring entry: {}", e);
-            format!("Failed to create keyring entry: {}", e)
-        })?;
-    
-    match entry.set_password(&api_key) {
-        Ok(_) => {
-            println!("DEBUG: Successfully saved API key to keyring");
-            
-            // Verify the save immediately
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-            match entry.get_password() {
-                Ok(saved_key) => {
-                    println!("DEBUG: Verification read successful, length: {}", saved_key.len());
-                    Ok(true)
-                },
-                Err(e) => {
-                    println!("DEBUG: Verification read failed: {}", e);
-                    // Still return success since the save operation succeeded
-                    Ok(true)
-                }
+    let dictionary = match dictionary_path {
+        Some(path) => {
+            emit_pipeline_stage(&app, PipelineStage::TopicAnalysis, PipelineStatus::Skipped, "Using a custom dictionary");
+            emit_pipeline_stage(&app, PipelineStage::Dictionary, PipelineStatus::Started, &path);
+            let dictionary = load_dictionary_csv(path).await.map_err(AppError::from);
+            let dictionary = pipeline_step(&app, PipelineStage::Dictionary, dictionary)?;
+            update_session(&session_store, session_id.as_deref(), |session| session.dictionary = Some(dictionary.clone()));
+            dictionary
+        }
+        None => {
+            emit_pipeline_stage(&app, PipelineStage::TopicAnalysis, PipelineStatus::Started, "");
+            let analysis = generate_topic_analysis(&app, &prompt_templates, &result.srt, api_key.clone()).await;
+            let (analysis, usage) = pipeline_step(&app, PipelineStage::TopicAnalysis, analysis)?;
+            result.usage.extend(usage);
+            let topic = analysis.to_text();
+            update_session(&session_store, session_id.as_deref(), |session| session.topic = Some(topic.clone()));
+            result.topic = Some(topic.clone());
+            if analysis.keywords.iter().all(|keyword| keyword.trim().is_empty()) {
+                emit_pipeline_stage(&app, PipelineStage::TopicAnalysis, PipelineStatus::Completed, "No keywords found");
+                emit_pipeline_stage(&app, PipelineStage::Dictionary, PipelineStatus::Skipped, "No keywords found");
+                emit_pipeline_stage(&app, PipelineStage::Enhancement, PipelineStatus::Skipped, "No keywords found");
+                return Ok(result);
             }
-        },
-        Err(e) => {
-            println!("DEBUG: Failed to save API key: {}", e);
-            Err(format!("Failed to store API key: {}", e))
+            emit_pipeline_stage(&app, PipelineStage::TopicAnalysis, PipelineStatus::Completed, analysis.topic_summary.trim());
+
+            emit_pipeline_stage(&app, PipelineStage::Dictionary, PipelineStatus::Started, "");
+            let created = create_dictionary(
+                app.clone(),
+                session_store.clone(),
+                topic,
+                None,
+                None,
+                session_id.clone(),
+                api_key.clone(),
+            )
+            .await;
+            let created = pipeline_step(&app, PipelineStage::Dictionary, created)?;
+            result.citations = created.citations;
+            result.usage.extend(created.usage);
+            created.csv
         }
+    };
+    let entries = validate_dictionary_csv(&dictionary).map_err(AppError::from);
+    let terms = pipeline_step(&app, PipelineStage::Dictionary, entries)?.len();
+    emit_pipeline_stage(&app, PipelineStage::Dictionary, PipelineStatus::Completed, &format!("{} terms", terms));
+    result.dictionary = Some(dictionary.clone());
+    if terms == 0 {
+        emit_pipeline_stage(&app, PipelineStage::Enhancement, PipelineStatus::Skipped, "The dictionary is empty");
+        return Ok(result);
     }
+
+    emit_pipeline_stage(&app, PipelineStage::Enhancement, PipelineStatus::Started, "");
+    // The enhancement picks its own text model
+    let enhancement_options = TranscriptionOptions {
+        model: None,
+        generation_config: None,
+        ..options
+    };
+    let enhanced = enhance_transcription_with_dictionary(
+        app.clone(),
+        result.srt.clone(),
+        dictionary,
+        enhancement_options,
+        source_file_name(&file_path),
+        api_key,
+    )
+    .await;
+    let enhanced = pipeline_step(&app, PipelineStage::Enhancement, enhanced)?;
+    result.srt = enhanced.srt;
+    result.usage.extend(enhanced.usage);
+    emit_pipeline_stage(&app, PipelineStage::Enhancement, PipelineStatus::Completed, "");
+    Ok(result)
 }
 
-#[tauri::command]
-async fn get_api_key() -> Result<String, String> {
-    let entry = Entry::new(SERVICE_NAME, API_KEY_ENTRY)
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-    
-    match entry.get_password() {
-        Ok(password) => Ok(password),
-        Err(keyring::Error::NoEntry) => Ok(String::new()),
-        Err(e) => Err(format!("Failed to retrieve API key: {}", e)),
+fn emit_pipeline_stage(app: &AppHandle, stage: PipelineStage, status: PipelineStatus, detail: &str) {
+    let payload = PipelineStageEvent {
+        stage,
+        status,
+        detail: detail.to_string(),
+    };
+    if let Err(e) = app.emit("pipeline-stage", payload) {
+        warn!("Failed to emit pipeline stage: {}", e);
     }
 }
 
-#[tauri::command]
-async fn delete_api_key() -> Result<bool, String> {
-    let entry = Entry::new(SERVICE_NAME, API_KEY_ENTRY)
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-    
-    match entry.delete_credential() {
-        Ok(_) => Ok(true),
-        Err(keyring::Error::NoEntry) => Ok(true), // Already deleted
-        Err(e) => Err(format!("Failed to delete API key: {}", e)),
-    }
+/// Emits a `failed` event for `stage` when `result` is an error
+fn pipeline_step<T>(app: &AppHandle, stage: PipelineStage, result: Result<T, AppError>) -> Result<T, AppError> {
+    result.inspect_err(|e| emit_pipeline_stage(app, stage, PipelineStatus::Failed, &e.to_string()))
 }
 
-#[tauri::command]
-async fn get_api_key_preview() -> Result<String, String> {
-    let entry = Entry::new(SERVICE_NAME, API_KEY_ENTRY)
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-    
-    match entry.get_password() {
-        Ok(password) => {
-            println!("DEBUG: Retrieved password, length: {}", password.len());
-            if password.trim().is_empty() {
-                println!("DEBUG: Password is empty");
-                Ok(String::new())
-            } else if password.len() > 4 {
-                let preview = format!("****{}", &password[password.len()-4..]);
-                println!("DEBUG: Generated preview: {}", preview);
-                Ok(preview)
-            } else {
-                println!("DEBUG: Password too short, using ****");
-                Ok("****".to_string())
-            }
-        },
-        Err(keyring::Error::NoEntry) => {
-            println!("DEBUG: No entry found in keyring");
-            Ok(String::new())
-        },
-        Err(e) => {
-            println!("DEBUG: Keyring error: {}", e);
-            Err(format!("Failed to retrieve API key: {}", e))
-        },
+/// Wraps every cue into at most [`MAX_CUE_LINES`] lines of
+/// `max_line_chars`; content that does not parse is returned unchanged
+fn wrap_srt_lines(srt: String, max_line_chars: u32) -> String {
+    match parse_srt(&srt) {
+        Ok(mut cues) => {
+            wrap_cue_lines(&mut cues, max_line_chars as usize, MAX_CUE_LINES);
+            serialize_srt(&cues)
+        }
+        Err(_) => srt,
     }
 }
 
-#[tauri::command]
-async fn debug_keyring() -> Result<String, String> {
-    let entry = Entry::new(SERVICE_NAME, API_KEY_ENTRY)
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-    
-    match entry.get_password() {
-        Ok(password) => {
-            Ok(format!("Found API key, length: {}, first 10 chars: {}", 
-                password.len(), 
-                if password.len() > 10 { &password[0..10] } else { &password }))
-        },
-        Err(keyring::Error::NoEntry) => Ok("No API key found in keyring".to_string()),
-        Err(e) => Err(format!("Keyring error: {}", e)),
+fn record_history(history: &HistoryStore, entry: NewHistoryEntry) {
+    if let Err(e) = history.insert(&entry) {
+        warn!("Failed to record transcription history: {}", e);
     }
 }
 
-#[tauri::command]
-async fn transcribe_audio(file_path: String, max_chars_per_subtitle: u32, enable_speaker_detection: bool, duration_ms: Option<u32>, model: Option<String>, api_key: String) -> Result<String, String> {
-    if api_key.trim().is_empty() {
-        return Err("API key is empty. Please set your Gemini API key in settings.".to_string());
+/// Saves a new session holding the transcription and returns its id; a
+/// failure is logged without failing the transcription
+fn start_session(
+    session_store: &SessionStore,
+    file_path: &str,
+    model: &str,
+    max_chars_per_subtitle: u32,
+    enable_speaker_detection: bool,
+    duration_ms: Option<u32>,
+    transcription: &str,
+) -> Option<String> {
+    let session = Session {
+        id: new_session_id(),
+        created_at: unix_timestamp(),
+        source_filename: source_file_name(file_path),
+        model: model.to_string(),
+        max_chars_per_subtitle,
+        enable_speaker_detection,
+        duration_ms,
+        transcription: transcription.to_string(),
+        topic: None,
+        dictionary: None,
+    };
+    match session_store.save(&session) {
+        Ok(()) => Some(session.id),
+        Err(e) => {
+            warn!("Failed to save transcription session: {}", e);
+            None
+        }
     }
+}
 
-    // Validate file exists
-    if !Path::new(&file_path).exists() {
-        return Err("Audio file not found".to_string());
+/// Records the result of a pipeline step in the session, if one was given
+fn update_session(session_store: &SessionStore, session_id: Option<&str>, update: impl FnOnce(&mut Session)) {
+    if let Some(session_id) = session_id {
+        if let Err(e) = session_store.update(session_id, update) {
+            warn!("Failed to update session: {}", e);
+        }
     }
+}
 
-    // Guess MIME type
-    let mime_type = mime_guess::from_path(&file_path)
-        .first_or_octet_stream()
-        .to_string();
-
-    // Create Gemini client
-    let client = GeminiClient::new(api_key);
+#[tauri::command]
+async fn get_session(session_store: State<'_, SessionStore>, session_id: String) -> Result<Session, String> {
+    session_store.get(&session_id)
+}
 
-    // Upload file to Gemini Files API
-    let file_info = client.upload_file(&file_path, &mime_type).await
-        .map_err(|e| format!("Failed to upload file: {}", e))?;
+/// Returns the original file name, without the prefix added by `save_temp_file`
+fn source_file_name(file_path: &str) -> Option<String> {
+    let file_name = Path::new(file_path).file_name()?.to_str()?;
+    let original = file_name
+        .strip_prefix("str_app_temp_")
+        .and_then(|rest| rest.split_once('_'))
+        .map_or(file_name, |(_, name)| name);
+    Some(original.to_string())
+}
 
-    // Wait for file processing
-    client.wait_for_file_processing(&file_info.name).await
-        .map_err(|e| format!("File processing failed: {}", e))?;
+#[tauri::command]
+async fn list_history(history: State<'_, HistoryStore>) -> Result<Vec<HistorySummary>, String> {
+    history.list()
+        .map_err(|e| format!("Failed to load history: {}", e))
+}
 
-    // Use provided model or default to gemini-2.0-flash
-    let selected_model = model.unwrap_or_else(|| "gemini-2.0-flash".to_string());
+#[tauri::command]
+async fn get_history_entry(history: State<'_, HistoryStore>, id: i64) -> Result<HistoryEntry, String> {
+    history.get(id)
+        .map_err(|e| format!("Failed to load history entry: {}", e))?
+        .ok_or_else(|| format!("History entry {} not found", id))
+}
 
-    // Generate prompt based on model type
-    let prompt = if selected_model.contains("gemini-2.0-flash") {
-        // Basic transcription prompt for initial transcription
-        "音声ファイルの内容を文字起こししてください。\n\n# 目的\nこの文字起こしは、会話のトピック分析と専門用語辞書作成のために使用します。\n\n# 要求事項\n1. **話者の発言を正確に文字起こし**\n2. **フィラーワード（えーっと、あのー等）も含めて全て記録**\n3. **専門用語や固有名詞は正確に記録**\n4. **会話の流れや文脈がわかるように**\n\n# 出力形式\n- プレーンテキストで出力\n- 話者が複数いる場合は「話者1:」「話者2:」等で区別\n- タイムスタンプは不要\n- 改行で発言を区切る\n\n**説明や前置きは不要です。文字起こしテキストのみを出力してください。**".to_string()
-    } else {
-        // Full SRT prompt for direct SRT generation
-        let duration_text = if let Some(duration) = duration_ms {
-            format!("\n\n**音声ファイルの長さ: {}分{}秒 ({}ms)**\n音声の長さを考慮して、適切な字幕の分割と表示タイミングを決定してください。", 
-                    duration / 60000, (duration % 60000) / 1000, duration)
-        } else {
-            String::new()
-        };
-        
-        let speaker_text = if enable_speaker_detection {
-            "\n    - **話者の区別:** 会話に複数の話者がいる場合は、各字幕の先頭に話者名を明記してください。（例: `アオイ: `、`ユーザー: `）"
-        } else {
-            "\n    - **話者の区別:** 話者名は付けず、純粋な発話内容のみを記録してください。"
-        };
-        
-        format!(r#"提供する音声（または動画）ファイルの内容を、高品質なSRT（SubRip Text）ファイル形式で文字起こししてください。{}
+#[tauri::command]
+async fn delete_history_entry(history: State<'_, HistoryStore>, id: i64) -> Result<bool, String> {
+    history.delete(id)
+        .map_err(|e| format!("Failed to delete history entry: {}", e))
+}
 
-# 1. SRTファイルの基本構造について
+#[tauri::command]
+#[allow(non_snake_case)]
+async fn save_dictionary_csv(
+    app: AppHandle,
+    content: String,
+    suggestedFilename: String,
+    target_dir: Option<String>,
+) -> Result<String, String> {
+    debug!("save_dictionary_csv called with filename: {}, content length: {}", suggestedFilename, content.len());
+    
+    // 保存先フォルダ（未指定ならダウンロードフォルダ）に辞書CSVを保存
+    let output_dir = resolve_output_dir(&app, target_dir)?;
+    let base_name = sanitize_filename(suggestedFilename.trim_end_matches(".csv"));
+    let file_path = unique_path(&output_dir, &format!("{}_dictionary", base_name), "csv")?;
+    
+    write_output_file(&file_path, &content, &EncodingOptions::default()).await
+}
 
-まず、納品していただくSRTファイルの構造について共通認識を持つために、基本的なルールを説明します。SRTファイルは、以下の4つの要素が1セットとなって構成されるテキストファイルです。
+#[tauri::command]
+async fn load_dictionary_csv(file_path: String) -> Result<String, String> {
+    fs::read_to_string(&file_path).await
+        .map_err(|e| format!("Failed to read dictionary file: {}", e))
+}
 
-1.  **通し番号:** `1`から始まる字幕の連番です。
-2.  **タイムスタンプ:** `時:分:秒,ミリ秒 --> 時:分:秒,ミリ秒` の形式で、字幕の表示開始時間と終了時間を指定します。（例: `00:01:23,456 --> 00:01:28,912`）
-3.  **字幕テキスト:** 画面に表示する文章です。改行を含めず、インラインで記述してください
-4.  **空行:** 各字幕ブロックを区切るための、何も書かれていない行です。必ず必要です
+/// Combines several dictionary CSV files into one sorted dictionary
+#[tauri::command]
+async fn merge_dictionaries(paths: Vec<String>) -> Result<String, String> {
+    if paths.is_empty() {
+        return Err("No dictionaries to merge".to_string());
+    }
 
-**【具体例】**
-1
-00:00:05,520 --> 00:00:08,910
-これは1番目の字幕の
-テキストです。
+    let mut sources = Vec::with_capacity(paths.len());
+    for path in paths {
+        let content = load_dictionary_csv(path.clone()).await?;
+        let entries = validate_dictionary_csv(&content).map_err(|e| format!("{}: {}", path, e))?;
+        sources.push((source_file_name(&path).unwrap_or(path), entries));
+    }
+    dictionary_to_csv(&merge_dictionary_entries(&sources)?)
+}
 
-2
-00:00:09,150 --> 00:00:11,300
-そして、これが2番目の字幕です。
+#[tauri::command]
+async fn load_subtitle_file(path: String) -> Result<String, String> {
+    let content = fs::read_to_string(&path).await
+        .map_err(|e| format!("Failed to read subtitle file: {}", e))?;
+    
+    let extension = Path::new(&path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .unwrap_or_default();
+    let is_vtt = content.trim_start_matches('\u{feff}').starts_with("WEBVTT") || extension == "vtt";
+    
+    if is_vtt {
+        vtt_to_srt(&content)
+    } else if extension == "srt" {
+        let cues = parse_srt(&content)
+            .map_err(|e| format!("Invalid SRT file: {}", e))?;
+        Ok(serialize_srt(&cues))
+    } else {
+        Err(format!("Unsupported subtitle format: .{}", extension))
+    }
+}
 
-この構造を厳密に守ってファイルを作成してください。.srtファイルとして納品してください
+#[tauri::command]
+async fn save_temp_file(file_data: Vec<u8>, file_name: String, mime_override: Option<String>) -> Result<String, String> {
+    let temp_dir = std::env::temp_dir();
+    let mut temp_file_name = format!("str_app_temp_{}_{}", unix_timestamp(), sanitize_filename(&file_name));
+    
+    // Make sure the extension matches the overridden type so it can be resolved later
+    if let Some(mime_override) = mime_override.as_deref() {
+        let mime_type = resolve_mime_type(&temp_file_name, Some(mime_override))?;
+        if resolve_mime_type(&temp_file_name, None).ok().as_deref() != Some(mime_type.as_str()) {
+            if let Some(extension) = extension_for_mime_type(&mime_type) {
+                temp_file_name = format!("{}.{}", temp_file_name, extension);
+            }
+        }
+    }
+    let temp_file_path = temp_dir.join(&temp_file_name);
+    
+    fs::write(&temp_file_path, &file_data).await
+        .map_err(|e| format!("Failed to save temporary file: {}", e))?;
+    
+    Ok(temp_file_path.to_string_lossy().to_string())
+}
 
-# 2. 文字起こしの詳細なルール
+#[tauri::command]
+#[allow(non_snake_case)]
+async fn save_srt_file(
+    app: AppHandle,
+    content: String,
+    suggestedFilename: String,
+    encoding_options: Option<EncodingOptions>,
+    target_dir: Option<String>,
+) -> Result<String, String> {
+    debug!("save_srt_file called with filename: {}, content length: {}", suggestedFilename, content.len());
+    
+    // 保存先フォルダ（未指定ならダウンロードフォルダ）に保存
+    let output_dir = resolve_output_dir(&app, target_dir)?;
+    let base_name = sanitize_filename(suggestedFilename.trim_end_matches(".srt"));
+    let file_path = unique_path(&output_dir, &base_name, "srt")?;
+    
+    write_output_file(&file_path, &content, &encoding_options.unwrap_or_default()).await
+}
 
-上記の基本構造を踏まえ、以下の詳細なルールに従って作業を進めてください。
+#[tauri::command]
+async fn merge_subtitles(srt: String, min_chars: u32, max_gap_ms: u32) -> Result<String, String> {
+    merge_short_cues(&srt, min_chars, max_gap_ms)
+}
 
-1.  **タイムスタンプの精度**
-    - `hh:mm:ss,ms` の形式を厳守し、ミリ秒は3桁で記述してください。
-    - 音声の発話タイミングと字幕の表示タイミングを正確に一致させてください。
+#[tauri::command]
+async fn split_subtitles(srt: String, max_chars: u32) -> Result<String, String> {
+    split_long_cues(&srt, max_chars)
+}
 
-2.  **字幕テキストの編集ルール**
-    - **文字数制限:** 1つの字幕ブロック（通し番号1つにつき）のテキストは、**{}文字以内**を目安にしてください。長くなる場合は、意味の区切りが良い箇所で改行するなど、読みやすさを最優先してください。
-    - **フィラーワードの削除:** 会話中の「えーっと」「あのー」「なんか」といった、意味を持たないフィラーワードはすべて削除し、自然で聞き取りやすい文章にしてください。{}
+#[tauri::command]
+async fn shift_subtitles(srt: String, offset_ms: i64) -> Result<String, String> {
+    shift_srt(&srt, offset_ms)
+}
 
-3.  **品質要求**
-    - 字幕として読みやすく、視聴者にとって理解しやすい文章にしてください。
-    - 音声が不明瞭な部分は [不明瞭] として記録してください。
-    - 無音部分や間は適切に反映し、字幕の切り替えタイミングを自然にしてください。
+/// Lists `[不明瞭]` markers (and any `extra_markers`) with their cue timing
+/// so the player can jump to them
+#[tauri::command]
+async fn find_unclear_segments(srt: String, extra_markers: Option<Vec<String>>) -> Result<Vec<UnclearSegment>, String> {
+    find_unclear(&srt, &extra_markers.unwrap_or_default())
+}
 
-**時間の精度が重要です。時間が合っているか確認をしたのち、最終的にSRT形式のテキストのみを出力してください。説明や前置きは不要です。**"#, duration_text, max_chars_per_subtitle, speaker_text)
-    };
+/// Joins subtitles of a recording split into parts, each given as its SRT
+/// content and the offset in milliseconds at which the part starts
+#[tauri::command]
+async fn merge_srt_files(parts: Vec<(String, u64)>, tolerance_ms: Option<u64>) -> Result<String, String> {
+    if parts.is_empty() {
+        return Err("No subtitle parts to merge".to_string());
+    }
+    merge_srt_parts(&parts, tolerance_ms.unwrap_or(DEFAULT_SEAM_TOLERANCE_MS))
+}
 
-    // Generate transcription
-    let raw_transcription = client.generate_content(&file_info.uri, &file_info.mime_type, &prompt, &selected_model).await
-        .map_err(|e| format!("Failed to generate transcription: {}", e))?;
+/// Cuts subtitles in two at `split_at_ms`, e.g. when the video is split for
+/// upload; the second half starts at 00:00:00
+#[tauri::command]
+async fn split_srt(content: String, split_at_ms: u64) -> Result<(String, String), String> {
+    split_srt_at(&content, split_at_ms)
+}
 
-    // Extract SRT content, removing any code block markers
-    let transcription = extract_srt_content(&raw_transcription);
+/// Indexes SRT content for `get_cue_at` and `get_adjacent_cues`, replacing
+/// the previous subtitles; returns the number of cues
+#[tauri::command]
+fn load_srt_into_session(srt_index: State<'_, SrtIndex>, content: String) -> Result<usize, String> {
+    let cues = parse_srt(&content)?;
+    let count = cues.len();
+    srt_index.load(cues);
+    Ok(count)
+}
 
-    Ok(transcription.to_string())
+/// The cue showing at `time_ms`, or `None` between cues
+#[tauri::command]
+fn get_cue_at(srt_index: State<'_, SrtIndex>, time_ms: u64) -> Option<SubtitleCue> {
+    srt_index.cue_at(time_ms)
 }
 
+/// The cue showing at `time_ms` with up to `n` cues before and after it
 #[tauri::command]
-async fn get_transcription_progress() -> Result<String, String> {
-    // This could be enhanced to track upload/processing progress
-    Ok("Processing...".to_string())
+fn get_adjacent_cues(srt_index: State<'_, SrtIndex>, time_ms: u64, n: usize) -> AdjacentCues {
+    srt_index.adjacent(time_ms, n)
 }
 
+/// Duration of an audio or video file in milliseconds, read from its headers
 #[tauri::command]
-async fn analyze_topic(transcription: String, api_key: String) -> Result<String, String> {
-    if api_key.trim().is_empty() {
-        return Err("API key is empty".to_string());
-    }
+async fn probe_audio_duration(file_path: String) -> Result<u32, String> {
+    probe_duration_ms(Path::new(&file_path))
+}
 
-    let client = GeminiClient::new(api_key);
-    
-    // トピック分析用プロンプト
-    let prompt = format!("以下の文字起こしテキストを分析して、会話の主なトピックを特定してください。\n\n# 文字起こしテキスト\n{}\n\n# 要求事項\n**頻出する専門用語や固有名詞をリストアップ**\n\n# 出力形式\nキーワード: [重要な用語をカンマ区切り]\n\n**簡潔に出力してください。**", transcription);
-    
-    let analysis = client.generate_text_content(&prompt, "gemini-2.0-flash").await
-        .map_err(|e| format!("Failed to analyze topic: {}", e))?;
+/// Cue count, coverage, reading speed, per-speaker talk time and the number
+/// of unclear-speech markers
+#[tauri::command]
+async fn get_srt_statistics(content: String, duration_ms: Option<u32>) -> Result<SrtStatistics, String> {
+    srt_statistics(&content, duration_ms.map(u64::from))
+}
 
-    Ok(analysis)
+/// Sorts cues, resolves overlapping timestamps and renumbers them so that
+/// strict players accept the file
+#[tauri::command]
+async fn normalize_subtitles(srt: String, min_gap_ms: Option<u64>) -> Result<String, String> {
+    let mut cues = parse_srt(&srt)?;
+    let fixed = fix_overlaps(&mut cues, min_gap_ms.unwrap_or(0));
+    debug!("normalize_subtitles: adjusted {} overlapping cues", fixed);
+    renumber(&mut cues);
+    Ok(serialize_srt(&cues))
 }
 
+/// Extends or merges cues shorter than `min_ms` and splits cues longer than
+/// `max_ms`; the limits default to 1 and 7 seconds
 #[tauri::command]
-async fn create_dictionary(topic: String, api_key: String) -> Result<String, String> {
-    if api_key.trim().is_empty() {
-        return Err("API key is empty".to_string());
+async fn enforce_duration_limits(srt: String, min_ms: Option<u64>, max_ms: Option<u64>) -> Result<DurationLimitResult, String> {
+    let limits = DurationLimits {
+        min_ms: min_ms.unwrap_or(DEFAULT_MIN_CUE_MS),
+        max_ms: max_ms.unwrap_or(DEFAULT_MAX_CUE_MS),
+    };
+    if limits.min_ms > limits.max_ms {
+        return Err(format!("min_ms ({}) must not exceed max_ms ({})", limits.min_ms, limits.max_ms));
     }
+    let (cues, adjustments) = srt_utils::enforce_duration_limits(parse_srt(&srt)?, limits);
+    debug!("enforce_duration_limits: {:?}", adjustments);
+    Ok(DurationLimitResult {
+        srt: serialize_srt(&cues),
+        adjustments,
+    })
+}
 
-    let client = GeminiClient::new(api_key);
-    
-    // Google検索を使って正確な情報を取得した辞書作成用プロンプト
-    let prompt = format!(
-        "{}に出てくる用語の辞書を構築して。\n表記、ふりがなのみをセットでcsv形式で記載してください。topic自体に誤字脱字がないか確認してから、辞書を作成してください。\n日本語話者がわかるような辞書にしてください。固有名詞は正式な表記が何か調べてください。\n**「自己紹介と職務経歴に関するIT分野の用語集ですね。..に関する用語を調べ、CSV形式で出力します」といった説明や補足、```csv ... ```のようなコードブロックの囲いなどCSVと関係ないものは一切禁止されています。CSVデータのみを出力してください。**", 
-        topic
-    );
-    
-    let (dictionary, search_info) = client.generate_text_content_with_search(&prompt, "gemini-2.5-pro").await
-        .map_err(|e| format!("Failed to create dictionary with search: {}", e))?;
-
-    // 検索情報をログに出力（デバッグ用）
-    if let Some(search_content) = search_info {
-        println!("Search grounding info: {}", search_content);
+/// Extends cues shorter than `min_ms` up to `min_gap_ms` before the next cue
+/// and cuts cues longer than `max_ms`; only end times change
+#[tauri::command]
+async fn enforce_subtitle_durations(srt: String, min_ms: u64, max_ms: u64, min_gap_ms: Option<u64>) -> Result<String, String> {
+    if min_ms > max_ms {
+        return Err(format!("min_ms ({}) must not exceed max_ms ({})", min_ms, max_ms));
     }
-
-    Ok(dictionary)
+    let mut cues = parse_srt(&srt)?;
+    let adjusted = enforce_durations(&mut cues, min_ms, max_ms, min_gap_ms.unwrap_or(0));
+    debug!("enforce_subtitle_durations: adjusted {} cues", adjusted);
+    renumber(&mut cues);
+    Ok(serialize_srt(&cues))
 }
 
+/// Lists over-length lines, cues shown too briefly and cues too fast to read
+/// so the editor can highlight them
 #[tauri::command]
-async fn enhance_transcription_with_dictionary(
-    initial_transcription: String, 
-    dictionary: String, 
-    max_chars_per_subtitle: u32,
-    enable_speaker_detection: bool,
-    duration_ms: Option<u32>,
-    api_key: String
-) -> Result<String, String> {
-    if api_key.trim().is_empty() {
-        return Err("API key is empty".to_string());
-    }
+async fn lint_subtitles(srt: String, max_chars: u32) -> Result<Vec<LintWarning>, String> {
+    lint_srt(&srt, max_chars)
+}
 
-    let client = GeminiClient::new(api_key);
-    
-    // 既存の文字起こしを辞書を使ってSRT形式に変換するプロンプト
-    let duration_text = if let Some(duration) = duration_ms {
-        format!("**音声ファイルの長さ: {}分{}秒 ({}ms)**\n音声の長さを考慮して、適切な字幕の分割と表示タイミングを決定してください。\n\n", 
-                duration / 60000, (duration % 60000) / 1000, duration)
-    } else {
-        String::new()
-    };
-    
-    let prompt = format!(
-        r#"提供する音声（または動画）ファイルの内容を、高品質なSRT（SubRip Text）ファイル形式で文字起こししてください。{}
+/// Scores each cue from 0 to 1 by how likely its text is right, so the
+/// editor can jump to the cues most in need of review
+#[tauri::command]
+async fn score_subtitles(srt: String) -> Result<Vec<CueQuality>, String> {
+    let cues = parse_srt(&srt)?;
+    let scores = estimate_cue_quality(&cues);
+    Ok(cues
+        .iter()
+        .zip(scores)
+        .map(|(cue, score)| CueQuality {
+            index: cue.index,
+            start_ms: cue.start_ms,
+            end_ms: cue.end_ms,
+            score,
+        })
+        .collect())
+}
 
-# 専門用語辞書
-以下の辞書を参考に、専門用語の表記を統一してください：
+/// Strips HTML tags and markdown emphasis from cue text. Tags in
+/// `allowed_tags` are kept; by default only `<i>`.
+#[tauri::command]
+async fn strip_markup_tags(srt: String, allowed_tags: Option<Vec<String>>) -> Result<String, String> {
+    match allowed_tags {
+        Some(tags) => sanitize_markup(&srt, &tags.iter().map(String::as_str).collect::<Vec<_>>()),
+        None => sanitize_markup(&srt, &DEFAULT_ALLOWED_TAGS),
+    }
+}
 
-{}
+/// Removes filler words from the subtitles; without `filler_list` the list
+/// saved for `language`, or that language's defaults, is used. `language`
+/// is an ISO 639-1 code and defaults to Japanese.
+#[tauri::command]
+async fn remove_fillers(
+    app: AppHandle,
+    srt: String,
+    filler_list: Option<Vec<String>>,
+    language: Option<String>,
+) -> Result<String, String> {
+    let fillers = filler_list.unwrap_or_else(|| {
+        filler_words_or_defaults(&app, &TranscriptionLanguage::from_code(language.as_deref()), None)
+    });
+    remove_filler_words(&srt, &fillers.iter().map(String::as_str).collect::<Vec<_>>())
+}
 
-# 元の文字起こし
-{}
+#[tauri::command]
+async fn strip_speaker_labels(srt: String) -> Result<String, String> {
+    remove_speaker_labels(&srt)
+}
 
-# 1. SRTファイルの基本構造について
+#[tauri::command]
+async fn rename_speakers(srt: String, mapping: HashMap<String, String>) -> Result<String, String> {
+    remap_speaker_labels(&srt, &mapping)
+}
 
-まず、納品していただくSRTファイルの構造について共通認識を持つために、基本的なルールを説明します。SRTファイルは、以下の4つの要素が1セットとなって構成されるテキストファイルです。
+/// Applies the dictionary's misspelling column to the subtitles without
+/// calling Gemini
+#[tauri::command]
+async fn apply_dictionary_locally(srt: String, dictionary_csv: String) -> Result<LocalDictionaryResult, String> {
+    let terms = parse_dictionary_csv(&dictionary_csv);
+    if terms.is_empty() {
+        return Err("Dictionary is empty".to_string());
+    }
+    apply_dictionary(&srt, &terms)
+}
 
-1.  **通し番号:** `1`から始まる字幕の連番です。
-2.  **タイムスタンプ:** `時:分:秒,ミリ秒 --> 時:分:秒,ミリ秒` の形式で、字幕の表示開始時間と終了時間を指定します。（例: `00:01:23,456 --> 00:01:28,912`）
-3.  **字幕テキスト:** 画面に表示する文章です。改行を含めず、インラインで記述してください
-4.  **空行:** 各字幕ブロックを区切るための、何も書かれていない行です。必ず必要です
+/// Compares two transcriptions of the same audio, e.g. before and after
+/// `enhance_transcription_with_dictionary`
+#[tauri::command]
+async fn diff_transcriptions(
+    before_srt: String,
+    after_srt: String,
+    timing_threshold_ms: Option<u64>,
+) -> Result<TranscriptionDiff, String> {
+    diff_srt(&before_srt, &after_srt, timing_threshold_ms.unwrap_or(DEFAULT_TIMING_THRESHOLD_MS))
+}
 
-**【具体例】**
-1
-00:00:05,520 --> 00:00:08,910
-これは1番目の字幕の
-テキストです。
+/// Compares the cue texts of two transcriptions matched by sequence number,
+/// e.g. to highlight what `enhance_transcription_with_dictionary` changed
+/// without its timestamp adjustments
+#[tauri::command]
+async fn diff_transcription_cues(before_srt: String, after_srt: String) -> Result<Vec<DiffHunk>, String> {
+    diff_srt_by_index(&before_srt, &after_srt)
+}
 
-2
-00:00:09,150 --> 00:00:11,300
-そして、これが2番目の字幕です。
+#[tauri::command]
+async fn convert_srt_to_vtt(content: String, enable_speaker_detection: bool) -> Result<String, String> {
+    srt_to_vtt(&content, enable_speaker_detection)
+}
 
-この構造を厳密に守ってファイルを作成してください。.srtファイルとして納品してください
+#[tauri::command]
+async fn save_vtt_file(
+    app: AppHandle,
+    content: String,
+    suggested_filename: String,
+    encoding_options: Option<EncodingOptions>,
+    target_dir: Option<String>,
+) -> Result<String, String> {
+    debug!("save_vtt_file called with filename: {}, content length: {}", suggested_filename, content.len());
+    
+    let output_dir = resolve_output_dir(&app, target_dir)?;
+    let base_name = sanitize_filename(suggested_filename.trim_end_matches(".vtt"));
+    let file_path = unique_path(&output_dir, &base_name, "vtt")?;
+    
+    write_output_file(&file_path, &content, &encoding_options.unwrap_or_default()).await
+}
 
-# 2. 文字起こしの詳細なルール
+#[tauri::command]
+async fn export_ass(content: String, style_options: Option<AssStyle>, enable_speaker_detection: bool) -> Result<String, String> {
+    srt_to_ass(&content, &style_options.unwrap_or_default(), enable_speaker_detection)
+}
 
-上記の基本構造を踏まえ、以下の詳細なルールに従って作業を進めてください。
+/// Converts SRT content to ASS and saves it to the output folder; without
+/// `style_options` the default font, size and colour are used
+#[tauri::command]
+async fn save_ass_file(
+    app: AppHandle,
+    content: String,
+    suggested_filename: String,
+    style_options: Option<AssStyle>,
+    enable_speaker_detection: bool,
+    encoding_options: Option<EncodingOptions>,
+    target_dir: Option<String>,
+) -> Result<String, String> {
+    debug!("save_ass_file called with filename: {}, content length: {}", suggested_filename, content.len());
 
-1.  **タイムスタンプの精度**
-    - `hh:mm:ss,ms` の形式を厳守し、ミリ秒は3桁で記述してください。
-    - 音声の発話タイミングと字幕の表示タイミングを正確に一致させてください。
+    let ass = srt_to_ass(&content, &style_options.unwrap_or_default(), enable_speaker_detection)?;
+    let output_dir = resolve_output_dir(&app, target_dir)?;
+    let base_name = sanitize_filename(suggested_filename.trim_end_matches(".ass").trim_end_matches(".srt"));
+    let file_path = unique_path(&output_dir, &base_name, "ass")?;
 
-2.  **字幕テキストの編集ルール**
-    - **文字数制限:** 1つの字幕ブロック（通し番号1つにつき）のテキストは、**{}文字以内**を目安にしてください。長くなる場合は、意味の区切りが良い箇所で改行するなど、読みやすさを最優先してください。
-    - **フィラーワードの削除:** 会話中の「えーっと」「あのー」「なんか」といった、意味を持たないフィラーワードはすべて削除し、自然で聞き取りやすい文章にしてください。{}
+    write_output_file(&file_path, &ass, &encoding_options.unwrap_or_default()).await
+}
 
-3.  **品質要求**
-    - 字幕として読みやすく、視聴者にとって理解しやすい文章にしてください。
-    - 音声が不明瞭な部分は [不明瞭] として記録してください。
-    - 無音部分や間は適切に反映し、字幕の切り替えタイミングを自然にしてください。
+#[tauri::command]
+async fn export_transcript(content: String, format: TranscriptFormat, include_timestamps: Option<bool>) -> Result<String, String> {
+    srt_to_transcript(&content, format, include_timestamps.unwrap_or(false))
+}
 
-**時間の精度が重要です。時間が合っているか確認をしたのち、最終的にSRT形式のテキストのみを出力してください。説明や前置きは不要です。**
-"#,
-        duration_text,
-        dictionary,
-        initial_transcription,
-        max_chars_per_subtitle,
-        if enable_speaker_detection { 
-            "\n    - **話者の区別:** 会話に複数の話者がいる場合は、各字幕の先頭に話者名を明記してください。（例: `アオイ: `、`ユーザー: `）" 
-        } else { 
-            "\n    - **話者の区別:** 話者名は付けず、純粋な発話内容のみを記録してください。" 
-        }
-    );
+#[tauri::command]
+async fn save_transcript_file(
+    app: AppHandle,
+    content: String,
+    suggested_filename: String,
+    format: TranscriptFormat,
+    target_dir: Option<String>,
+) -> Result<String, String> {
+    debug!("save_transcript_file called with filename: {}, content length: {}", suggested_filename, content.len());
     
-    let raw_enhanced_result = client.generate_text_content(&prompt, "gemini-2.5-pro").await
-        .map_err(|e| format!("Failed to enhance transcription: {}", e))?;
-
-    // Extract SRT content, removing any code block markers
-    let enhanced_result = extract_srt_content(&raw_enhanced_result);
+    let output_dir = resolve_output_dir(&app, target_dir)?;
+    let extension = format.extension();
+    let base_name = sanitize_filename(suggested_filename.trim_end_matches(&format!(".{}", extension)));
+    let file_path = unique_path(&output_dir, &base_name, extension)?;
+    
+    write_output_file(&file_path, &content, &EncodingOptions::default()).await
+}
 
-    Ok(enhanced_result.to_string())
+#[tauri::command]
+async fn export_srt_as_csv(content: String) -> Result<String, String> {
+    srt_to_csv(&content)
 }
 
 #[tauri::command]
-async fn save_dictionary_csv(content: String, suggestedFilename: String) -> Result<String, String> {
-    println!("save_dictionary_csv called with filename: {}, content length: {}", suggestedFilename, content.len());
-    
-    // ダウンロードフォルダに辞書CSVを保存
-    let downloads_dir = dirs::download_dir()
-        .ok_or("Could not find downloads directory")?;
-    
-    println!("Downloads directory: {:?}", downloads_dir);
-    
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    
-    let base_name = suggestedFilename.trim_end_matches(".csv");
-    // ファイル名から不正な文字を除去
-    let safe_base_name = base_name
-        .chars()
-        .map(|c| match c {
-            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
-            _ => c
-        })
-        .collect::<String>();
-    let unique_filename = format!("{}_dictionary_{}.csv", safe_base_name, timestamp);
-    let file_path = downloads_dir.join(&unique_filename);
-    
-    println!("Attempting to write dictionary file to: {:?}", file_path);
-    
-    fs::write(&file_path, content.as_bytes()).await
-        .map_err(|e| {
-            println!("Failed to write dictionary file: {}", e);
-            format!("Failed to write dictionary file: {}", e)
-        })?;
-    
-    println!("Dictionary file written successfully");
-    
-    Ok(file_path.to_string_lossy().to_string())
+async fn export_srt_as_json(content: String) -> Result<String, String> {
+    srt_to_json(&content)
 }
 
 #[tauri::command]
-async fn load_dictionary_csv(file_path: String) -> Result<String, String> {
-    fs::read_to_string(&file_path).await
-        .map_err(|e| format!("Failed to read dictionary file: {}", e))
+async fn export_sbv(content: String, keep_speaker_labels: bool) -> Result<String, String> {
+    srt_to_sbv(&content, keep_speaker_labels)
 }
 
 #[tauri::command]
-async fn save_temp_file(file_data: Vec<u8>, file_name: String) -> Result<String, String> {
-    let temp_dir = std::env::temp_dir();
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    
-    let safe_file_name = file_name
-        .chars()
-        .map(|c| match c {
-            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
-            _ => c
-        })
-        .collect::<String>();
-    
-    let temp_file_name = format!("str_app_temp_{}_{}", timestamp, safe_file_name);
-    let temp_file_path = temp_dir.join(&temp_file_name);
+async fn save_sbv_file(
+    app: AppHandle,
+    content: String,
+    suggested_filename: String,
+    target_dir: Option<String>,
+) -> Result<String, String> {
+    debug!("save_sbv_file called with filename: {}, content length: {}", suggested_filename, content.len());
     
-    fs::write(&temp_file_path, &file_data).await
-        .map_err(|e| format!("Failed to save temporary file: {}", e))?;
+    let output_dir = resolve_output_dir(&app, target_dir)?;
+    let base_name = sanitize_filename(suggested_filename.trim_end_matches(".sbv"));
+    let file_path = unique_path(&output_dir, &base_name, "sbv")?;
     
-    Ok(temp_file_path.to_string_lossy().to_string())
+    write_output_file(&file_path, &content, &EncodingOptions::default()).await
 }
 
 #[tauri::command]
-async fn save_srt_file(content: String, suggestedFilename: String) -> Result<String, String> {
-    println!("save_srt_file called with filename: {}, content length: {}", suggestedFilename, content.len());
-    
-    // デバッグのため最初の100文字を出力
-    if content.len() > 100 {
-        println!("Content preview: {}...", &content[..100]);
-    } else {
-        println!("Content: {}", content);
-    }
-    
-    // ダウンロードフォルダに保存
-    let downloads_dir = dirs::download_dir()
-        .ok_or("Could not find downloads directory")?;
+async fn export_audacity_labels(content: String) -> Result<String, String> {
+    srt_to_audacity_labels(&content)
+}
+
+#[tauri::command]
+async fn save_audacity_labels(
+    app: AppHandle,
+    content: String,
+    suggested_filename: String,
+    target_dir: Option<String>,
+) -> Result<String, String> {
+    debug!("save_audacity_labels called with filename: {}, content length: {}", suggested_filename, content.len());
     
-    println!("Downloads directory: {:?}", downloads_dir);
+    let output_dir = resolve_output_dir(&app, target_dir)?;
+    let base_name = sanitize_filename(suggested_filename.trim_end_matches(".txt"));
+    let file_path = unique_path(&output_dir, &format!("{}_labels", base_name), "txt")?;
     
-    let timestamp = std::time::SystemTime::now()
+    write_output_file(&file_path, &content, &EncodingOptions::default()).await
+}
+
+fn unix_timestamp() -> u64 {
+    std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap()
-        .as_secs();
-    
-    let base_name = suggestedFilename.trim_end_matches(".srt");
-    // ファイル名から不正な文字を除去
-    let safe_base_name = base_name
-        .chars()
-        .map(|c| match c {
-            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
-            _ => c
-        })
-        .collect::<String>();
-    let unique_filename = format!("{}_{}.srt", safe_base_name, timestamp);
-    let file_path = downloads_dir.join(&unique_filename);
-    
-    println!("Attempting to write SRT file to: {:?}", file_path);
+        .as_secs()
+}
+
+fn downloads_dir() -> Result<PathBuf, String> {
+    dirs::download_dir().ok_or_else(|| "Could not find downloads directory".to_string())
+}
+
+/// `target_dir` when given, else the directory set with
+/// `set_default_output_dir`, else Downloads. A default directory that has
+/// since disappeared falls back to Downloads; an invalid `target_dir` is an error.
+fn resolve_output_dir(app: &AppHandle, target_dir: Option<String>) -> Result<PathBuf, String> {
+    if let Some(dir) = target_dir.filter(|dir| !dir.trim().is_empty()) {
+        let dir = PathBuf::from(dir.trim());
+        validate_output_dir(&dir)?;
+        return Ok(dir);
+    }
+    if let Some(dir) = load_output_dir(app) {
+        let dir = PathBuf::from(dir);
+        match validate_output_dir(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) => warn!("{}; saving to Downloads instead", e),
+        }
+    }
+    downloads_dir()
+}
+
+/// Writes `content` and returns the path it was written to
+async fn write_output_file(
+    file_path: &Path,
+    content: &str,
+    encoding_options: &EncodingOptions,
+) -> Result<String, String> {
+    debug!("Attempting to write file to: {:?}", file_path);
     
-    fs::write(&file_path, content.as_bytes()).await
-        .map_err(|e| {
-            println!("Failed to write SRT file: {}", e);
-            format!("Failed to write SRT file: {}", e)
-        })?;
+    write_encoded(file_path, content, encoding_options).await
+        .inspect_err(|e| error!("{}", e))?;
     
-    println!("SRT file written successfully");
+    info!("File written successfully");
     
     Ok(file_path.to_string_lossy().to_string())
 }
@@ -499,17 +3129,127 @@ pub fn run() {
             get_api_key,
             delete_api_key,
             get_api_key_preview,
+            get_key_storage_backend,
             debug_keyring,
+            add_api_key,
+            get_base_url,
+            set_base_url,
+            get_auth_mode,
+            set_auth_mode,
+            get_proxy,
+            set_proxy,
+            get_proxy_username,
+            set_proxy_credentials,
+            get_ca_certificate_path,
+            set_ca_certificate_path,
+            test_connection,
+            validate_api_key,
+            get_safety_settings,
+            set_safety_settings,
+            get_generation_settings,
+            get_filler_words,
+            set_filler_words,
+            set_generation_settings,
+            get_default_output_dir,
+            set_default_output_dir,
+            get_retry_settings,
+            get_concurrency_limits,
+            set_concurrency_limits,
+            set_retry_settings,
+            get_timeout_settings,
+            set_timeout_settings,
+            get_log_file_path,
+            get_log_level,
+            set_log_level,
+            list_api_keys,
+            remove_api_key,
             transcribe_audio,
+            probe_audio_duration,
+            estimate_transcription_cost,
+            detect_language,
+            preview_transcription_prompt,
+            get_prompt_template,
+            set_prompt_template,
+            transcribe_audio_chunked,
+            transcribe_with_speakers,
+            transcribe_batch,
+            transcribe_word_level,
+            convert_word_timings_to_srt,
+            cancel_transcription,
             get_transcription_progress,
+            get_available_models,
+            list_remote_files,
+            delete_remote_file,
             analyze_topic,
             create_dictionary,
             enhance_transcription_with_dictionary,
+            get_session,
+            get_session_usage,
+            enhance_from_session,
+            run_full_pipeline,
+            translate_srt,
             save_dictionary_csv,
             load_dictionary_csv,
+            merge_dictionaries,
+            load_subtitle_file,
             save_temp_file,
             save_srt_file,
+            list_history,
+            get_history_entry,
+            delete_history_entry,
+            merge_subtitles,
+            split_subtitles,
+            shift_subtitles,
+            merge_srt_files,
+            split_srt,
+            load_srt_into_session,
+            get_cue_at,
+            get_adjacent_cues,
+            get_srt_statistics,
+            find_unclear_segments,
+            normalize_subtitles,
+            enforce_duration_limits,
+            remove_fillers,
+            strip_markup_tags,
+            lint_subtitles,
+            score_subtitles,
+            enforce_subtitle_durations,
+            strip_speaker_labels,
+            rename_speakers,
+            apply_dictionary_locally,
+            diff_transcriptions,
+            diff_transcription_cues,
+            convert_srt_to_vtt,
+            save_vtt_file,
+            export_ass,
+            save_ass_file,
+            export_transcript,
+            save_transcript_file,
+            export_srt_as_csv,
+            export_srt_as_json,
+            export_sbv,
+            save_sbv_file,
+            export_audacity_labels,
+            save_audacity_labels,
         ])
+        .setup(|app| {
+            let data_dir = app.path().app_data_dir()?;
+            std::fs::create_dir_all(&data_dir)?;
+            let log = logging::init(data_dir.join("logs").join(LOG_FILE_NAME), load_log_level(app.handle()))?;
+            info!("Logging to {:?}", log.path());
+            app.manage(log);
+            app.manage(HistoryStore::open(&data_dir.join("history.db"))?);
+            app.manage(UploadCache::load(data_dir.join("upload_cache.json")));
+            app.manage(KeyStorage::new(data_dir.join("api_keys.enc")));
+            app.manage(TranscriptionLock::default());
+            app.manage(ProgressTracker::default());
+            app.manage(SrtIndex::default());
+            app.manage(UsageTracker::default());
+            app.manage(ClientCache::default());
+            app.manage(SessionStore::new(std::env::temp_dir().join("str_app_sessions")));
+            app.manage(PromptTemplates::load(app.path().app_config_dir()?.join("prompts")));
+            Ok(())
+        })
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_opener::init())
@@ -521,16 +3261,28 @@ pub fn run() {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_api_key_validation_status() {
+        let status = |result| ApiKeyValidation::from_result(result).status;
+        assert_eq!(status(Ok(())), ApiKeyStatus::Valid);
+        assert_eq!(status(Err(GeminiError::InvalidApiKey.context("API key check"))), ApiKeyStatus::Invalid);
+        let forbidden = GeminiError::Http { status: 403, body: "PERMISSION_DENIED".to_string(), retry_after: None };
+        assert_eq!(status(Err(forbidden)), ApiKeyStatus::Invalid);
+        assert_eq!(status(Err(GeminiError::Network("connection refused".to_string()))), ApiKeyStatus::NetworkError);
+        assert_eq!(status(Err(GeminiError::QuotaExceeded { retry_after: None })), ApiKeyStatus::Error);
+        assert_eq!(ApiKeyValidation::from_result(Ok(())).message, None);
+    }
+
     #[test]
     fn test_srt_extraction_integration() {
         // Test that extract_srt_content is properly integrated
         let test_cases = vec![
             // Case 1: Response with code block
-            ("Here's your SRT:\n\n```srt\n1\n00:00:00,000 --> 00:00:05,000\nHello\n```", "1\n00:00:00,000 --> 00:00:05,000\nHello"),
+            ("Here's your SRT:\n\n```srt\n1\n00:00:00,000 --> 00:00:05,000\nHello\n```", "1\n00:00:00,000 --> 00:00:05,000\nHello\n\n"),
             // Case 2: Response without code block
-            ("1\n00:00:00,000 --> 00:00:05,000\nHello", "1\n00:00:00,000 --> 00:00:05,000\nHello"),
+            ("1\n00:00:00,000 --> 00:00:05,000\nHello", "1\n00:00:00,000 --> 00:00:05,000\nHello\n\n"),
             // Case 3: Response with generic code block
-            ("```\n1\n00:00:00,000 --> 00:00:05,000\nHello\n```", "1\n00:00:00,000 --> 00:00:05,000\nHello"),
+            ("```\n1\n00:00:00,000 --> 00:00:05,000\nHello\n```", "1\n00:00:00,000 --> 00:00:05,000\nHello\n\n"),
         ];
 
         for (input, expected) in test_cases {
@@ -0,0 +1,322 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Write as _};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::Interest;
+use tracing::{Event, Level, Metadata, Subscriber};
+
+/// Name of the log file inside the `logs` directory of the app data dir
+pub const LOG_FILE_NAME: &str = "str-app.log";
+
+/// The log file is rotated once it would grow past this size
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Rotated files kept next to the current one, as `str-app.log.1` (newest) and so on
+const KEPT_LOG_FILES: usize = 3;
+
+/// Most verbose level written to the log file, saved with `set_log_level`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    const ALL: [LogLevel; 5] = [LogLevel::Error, LogLevel::Warn, LogLevel::Info, LogLevel::Debug, LogLevel::Trace];
+
+    fn as_level(self) -> Level {
+        match self {
+            LogLevel::Error => Level::ERROR,
+            LogLevel::Warn => Level::WARN,
+            LogLevel::Info => Level::INFO,
+            LogLevel::Debug => Level::DEBUG,
+            LogLevel::Trace => Level::TRACE,
+        }
+    }
+
+    fn from_index(index: u8) -> LogLevel {
+        LogLevel::ALL.get(index as usize).copied().unwrap_or_default()
+    }
+
+    fn index(self) -> u8 {
+        self as u8
+    }
+}
+
+/// Patterns for secrets that must never reach the log file, with their replacement
+static REDACTIONS: LazyLock<Vec<(Regex, &'static str)>> = LazyLock::new(|| {
+    [
+        // Google API keys
+        (r"AIza[0-9A-Za-z_\-]{35}", "[REDACTED_API_KEY]"),
+        // Keys and upload sessions passed as query parameters
+        (r#"([?&](?:key|upload_id|access_token)=)[^&\s"'<>]+"#, "${1}[REDACTED]"),
+        (r#"(?i)(x-goog-api-key["']?\s*[:=]\s*["']?)[^\s"',]+"#, "${1}[REDACTED]"),
+        (r"(?i)(bearer\s+)[A-Za-z0-9._\-]+", "${1}[REDACTED]"),
+        // Files API names and URIs, e.g. https://.../v1beta/files/abc123
+        (r"\bfiles/[A-Za-z0-9_\-]+", "files/[REDACTED]"),
+        // Cloud Storage URIs used with Vertex AI
+        (r#"gs://[^\s"'<>]+"#, "gs://[REDACTED]"),
+    ]
+    .into_iter()
+    .map(|(pattern, replacement)| (Regex::new(pattern).expect("valid redaction pattern"), replacement))
+    .collect()
+});
+
+/// Replaces API keys, access tokens and file URIs in a log line
+pub fn redact(line: &str) -> String {
+    REDACTIONS
+        .iter()
+        .fold(line.to_string(), |line, (pattern, replacement)| pattern.replace_all(&line, *replacement).into_owned())
+}
+
+/// Writes the message of an event first, then its other fields as `name=value`
+struct LineVisitor<'a> {
+    message: &'a mut String,
+    fields: &'a mut String,
+}
+
+impl Visit for LineVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+/// Formats one event as a redacted log line:
+/// `<local time> <LEVEL> <target>: <message> <fields>`
+pub fn format_event(event: &Event<'_>) -> String {
+    let mut message = String::new();
+    let mut fields = String::new();
+    event.record(&mut LineVisitor { message: &mut message, fields: &mut fields });
+    let metadata = event.metadata();
+    let line = format!(
+        "{} {:>5} {}: {}{}",
+        chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z"),
+        metadata.level(),
+        metadata.target(),
+        message,
+        fields
+    );
+    redact(&line)
+}
+
+/// Log file that is renamed to `<name>.1` once it grows past `max_bytes`,
+/// shifting older files up and dropping the oldest
+struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn new(path: PathBuf, max_bytes: u64) -> Self {
+        let size = fs::metadata(&path).map(|metadata| metadata.len()).unwrap_or(0);
+        RotatingFile { path, max_bytes, file: None, size }
+    }
+
+    fn rotated_path(&self, generation: usize) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}", generation));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file = None;
+        let _ = fs::remove_file(self.rotated_path(KEPT_LOG_FILES));
+        for generation in (1..KEPT_LOG_FILES).rev() {
+            let from = self.rotated_path(generation);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(generation + 1))?;
+            }
+        }
+        if self.path.exists() {
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.size > 0 && self.size + len > self.max_bytes {
+            self.rotate()?;
+        }
+        if self.file.is_none() {
+            if let Some(dir) = self.path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            self.file = Some(OpenOptions::new().create(true).append(true).open(&self.path)?);
+        }
+        let file = self.file.as_mut().expect("log file was just opened");
+        writeln!(file, "{}", line)?;
+        self.size += len;
+        Ok(())
+    }
+}
+
+/// Handle to the installed logger, managed as app state
+#[derive(Clone)]
+pub struct LogHandle {
+    path: PathBuf,
+    level: Arc<AtomicU8>,
+}
+
+impl LogHandle {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn level(&self) -> LogLevel {
+        LogLevel::from_index(self.level.load(Ordering::Relaxed))
+    }
+
+    /// Takes effect for the next event
+    pub fn set_level(&self, level: LogLevel) {
+        self.level.store(level.index(), Ordering::Relaxed);
+    }
+}
+
+/// `tracing` subscriber writing every enabled event to a rotating file.
+/// Spans are not recorded; only their ids are handed out.
+pub struct FileLogger {
+    level: Arc<AtomicU8>,
+    file: Mutex<RotatingFile>,
+    next_span_id: AtomicU64,
+}
+
+impl FileLogger {
+    pub fn new(path: PathBuf, level: LogLevel) -> (Self, LogHandle) {
+        let level = Arc::new(AtomicU8::new(level.index()));
+        let handle = LogHandle { path: path.clone(), level: level.clone() };
+        let logger = FileLogger {
+            level,
+            file: Mutex::new(RotatingFile::new(path, MAX_LOG_BYTES)),
+            next_span_id: AtomicU64::new(1),
+        };
+        (logger, handle)
+    }
+}
+
+impl Subscriber for FileLogger {
+    fn register_callsite(&self, _metadata: &'static Metadata<'static>) -> Interest {
+        // The level can change at runtime, so ask `enabled` every time
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        *metadata.level() <= LogLevel::from_index(self.level.load(Ordering::Relaxed)).as_level()
+    }
+
+    fn new_span(&self, _span: &Attributes<'_>) -> Id {
+        Id::from_u64(self.next_span_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let line = format_event(event);
+        if let Ok(mut file) = self.file.lock() {
+            // Nowhere left to report a failing log file
+            let _ = file.write_line(&line);
+        }
+    }
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+/// Installs the file logger for the whole process
+pub fn init(path: PathBuf, level: LogLevel) -> Result<LogHandle, String> {
+    let (logger, handle) = FileLogger::new(path, level);
+    tracing::subscriber::set_global_default(logger).map_err(|e| format!("Failed to install logger: {}", e))?;
+    Ok(handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_log_path() -> PathBuf {
+        std::env::temp_dir().join(format!("log_{}", uuid::Uuid::new_v4())).join(LOG_FILE_NAME)
+    }
+
+    #[test]
+    fn test_redact_secrets() {
+        let key = format!("AIza{}", "x".repeat(35));
+        assert_eq!(redact(&format!("Rejected {}", key)), "Rejected [REDACTED_API_KEY]");
+        assert_eq!(
+            redact("POST https://example.com/upload/v1beta/files?key=abc123&upload_id=xyz&alt=json"),
+            "POST https://example.com/upload/v1beta/files?key=[REDACTED]&upload_id=[REDACTED]&alt=json"
+        );
+        assert_eq!(
+            redact(r#"{"uri": "https://generativelanguage.googleapis.com/v1beta/files/abc-123", "name": "files/abc-123"}"#),
+            r#"{"uri": "https://generativelanguage.googleapis.com/v1beta/files/[REDACTED]", "name": "files/[REDACTED]"}"#
+        );
+        assert_eq!(redact("Authorization: Bearer ya29.a0AfH6"), "Authorization: Bearer [REDACTED]");
+        assert_eq!(redact("x-goog-api-key: secret"), "x-goog-api-key: [REDACTED]");
+        assert_eq!(redact("fileUri gs://bucket/audio.wav"), "fileUri gs://[REDACTED]");
+        assert_eq!(redact("Transcribing chunk 2 of 5"), "Transcribing chunk 2 of 5");
+    }
+
+    #[test]
+    fn test_logger_filters_by_level_and_redacts() {
+        let path = temp_log_path();
+        let (logger, handle) = FileLogger::new(path.clone(), LogLevel::Info);
+        tracing::subscriber::with_default(logger, || {
+            tracing::debug!("hidden");
+            tracing::info!(attempt = 2, "Deleted uploaded file {}", "files/abc123");
+            handle.set_level(LogLevel::Debug);
+            tracing::debug!("shown");
+        });
+
+        let log = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 2, "{}", log);
+        assert!(lines[0].contains(" INFO "), "{}", lines[0]);
+        assert!(lines[0].ends_with("logging::tests: Deleted uploaded file files/[REDACTED] attempt=2"), "{}", lines[0]);
+        assert!(lines[1].contains("DEBUG") && lines[1].ends_with("shown"));
+        assert_eq!(handle.level(), LogLevel::Debug);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_log_file_rotation() {
+        let path = temp_log_path();
+        let mut file = RotatingFile::new(path.clone(), 20);
+        for line in ["first line", "second line", "third line", "fourth line", "fifth line"] {
+            file.write_line(line).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fifth line\n");
+        assert_eq!(fs::read_to_string(file.rotated_path(1)).unwrap(), "fourth line\n");
+        assert_eq!(fs::read_to_string(file.rotated_path(3)).unwrap(), "second line\n");
+        assert!(!file.rotated_path(4).exists());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
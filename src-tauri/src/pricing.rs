@@ -0,0 +1,259 @@
+use serde::Serialize;
+use std::sync::Mutex;
+
+use crate::gemini::{ModalityTokenCount, TokenCount, UsageMetadata};
+
+/// Paid-tier list prices in USD per 1M tokens as (model prefix, text input,
+/// audio input, output). Update this table when Google changes its prices.
+const PRICES: [(&str, f64, f64, f64); 5] = [
+    ("gemini-2.0-flash-lite", 0.075, 0.075, 0.30),
+    ("gemini-2.0-flash", 0.10, 0.70, 0.40),
+    ("gemini-2.5-flash-lite", 0.10, 0.30, 0.40),
+    ("gemini-2.5-flash", 0.30, 1.00, 2.50),
+    ("gemini-2.5-pro", 1.25, 1.25, 10.00),
+];
+
+/// Gemini tokenizes audio at a fixed rate regardless of its content
+const AUDIO_TOKENS_PER_SECOND: u32 = 32;
+/// Rough SRT output per second of audio: the spoken text plus cue numbers
+/// and timestamps
+const EXPECTED_OUTPUT_TOKENS_PER_SECOND: u32 = 8;
+
+struct ModelPricing {
+    input: f64,
+    /// Audio input is billed separately on the Flash models
+    audio_input: f64,
+    output: f64,
+}
+
+impl ModelPricing {
+    fn cost(&self, text_tokens: u32, audio_tokens: u32, output_tokens: u32) -> f64 {
+        (text_tokens as f64 * self.input + audio_tokens as f64 * self.audio_input + output_tokens as f64 * self.output)
+            / 1_000_000.0
+    }
+}
+
+/// Looks up pricing for a model; the longest matching prefix wins so that
+/// e.g. `gemini-2.5-flash-lite` is not priced as `gemini-2.5-flash`
+fn pricing_for_model(model: &str) -> Option<ModelPricing> {
+    let model = model.strip_prefix("models/").unwrap_or(model);
+    PRICES
+        .iter()
+        .filter(|(prefix, ..)| model.starts_with(prefix))
+        .max_by_key(|(prefix, ..)| prefix.len())
+        .map(|(_, input, audio_input, output)| ModelPricing {
+            input: *input,
+            audio_input: *audio_input,
+            output: *output,
+        })
+}
+
+/// Token counts for a single request, as reported to the frontend
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TokenUsage {
+    pub model: String,
+    pub prompt_tokens: u32,
+    pub output_tokens: u32,
+    pub total_tokens: u32,
+    /// `None` when the model is not in the price table
+    pub estimated_cost_usd: Option<f64>,
+}
+
+fn count(value: Option<i32>) -> u32 {
+    value.unwrap_or(0).max(0) as u32
+}
+
+/// Audio tokens among `prompt_tokens`
+fn audio_tokens(details: Option<&Vec<ModalityTokenCount>>, prompt_tokens: u32) -> u32 {
+    details
+        .into_iter()
+        .flatten()
+        .filter(|detail| detail.modality == "AUDIO")
+        .map(|detail| count(detail.token_count))
+        .sum::<u32>()
+        .min(prompt_tokens)
+}
+
+impl TokenUsage {
+    pub fn from_metadata(model: &str, usage: &UsageMetadata) -> Self {
+        let prompt_tokens = count(usage.prompt_token_count);
+        let output_tokens = count(usage.candidates_token_count);
+        let total_tokens = usage
+            .total_token_count
+            .map_or(prompt_tokens + output_tokens, |total| count(Some(total)));
+        let audio_tokens = audio_tokens(usage.prompt_tokens_details.as_ref(), prompt_tokens);
+
+        let estimated_cost_usd = pricing_for_model(model).map(|pricing| {
+            // Billed output includes thinking tokens, which are the difference to the total
+            let billed_output = total_tokens.saturating_sub(prompt_tokens).max(output_tokens);
+            pricing.cost(prompt_tokens - audio_tokens, audio_tokens, billed_output)
+        });
+
+        Self {
+            model: model.to_string(),
+            prompt_tokens,
+            output_tokens,
+            total_tokens,
+            estimated_cost_usd,
+        }
+    }
+}
+
+/// Token usage summed over every generation since the app started
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SessionUsage {
+    pub generations: u32,
+    pub prompt_tokens: u64,
+    pub output_tokens: u64,
+    pub total_tokens: u64,
+    /// Sum over the models in the price table
+    pub estimated_cost_usd: f64,
+    /// Totals per model, in order of first use
+    pub models: Vec<TokenUsage>,
+}
+
+impl SessionUsage {
+    fn add(&mut self, usage: &TokenUsage) {
+        self.generations += 1;
+        self.prompt_tokens += u64::from(usage.prompt_tokens);
+        self.output_tokens += u64::from(usage.output_tokens);
+        self.total_tokens += u64::from(usage.total_tokens);
+        self.estimated_cost_usd += usage.estimated_cost_usd.unwrap_or(0.0);
+        match self.models.iter_mut().find(|model| model.model == usage.model) {
+            Some(model) => {
+                model.prompt_tokens = model.prompt_tokens.saturating_add(usage.prompt_tokens);
+                model.output_tokens = model.output_tokens.saturating_add(usage.output_tokens);
+                model.total_tokens = model.total_tokens.saturating_add(usage.total_tokens);
+                model.estimated_cost_usd = match (model.estimated_cost_usd, usage.estimated_cost_usd) {
+                    (Some(total), Some(cost)) => Some(total + cost),
+                    (total, cost) => total.or(cost),
+                };
+            }
+            None => self.models.push(usage.clone()),
+        }
+    }
+}
+
+/// Running [`SessionUsage`], kept in Tauri managed state
+#[derive(Default)]
+pub struct UsageTracker {
+    totals: Mutex<SessionUsage>,
+}
+
+impl UsageTracker {
+    pub fn record(&self, usage: &TokenUsage) {
+        self.totals.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).add(usage);
+    }
+
+    pub fn totals(&self) -> SessionUsage {
+        self.totals.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+}
+
+/// Expected tokens and price of a transcription, before running it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CostEstimate {
+    pub model: String,
+    pub input_tokens: u32,
+    /// Part of `input_tokens` taken up by the audio
+    pub audio_tokens: u32,
+    /// Guessed from the audio length; thinking tokens are not included
+    pub expected_output_tokens: u32,
+    /// `None` when the model is not in the price table
+    pub estimated_cost_usd: Option<f64>,
+}
+
+impl CostEstimate {
+    pub fn from_token_count(model: &str, token_count: &TokenCount) -> Self {
+        let input_tokens = count(Some(token_count.total_tokens));
+        let audio_tokens = audio_tokens(token_count.prompt_tokens_details.as_ref(), input_tokens);
+        let expected_output_tokens = audio_tokens / AUDIO_TOKENS_PER_SECOND * EXPECTED_OUTPUT_TOKENS_PER_SECOND;
+        let estimated_cost_usd = pricing_for_model(model)
+            .map(|pricing| pricing.cost(input_tokens - audio_tokens, audio_tokens, expected_output_tokens));
+
+        Self {
+            model: model.to_string(),
+            input_tokens,
+            audio_tokens,
+            expected_output_tokens,
+            estimated_cost_usd,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(prompt: i32, output: i32, audio: Option<i32>) -> UsageMetadata {
+        UsageMetadata {
+            prompt_token_count: Some(prompt),
+            candidates_token_count: Some(output),
+            total_token_count: Some(prompt + output),
+            prompt_tokens_details: audio.map(|tokens| {
+                vec![ModalityTokenCount {
+                    modality: "AUDIO".to_string(),
+                    token_count: Some(tokens),
+                }]
+            }),
+        }
+    }
+
+    #[test]
+    fn test_cost_splits_audio_and_text_input() {
+        let usage = TokenUsage::from_metadata("gemini-2.0-flash", &usage(1_000_000, 1_000_000, Some(800_000)));
+        assert_eq!(usage.total_tokens, 2_000_000);
+        // 0.2M text * 0.10 + 0.8M audio * 0.70 + 1M output * 0.40
+        let cost = usage.estimated_cost_usd.unwrap();
+        assert!((cost - 0.98).abs() < 1e-9, "cost was {}", cost);
+    }
+
+    #[test]
+    fn test_longest_prefix_and_unknown_models() {
+        let lite = TokenUsage::from_metadata("models/gemini-2.5-flash-lite", &usage(1_000_000, 0, None));
+        assert!((lite.estimated_cost_usd.unwrap() - 0.10).abs() < 1e-9);
+
+        let unknown = TokenUsage::from_metadata("gemma-3", &usage(10, 20, None));
+        assert_eq!(unknown.estimated_cost_usd, None);
+        assert_eq!(unknown.output_tokens, 20);
+    }
+
+    #[test]
+    fn test_usage_tracker_sums_per_model() {
+        let tracker = UsageTracker::default();
+        tracker.record(&TokenUsage::from_metadata("gemini-2.5-pro", &usage(1_000_000, 100_000, None)));
+        tracker.record(&TokenUsage::from_metadata("gemma-3", &usage(10, 20, None)));
+        tracker.record(&TokenUsage::from_metadata("gemini-2.5-pro", &usage(1_000_000, 100_000, None)));
+
+        let totals = tracker.totals();
+        assert_eq!(totals.generations, 3);
+        assert_eq!(totals.prompt_tokens, 2_000_010);
+        assert_eq!(totals.total_tokens, 2_200_030);
+        // 2M input * 1.25 + 0.2M output * 10.00; gemma is not priced
+        assert!((totals.estimated_cost_usd - 4.5).abs() < 1e-9, "cost was {}", totals.estimated_cost_usd);
+        assert_eq!(totals.models.len(), 2);
+        assert_eq!(totals.models[0].model, "gemini-2.5-pro");
+        assert_eq!(totals.models[0].output_tokens, 200_000);
+        assert_eq!(totals.models[1].estimated_cost_usd, None);
+    }
+
+    #[test]
+    fn test_cost_estimate_from_token_count() {
+        // One hour of audio plus a short prompt
+        let token_count = TokenCount {
+            total_tokens: 115_200 + 800,
+            prompt_tokens_details: Some(vec![ModalityTokenCount {
+                modality: "AUDIO".to_string(),
+                token_count: Some(115_200),
+            }]),
+        };
+        let estimate = CostEstimate::from_token_count("gemini-2.5-flash", &token_count);
+        assert_eq!(estimate.input_tokens, 116_000);
+        assert_eq!(estimate.expected_output_tokens, 28_800);
+        // 800 text * 0.30 + 115,200 audio * 1.00 + 28,800 output * 2.50
+        let cost = estimate.estimated_cost_usd.unwrap();
+        assert!((cost - 0.18744).abs() < 1e-9, "cost was {}", cost);
+
+        assert_eq!(CostEstimate::from_token_count("gemma-3", &token_count).estimated_cost_usd, None);
+    }
+}
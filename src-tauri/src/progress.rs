@@ -0,0 +1,102 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Step of a transcription job, in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressStage {
+    /// Waiting for a free upload or generation slot, before either step
+    Queued,
+    Uploading,
+    /// The Files API is processing the uploaded file
+    Processing,
+    Generating,
+    /// Cleaning up and validating the generated subtitles
+    Finalizing,
+    Done,
+}
+
+/// Payload of the `transcription-progress` event
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TranscriptionProgress {
+    pub job_id: String,
+    pub stage: ProgressStage,
+    /// Percentage of the current stage, when it can be measured
+    pub percent: Option<u8>,
+    pub detail: String,
+}
+
+/// `sent` as a percentage of `total`; an empty file counts as complete
+pub fn percent_of(sent: u64, total: u64) -> u8 {
+    (sent.min(total) * 100).checked_div(total).map_or(100, |percent| percent as u8)
+}
+
+#[derive(Default)]
+struct Jobs {
+    progress: HashMap<String, TranscriptionProgress>,
+    latest: Option<String>,
+}
+
+/// Latest progress of each transcription job, so the frontend can ask for it
+/// as well as listen for events. Kept in Tauri managed state.
+#[derive(Default)]
+pub struct ProgressTracker {
+    jobs: Mutex<Jobs>,
+}
+
+impl ProgressTracker {
+    fn jobs(&self) -> std::sync::MutexGuard<'_, Jobs> {
+        self.jobs.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn update(&self, progress: TranscriptionProgress) {
+        let mut jobs = self.jobs();
+        jobs.latest = Some(progress.job_id.clone());
+        jobs.progress.insert(progress.job_id.clone(), progress);
+    }
+
+    /// Progress of `job_id`, or of the most recently updated job
+    pub fn get(&self, job_id: Option<&str>) -> Option<TranscriptionProgress> {
+        let jobs = self.jobs();
+        let job_id = job_id.or(jobs.latest.as_deref())?;
+        jobs.progress.get(job_id).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(job_id: &str, stage: ProgressStage) -> TranscriptionProgress {
+        TranscriptionProgress {
+            job_id: job_id.to_string(),
+            stage,
+            percent: None,
+            detail: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_tracker_keeps_latest_progress_per_job() {
+        let tracker = ProgressTracker::default();
+        assert_eq!(tracker.get(None), None);
+
+        tracker.update(progress("a", ProgressStage::Uploading));
+        tracker.update(progress("b", ProgressStage::Uploading));
+        tracker.update(progress("a", ProgressStage::Generating));
+
+        assert_eq!(tracker.get(Some("a")).map(|p| p.stage), Some(ProgressStage::Generating));
+        assert_eq!(tracker.get(Some("b")).map(|p| p.stage), Some(ProgressStage::Uploading));
+        assert_eq!(tracker.get(None).map(|p| p.job_id), Some("a".to_string()));
+        assert_eq!(tracker.get(Some("c")), None);
+    }
+
+    #[test]
+    fn test_percent_of() {
+        assert_eq!(percent_of(0, 200), 0);
+        assert_eq!(percent_of(199, 200), 99);
+        assert_eq!(percent_of(300, 200), 100);
+        assert_eq!(percent_of(0, 0), 100);
+    }
+}
@@ -0,0 +1,714 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::srt_utils::format_timestamp;
+
+/// Language of the audio and of the subtitles, selected by ISO 639-1 code
+#[derive(Debug, Clone, PartialEq)]
+pub enum TranscriptionLanguage {
+    Japanese,
+    English,
+    Spanish,
+    /// Any other ISO 639-1 code; guidance is given in English
+    Other(String),
+}
+
+impl TranscriptionLanguage {
+    /// Japanese is the default when no code (or an empty one) is given
+    pub fn from_code(code: Option<&str>) -> Self {
+        let code = code.map(|code| code.trim().to_lowercase()).unwrap_or_default();
+        match code.as_str() {
+            "" | "ja" => TranscriptionLanguage::Japanese,
+            "en" => TranscriptionLanguage::English,
+            "es" => TranscriptionLanguage::Spanish,
+            _ => TranscriptionLanguage::Other(code),
+        }
+    }
+
+    /// Lowercase ISO 639-1 code
+    pub fn code(&self) -> &str {
+        match self {
+            TranscriptionLanguage::Japanese => "ja",
+            TranscriptionLanguage::English => "en",
+            TranscriptionLanguage::Spanish => "es",
+            TranscriptionLanguage::Other(code) => code,
+        }
+    }
+
+    /// Filler words typical for the language, named in prompts unless the
+    /// user configured their own
+    pub fn default_filler_words(&self) -> &'static [&'static str] {
+        match self {
+            TranscriptionLanguage::Japanese => &["えーっと", "あのー", "なんか"],
+            TranscriptionLanguage::English | TranscriptionLanguage::Other(_) => &["um", "uh", "like", "you know"],
+            TranscriptionLanguage::Spanish => &["eh", "este", "o sea", "pues"],
+        }
+    }
+
+    /// `filler_words`, or the defaults when `None`, quoted for use in prompts
+    pub fn filler_examples(&self, filler_words: Option<&[String]>) -> String {
+        let words = match filler_words {
+            Some(words) => words.iter().map(String::as_str).collect(),
+            None => self.default_filler_words().to_vec(),
+        };
+        match self {
+            TranscriptionLanguage::Japanese => words.iter().map(|word| format!("「{}」", word)).collect(),
+            _ => words.iter().map(|word| format!("\"{}\"", word)).collect::<Vec<_>>().join(", "),
+        }
+    }
+
+    /// Instruction to keep the output in the spoken language; empty for
+    /// Japanese, where the prompt language already matches
+    pub fn output_language_instruction(&self) -> String {
+        match self {
+            TranscriptionLanguage::Japanese => String::new(),
+            TranscriptionLanguage::English => "\n- 音声は英語です。英語のまま書き起こし、翻訳しないでください".to_string(),
+            TranscriptionLanguage::Spanish => "\n- 音声はスペイン語です。スペイン語のまま書き起こし、翻訳しないでください".to_string(),
+            TranscriptionLanguage::Other(code) => {
+                format!("\n- 音声の言語はISO 639-1コード `{}` の言語です。その言語のまま書き起こし、翻訳しないでください", code)
+            }
+        }
+    }
+
+    /// Rule on whether to label speakers, written in the target language
+    pub fn speaker_instruction(&self, enable_speaker_detection: bool) -> &'static str {
+        match (self, enable_speaker_detection) {
+            (TranscriptionLanguage::Japanese, true) => "\n    - **話者の区別:** 会話に複数の話者がいる場合は、各字幕の先頭に話者名を明記してください。（例: `アオイ: `、`ユーザー: `）",
+            (TranscriptionLanguage::Japanese, false) => "\n    - **話者の区別:** 話者名は付けず、純粋な発話内容のみを記録してください。",
+            (TranscriptionLanguage::Spanish, true) => "\n    - **Hablantes:** Si hay varios hablantes, indique el nombre del hablante al principio de cada subtítulo (p. ej. `Ana: `, `Usuario: `).",
+            (TranscriptionLanguage::Spanish, false) => "\n    - **Hablantes:** No añada nombres de hablantes; registre solo lo que se dice.",
+            (_, true) => "\n    - **Speakers:** If there are multiple speakers, put the speaker's name at the start of each subtitle (e.g. `Alex: `, `User: `).",
+            (_, false) => "\n    - **Speakers:** Do not add speaker names; record only what is said.",
+        }
+    }
+
+    /// Rule on filler words, written in the target language: remove them, or
+    /// in verbatim mode keep them along with false starts and repetitions
+    pub fn filler_rule(&self, verbatim: bool, filler_words: Option<&[String]>) -> String {
+        let rule = match (self, verbatim) {
+            (TranscriptionLanguage::Japanese, false) => "\n    - **フィラーワードの削除:** 会話中の{}といった、意味を持たないフィラーワードはすべて削除し、自然で聞き取りやすい文章にしてください。",
+            (TranscriptionLanguage::Japanese, true) => "\n    - **逐語記録:** 会話中の{}といったフィラーワード、言い直し、繰り返し、言いよどみも削除せず、話された通りにすべて記録してください。",
+            (TranscriptionLanguage::Spanish, false) => "\n    - **Eliminación de muletillas:** Elimine las muletillas sin significado como {} para que el texto sea natural y fácil de leer.",
+            (TranscriptionLanguage::Spanish, true) => "\n    - **Transcripción literal:** Conserve las muletillas como {}, los falsos comienzos, las repeticiones y los tartamudeos tal como se dicen; no los elimine.",
+            (_, false) => "\n    - **Remove filler words:** Delete meaningless filler words such as {} so the text reads naturally.",
+            (_, true) => "\n    - **Verbatim:** Keep filler words such as {}, false starts, repetitions and stutters exactly as spoken; do not clean them up.",
+        };
+        rule.replace("{}", &self.filler_examples(filler_words))
+    }
+
+    /// Sections 2 and 3 of the SRT prompt: subtitle editing rules and
+    /// quality requirements, written in the target language
+    pub fn editing_guidance(
+        &self,
+        max_chars_per_subtitle: u32,
+        enable_speaker_detection: bool,
+        verbatim: bool,
+        filler_words: Option<&[String]>,
+    ) -> String {
+        let filler_rule = self.filler_rule(verbatim, filler_words);
+        let speaker_text = self.speaker_instruction(enable_speaker_detection);
+        match self {
+            TranscriptionLanguage::Japanese => {
+                format!(
+                    "2.  **字幕テキストの編集ルール**\n    - **文字数制限:** 1つの字幕ブロック（通し番号1つにつき）のテキストは、**{}文字以内**を目安にしてください。長くなる場合は、意味の区切りが良い箇所で改行するなど、読みやすさを最優先してください。{}{}\n\n3.  **品質要求**\n    - 字幕として読みやすく、視聴者にとって理解しやすい文章にしてください。\n    - 音声が不明瞭な部分は [不明瞭] として記録してください。\n    - 無音部分や間は適切に反映し、字幕の切り替えタイミングを自然にしてください。",
+                    max_chars_per_subtitle,
+                    filler_rule,
+                    speaker_text
+                )
+            }
+            TranscriptionLanguage::Spanish => {
+                format!(
+                    "2.  **Reglas de edición del texto de los subtítulos**\n    - **Idioma:** Escriba los subtítulos en español, tal como se habla, sin traducir.\n    - **Límite de caracteres:** El texto de cada bloque de subtítulos debe tener como máximo **unos {} caracteres**. Si es más largo, divídalo en un punto natural priorizando la legibilidad.{}{}\n\n3.  **Requisitos de calidad**\n    - Redacte subtítulos fáciles de leer y de entender para el espectador.\n    - Marque las partes inaudibles como [inaudible].\n    - Refleje los silencios y pausas para que los cambios de subtítulo sean naturales.",
+                    max_chars_per_subtitle,
+                    filler_rule,
+                    speaker_text
+                )
+            }
+            TranscriptionLanguage::English | TranscriptionLanguage::Other(_) => {
+                let language_text = match self {
+                    TranscriptionLanguage::Other(code) => {
+                        format!("Write the subtitles in the spoken language (ISO 639-1 code `{}`), without translating.", code)
+                    }
+                    _ => "Write the subtitles in English, as spoken, without translating.".to_string(),
+                };
+                format!(
+                    "2.  **Subtitle text editing rules**\n    - **Language:** {}\n    - **Character limit:** Keep the text of each subtitle block to **about {} characters**. If it gets longer, break it at a natural point, prioritizing readability.{}{}\n\n3.  **Quality requirements**\n    - Write subtitles that are easy for viewers to read and understand.\n    - Mark unclear audio as [inaudible].\n    - Reflect silences and pauses so subtitle changes feel natural.",
+                    language_text,
+                    max_chars_per_subtitle,
+                    filler_rule,
+                    speaker_text
+                )
+            }
+        }
+    }
+}
+
+/// Models other than Gemini 2.0 Flash are asked for SRT directly; 2.0 Flash
+/// produces the plain-text first pass of the dictionary workflow
+pub fn is_srt_model(model: &str) -> bool {
+    !model.contains("gemini-2.0-flash")
+}
+
+/// Gemma models reject `systemInstruction` ("Developer instruction is not
+/// enabled")
+pub fn supports_system_instruction(model: &str) -> bool {
+    !model.contains("gemma")
+}
+
+/// A prompt split into the invariant rules, sent as the system instruction,
+/// and the per-job request sent as the user turn
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prompt {
+    pub system_instruction: Option<String>,
+    pub text: String,
+}
+
+impl Prompt {
+    /// For models without system instruction support, the rules lead the
+    /// user turn as they did before the split
+    pub fn for_model(self, model: &str) -> Self {
+        if supports_system_instruction(model) {
+            self
+        } else {
+            Self {
+                text: self.combined(),
+                system_instruction: None,
+            }
+        }
+    }
+
+    /// The rules and the request as one text, e.g. for previews
+    pub fn combined(&self) -> String {
+        match &self.system_instruction {
+            Some(system_instruction) => format!("{}\n\n{}", system_instruction, self.text),
+            None => self.text.clone(),
+        }
+    }
+}
+
+/// Subtitle settings the transcription prompts are rendered with
+#[derive(Debug, Clone, Copy)]
+pub struct SubtitleRules<'a> {
+    pub max_chars_per_subtitle: u32,
+    pub enable_speaker_detection: bool,
+    /// Mentioned in the prompt so timestamps stay within the recording
+    pub duration_ms: Option<u32>,
+    pub language: &'a TranscriptionLanguage,
+    /// Keep fillers, false starts and repetitions instead of removing them
+    pub verbatim: bool,
+    /// Replaces the default filler examples of the language
+    pub filler_words: Option<&'a [String]>,
+}
+
+/// Prompts that can be customized, each stored as `<name>.txt` in the
+/// prompt template directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PromptTemplate {
+    /// Plain-text first pass of the dictionary workflow (Gemini 2.0 Flash)
+    TranscriptionBasic,
+    /// Direct SRT transcription by the other models
+    TranscriptionSrt,
+    /// SRT format rules sent as the system instruction of every SRT request
+    SrtRules,
+    AnalyzeTopic,
+    CreateDictionary,
+    EnhanceWithDictionary,
+}
+
+impl PromptTemplate {
+    pub const ALL: [PromptTemplate; 6] = [
+        PromptTemplate::TranscriptionBasic,
+        PromptTemplate::TranscriptionSrt,
+        PromptTemplate::SrtRules,
+        PromptTemplate::AnalyzeTopic,
+        PromptTemplate::CreateDictionary,
+        PromptTemplate::EnhanceWithDictionary,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PromptTemplate::TranscriptionBasic => "transcription_basic",
+            PromptTemplate::TranscriptionSrt => "transcription_srt",
+            PromptTemplate::SrtRules => "srt_rules",
+            PromptTemplate::AnalyzeTopic => "analyze_topic",
+            PromptTemplate::CreateDictionary => "create_dictionary",
+            PromptTemplate::EnhanceWithDictionary => "enhance_with_dictionary",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|template| template.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::ALL.iter().map(|template| template.name()).collect();
+                format!("Unknown prompt template: {} (expected one of {})", name, names.join(", "))
+            })
+    }
+
+    /// The built-in template
+    pub fn default_content(self) -> &'static str {
+        match self {
+            PromptTemplate::TranscriptionBasic => TRANSCRIPTION_BASIC_TEMPLATE,
+            PromptTemplate::TranscriptionSrt => TRANSCRIPTION_SRT_TEMPLATE,
+            PromptTemplate::SrtRules => SRT_RULES_TEMPLATE,
+            PromptTemplate::AnalyzeTopic => ANALYZE_TOPIC_TEMPLATE,
+            PromptTemplate::CreateDictionary => CREATE_DICTIONARY_TEMPLATE,
+            PromptTemplate::EnhanceWithDictionary => ENHANCE_WITH_DICTIONARY_TEMPLATE,
+        }
+    }
+}
+
+/// Fills in `{name}` placeholders from `values`. Placeholders without a value
+/// and other braces are kept as written, and substituted text is not scanned
+/// again, so transcriptions containing braces pass through unchanged.
+pub fn render_template(template: &str, values: &[(&str, &str)]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        rendered.push_str(&rest[..open]);
+        let after_open = &rest[open + 1..];
+        let value = after_open
+            .find('}')
+            .and_then(|close| values.iter().find(|(name, _)| *name == &after_open[..close]).map(|(_, value)| (close, value)));
+        match value {
+            Some((close, value)) => {
+                rendered.push_str(value);
+                rest = &after_open[close + 1..];
+            }
+            None => {
+                rendered.push('{');
+                rest = after_open;
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Prompt templates with user overrides loaded from the config directory,
+/// kept in Tauri managed state
+pub struct PromptTemplates {
+    dir: PathBuf,
+    overrides: Mutex<HashMap<PromptTemplate, String>>,
+}
+
+impl PromptTemplates {
+    /// Loads the overrides in `dir`; templates without a readable file use
+    /// the built-in default
+    pub fn load(dir: PathBuf) -> Self {
+        let overrides = PromptTemplate::ALL
+            .into_iter()
+            .filter_map(|template| {
+                let content = std::fs::read_to_string(dir.join(format!("{}.txt", template.name()))).ok()?;
+                Some((template, content))
+            })
+            .collect();
+        Self {
+            dir,
+            overrides: Mutex::new(overrides),
+        }
+    }
+
+    fn overrides(&self) -> std::sync::MutexGuard<'_, HashMap<PromptTemplate, String>> {
+        self.overrides.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn get(&self, template: PromptTemplate) -> String {
+        self.overrides()
+            .get(&template)
+            .cloned()
+            .unwrap_or_else(|| template.default_content().to_string())
+    }
+
+    /// Stores an override; blank content restores the built-in default
+    pub fn set(&self, template: PromptTemplate, content: &str) -> Result<(), String> {
+        let path = self.dir.join(format!("{}.txt", template.name()));
+        let mut overrides = self.overrides();
+        if content.trim().is_empty() {
+            if path.exists() {
+                std::fs::remove_file(&path).map_err(|e| format!("Failed to remove prompt template: {}", e))?;
+            }
+            overrides.remove(&template);
+        } else {
+            std::fs::create_dir_all(&self.dir).map_err(|e| format!("Failed to create prompt directory: {}", e))?;
+            std::fs::write(&path, content).map_err(|e| format!("Failed to save prompt template: {}", e))?;
+            overrides.insert(template, content.to_string());
+        }
+        Ok(())
+    }
+}
+
+const TRANSCRIPTION_BASIC_TEMPLATE: &str = "音声ファイルの内容を文字起こししてください。\n\n# 目的\nこの文字起こしは、会話のトピック分析と専門用語辞書作成のために使用します。\n\n# 要求事項\n1. **話者の発言を正確に文字起こし**\n2. **フィラーワード（{filler_examples}等）も含めて全て記録**\n3. **専門用語や固有名詞は正確に記録**\n4. **会話の流れや文脈がわかるように**\n\n# 出力形式\n- プレーンテキストで出力\n- 話者が複数いる場合は「話者1:」「話者2:」等で区別\n- タイムスタンプは不要\n- 改行で発言を区切る{language_instruction}\n\n**説明や前置きは不要です。文字起こしテキストのみを出力してください。**";
+
+const SRT_RULES_TEMPLATE: &str = r#"あなたは音声（または動画）ファイルを高品質なSRT（SubRip Text）ファイル形式で文字起こしする専門家です。
+
+# 1. SRTファイルの基本構造について
+
+まず、納品していただくSRTファイルの構造について共通認識を持つために、基本的なルールを説明します。SRTファイルは、以下の4つの要素が1セットとなって構成されるテキストファイルです。
+
+1.  **通し番号:** `1`から始まる字幕の連番です。
+2.  **タイムスタンプ:** `時:分:秒,ミリ秒 --> 時:分:秒,ミリ秒` の形式で、字幕の表示開始時間と終了時間を指定します。（例: `00:01:23,456 --> 00:01:28,912`）
+3.  **字幕テキスト:** 画面に表示する文章です。改行を含めず、インラインで記述してください
+4.  **空行:** 各字幕ブロックを区切るための、何も書かれていない行です。必ず必要です
+
+**【具体例】**
+1
+00:00:05,520 --> 00:00:08,910
+これは1番目の字幕の
+テキストです。
+
+2
+00:00:09,150 --> 00:00:11,300
+そして、これが2番目の字幕です。
+
+この構造を厳密に守ってファイルを作成してください。.srtファイルとして納品してください
+
+# 2. タイムスタンプの形式
+
+- `hh:mm:ss,ms` の形式を厳守し、ミリ秒は3桁で記述してください。
+- 音声の発話タイミングと字幕の表示タイミングを正確に一致させてください。
+
+# 3. 出力
+
+最終的にSRT形式のテキストのみを出力してください。説明や前置き、コードブロックの囲いは不要です。"#;
+
+const TRANSCRIPTION_SRT_TEMPLATE: &str = r#"提供する音声（または動画）ファイルの内容を、高品質なSRT（SubRip Text）ファイル形式で文字起こししてください。{duration}
+
+# 文字起こしの詳細なルール
+
+1.  **タイムスタンプの精度**
+    - 音声の発話タイミングと字幕の表示タイミングを正確に一致させてください。
+
+{editing_guidance}
+
+**時間の精度が重要です。時間が合っているか確認をしたのち、SRT形式のテキストのみを出力してください。**"#;
+
+const ANALYZE_TOPIC_TEMPLATE: &str = "以下の文字起こしテキストを分析して、会話の主なトピックを特定してください。\n\n# 文字起こしテキスト\n{transcription}\n\n# 要求事項\n- keywords: 頻出する専門用語や固有名詞\n- topic_summary: 会話の主なトピックを簡潔に一文で";
+
+const CREATE_DICTIONARY_TEMPLATE: &str = "{topic}に出てくる用語の辞書を構築して。\n表記、ふりがなのみをセットでcsv形式で記載してください。topic自体に誤字脱字がないか確認してから、辞書を作成してください。\n日本語話者がわかるような辞書にしてください。固有名詞は正式な表記が何か調べてください。";
+
+const ENHANCE_WITH_DICTIONARY_TEMPLATE: &str = r#"提供する音声（または動画）ファイルの内容を、高品質なSRT（SubRip Text）ファイル形式で文字起こししてください。{duration}
+
+# 専門用語辞書
+以下の辞書を参考に、専門用語の表記を統一してください：
+
+{dictionary}
+
+# 元の文字起こし
+{transcription}
+
+# 文字起こしの詳細なルール
+
+1.  **タイムスタンプの精度**
+    - 音声の発話タイミングと字幕の表示タイミングを正確に一致させてください。
+
+2.  **字幕テキストの編集ルール**
+    - **文字数制限:** 1つの字幕ブロック（通し番号1つにつき）のテキストは、**{max_chars}文字以内**を目安にしてください。長くなる場合は、意味の区切りが良い箇所で改行するなど、読みやすさを最優先してください。{filler_rule}{speaker_instruction}
+
+3.  **品質要求**
+    - 字幕として読みやすく、視聴者にとって理解しやすい文章にしてください。
+    - 音声が不明瞭な部分は [不明瞭] として記録してください。
+    - 無音部分や間は適切に反映し、字幕の切り替えタイミングを自然にしてください。
+
+**時間の精度が重要です。時間が合っているか確認をしたのち、SRT形式のテキストのみを出力してください。**
+"#;
+
+/// Value of the `{duration}` placeholder: the audio length in the prompt
+/// language, or empty when unknown
+fn duration_text(duration_ms: Option<u32>) -> String {
+    match duration_ms {
+        Some(duration) => format!(
+            "\n\n**音声ファイルの長さ: {}分{}秒 ({}ms)**\n音声の長さを考慮して、適切な字幕の分割と表示タイミングを決定してください。",
+            duration / 60000,
+            (duration % 60000) / 1000,
+            duration
+        ),
+        None => String::new(),
+    }
+}
+
+/// Builds the prompt `transcribe_audio` sends for `model`. `filler_words`
+/// replaces the language's default examples.
+pub fn build_transcription_prompt(templates: &PromptTemplates, model: &str, rules: &SubtitleRules) -> Prompt {
+    let prompt = if is_srt_model(model) {
+        // Full SRT prompt for direct SRT generation
+        srt_prompt(templates, rules)
+    } else {
+        // Basic transcription prompt for initial transcription
+        Prompt {
+            system_instruction: None,
+            text: render_template(
+                &templates.get(PromptTemplate::TranscriptionBasic),
+                &[
+                    ("filler_examples", &rules.language.filler_examples(rules.filler_words)),
+                    ("language_instruction", &rules.language.output_language_instruction()),
+                ],
+            ),
+        }
+    };
+    prompt.for_model(model)
+}
+
+/// Renders the SRT rules and the SRT transcription template for the
+/// uploaded file
+pub fn srt_prompt(templates: &PromptTemplates, rules: &SubtitleRules) -> Prompt {
+    let language = rules.language;
+    Prompt {
+        system_instruction: Some(templates.get(PromptTemplate::SrtRules)),
+        text: render_template(
+            &templates.get(PromptTemplate::TranscriptionSrt),
+            &[
+                ("duration", &duration_text(rules.duration_ms)),
+                ("max_chars", &rules.max_chars_per_subtitle.to_string()),
+                ("speaker_instruction", language.speaker_instruction(rules.enable_speaker_detection)),
+                (
+                    "editing_guidance",
+                    &language.editing_guidance(rules.max_chars_per_subtitle, rules.enable_speaker_detection, rules.verbatim, rules.filler_words),
+                ),
+            ],
+        ),
+    }
+}
+
+/// Section appended to the SRT prompt when a request covers only
+/// `start_ms..end_ms` of the file, written in the target language
+pub fn chunk_range_instruction(language: &TranscriptionLanguage, start_ms: u64, end_ms: u64) -> String {
+    let (start, end, seconds) = (format_timestamp(start_ms), format_timestamp(end_ms), (end_ms - start_ms) / 1000);
+    match language {
+        TranscriptionLanguage::Japanese => format!(
+            "\n\n# 文字起こしの対象区間\nこのファイルのうち **{} から {} まで** の区間（{}秒間）のみを文字起こししてください。区間外の発話は出力しないでください。\nタイムスタンプは、この区間の開始時刻を `00:00:00,000` とした相対時間で記述してください。",
+            start, end, seconds
+        ),
+        TranscriptionLanguage::Spanish => format!(
+            "\n\n# Intervalo a transcribir\nTranscriba solo el intervalo **de {} a {}** de este archivo ({} segundos). No incluya lo que se dice fuera de él.\nEscriba las marcas de tiempo relativas al inicio del intervalo, que corresponde a `00:00:00,000`.",
+            start, end, seconds
+        ),
+        _ => format!(
+            "\n\n# Range to transcribe\nTranscribe only the range **from {} to {}** of this file ({} seconds). Do not output speech outside it.\nWrite timestamps relative to the start of the range, which is `00:00:00,000`.",
+            start, end, seconds
+        ),
+    }
+}
+
+/// Prompt for `transcribe_word_level`; the JSON shape itself is enforced by
+/// the response schema
+pub fn word_level_prompt(duration_ms: Option<u32>, language: &TranscriptionLanguage) -> String {
+    format!(
+        "提供する音声（または動画）ファイルの内容を単語単位で文字起こしし、各単語の開始時刻と終了時刻を出力してください。{}\n\n# 要求事項\n- word: 発話された単語。日本語は文節ではなく単語ごとに区切ってください\n- start_ms / end_ms: 音声の先頭からのミリ秒\n- 単語は発話順に並べ、前の単語の終了時刻より前に次の単語が始まらないようにしてください\n- 句読点は直前の単語に含めてください{}",
+        duration_text(duration_ms),
+        language.output_language_instruction()
+    )
+}
+
+pub fn analyze_topic_prompt(templates: &PromptTemplates, transcription: &str) -> String {
+    render_template(&templates.get(PromptTemplate::AnalyzeTopic), &[("transcription", transcription)])
+}
+
+pub fn create_dictionary_prompt(templates: &PromptTemplates, topic: &str) -> String {
+    render_template(&templates.get(PromptTemplate::CreateDictionary), &[("topic", topic)])
+}
+
+/// Kept short since `detect_language` is meant to be cheap; the answer's
+/// shape comes from the response schema
+pub const DETECT_LANGUAGE_PROMPT: &str = "Identify the main spoken language of this audio. Answer with its ISO 639-1 code and your confidence between 0 and 1.";
+
+/// Prompt turning the grounded dictionary answer into terms for the JSON
+/// schema, which cannot be combined with the search tool
+pub fn structure_dictionary_prompt(answer: &str) -> String {
+    format!("以下の用語辞書から、各用語の表記（surface）とふりがな（reading）を抜き出してください。説明文や見出しは含めないでください。\n\n# 用語辞書\n{}", answer)
+}
+
+/// Prompt turning the plain-text first pass into SRT using the dictionary;
+/// the guidance is Japanese like the rest of the dictionary workflow, with
+/// the filler and speaker rules in the spoken language
+pub fn enhance_with_dictionary_prompt(
+    templates: &PromptTemplates,
+    initial_transcription: &str,
+    dictionary: &str,
+    rules: &SubtitleRules,
+) -> Prompt {
+    Prompt {
+        system_instruction: Some(templates.get(PromptTemplate::SrtRules)),
+        text: render_template(
+            &templates.get(PromptTemplate::EnhanceWithDictionary),
+            &[
+                ("duration", &duration_text(rules.duration_ms)),
+                ("dictionary", dictionary),
+                ("transcription", initial_transcription),
+                ("max_chars", &rules.max_chars_per_subtitle.to_string()),
+                ("filler_rule", &rules.language.filler_rule(rules.verbatim, rules.filler_words)),
+                ("speaker_instruction", rules.language.speaker_instruction(rules.enable_speaker_detection)),
+            ],
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_template_dir() -> PromptTemplates {
+        PromptTemplates::load(std::env::temp_dir().join(format!("prompts_{}", uuid::Uuid::new_v4())))
+    }
+
+    #[test]
+    fn test_render_template() {
+        assert_eq!(
+            render_template("{max_chars}文字 {unknown} {a {duration}}", &[("max_chars", "20"), ("duration", "{max_chars}")]),
+            "20文字 {unknown} {a {max_chars}}"
+        );
+        assert_eq!(render_template("no placeholders", &[("max_chars", "20")]), "no placeholders");
+    }
+
+    #[test]
+    fn test_prompt_template_overrides() {
+        let templates = empty_template_dir();
+        assert_eq!(templates.get(PromptTemplate::AnalyzeTopic), ANALYZE_TOPIC_TEMPLATE);
+
+        let template = PromptTemplate::from_name("analyze_topic").unwrap();
+        templates.set(template, "Topics in: {transcription}").unwrap();
+        let reloaded = PromptTemplates::load(templates.dir.clone());
+        assert_eq!(analyze_topic_prompt(&reloaded, "{topic}"), "Topics in: {topic}");
+
+        reloaded.set(template, "  ").unwrap();
+        assert_eq!(PromptTemplates::load(templates.dir.clone()).get(template), ANALYZE_TOPIC_TEMPLATE);
+        assert!(PromptTemplate::from_name("summary").is_err());
+        std::fs::remove_dir_all(&templates.dir).unwrap();
+    }
+
+    fn rules(language: &TranscriptionLanguage, enable_speaker_detection: bool, duration_ms: Option<u32>) -> SubtitleRules<'_> {
+        SubtitleRules {
+            max_chars_per_subtitle: 20,
+            enable_speaker_detection,
+            duration_ms,
+            language,
+            verbatim: false,
+            filler_words: None,
+        }
+    }
+
+    #[test]
+    fn test_from_code() {
+        assert_eq!(TranscriptionLanguage::from_code(None), TranscriptionLanguage::Japanese);
+        assert_eq!(TranscriptionLanguage::from_code(Some("EN")), TranscriptionLanguage::English);
+        assert_eq!(
+            TranscriptionLanguage::from_code(Some("fr")),
+            TranscriptionLanguage::Other("fr".to_string())
+        );
+    }
+
+    #[test]
+    fn test_build_transcription_prompt_by_model() {
+        let templates = empty_template_dir();
+        let language = TranscriptionLanguage::Japanese;
+        let srt = build_transcription_prompt(&templates, "gemini-2.5-pro", &rules(&language, true, Some(150_500)));
+        assert!(srt.text.contains("**音声ファイルの長さ: 2分30秒 (150500ms)**"));
+        assert!(srt.text.contains("**20文字以内**"));
+        assert!(srt.text.contains("`アオイ: `"));
+        assert!(srt.system_instruction.as_deref().is_some_and(|rules| rules.contains("`hh:mm:ss,ms` の形式を厳守")));
+        assert!(!srt.text.contains("`hh:mm:ss,ms`"));
+
+        let basic = build_transcription_prompt(&templates, "gemini-2.0-flash", &rules(&language, true, Some(150_500)));
+        assert!(basic.text.contains("プレーンテキストで出力"));
+        assert!(!basic.text.contains("音声ファイルの長さ"));
+        assert_eq!(basic.system_instruction, None);
+
+        let without_duration = build_transcription_prompt(&templates, "gemini-2.5-flash", &rules(&language, false, None));
+        assert!(!without_duration.text.contains("音声ファイルの長さ"));
+        assert!(without_duration.text.contains("話者名は付けず"));
+    }
+
+    #[test]
+    fn test_rules_join_the_user_turn_without_system_instruction_support() {
+        let templates = empty_template_dir();
+        let language = TranscriptionLanguage::Japanese;
+        let split = build_transcription_prompt(&templates, "gemini-2.5-pro", &rules(&language, true, None));
+        let single = build_transcription_prompt(&templates, "gemma-3-27b-it", &rules(&language, true, None));
+        assert_eq!(single.system_instruction, None);
+        assert_eq!(single.text, split.combined());
+        assert!(single.text.starts_with(SRT_RULES_TEMPLATE));
+    }
+
+    #[test]
+    fn test_editing_guidance_is_localized() {
+        let japanese = TranscriptionLanguage::Japanese.editing_guidance(20, false, false, None);
+        assert!(japanese.contains("**20文字以内**"));
+        assert!(japanese.contains("「えーっと」「あのー」「なんか」"));
+
+        let english = TranscriptionLanguage::English.editing_guidance(42, true, false, None);
+        assert!(english.contains("**about 42 characters**"));
+        assert!(english.contains("\"um\", \"uh\""));
+        assert!(english.contains("`Alex: `"));
+        assert!(!english.contains("えーっと"));
+
+        let spanish = TranscriptionLanguage::Spanish.editing_guidance(42, false, false, None);
+        assert!(spanish.contains("\"eh\", \"este\""));
+
+        let verbatim = TranscriptionLanguage::Japanese.editing_guidance(20, false, true, None);
+        assert!(!verbatim.contains("フィラーワードの削除"));
+        assert_eq!(
+            verbatim,
+            japanese.replace(
+                "\n    - **フィラーワードの削除:** 会話中の「えーっと」「あのー」「なんか」といった、意味を持たないフィラーワードはすべて削除し、自然で聞き取りやすい文章にしてください。",
+                "\n    - **逐語記録:** 会話中の「えーっと」「あのー」「なんか」といったフィラーワード、言い直し、繰り返し、言いよどみも削除せず、話された通りにすべて記録してください。"
+            )
+        );
+    }
+
+    #[test]
+    fn test_verbatim_enhancement_keeps_fillers_and_other_rules() {
+        let templates = empty_template_dir();
+        let japanese = TranscriptionLanguage::Japanese;
+        let clean_rules = SubtitleRules { max_chars_per_subtitle: 18, ..rules(&japanese, true, None) };
+        let clean = enhance_with_dictionary_prompt(&templates, "えーっと、今日は", "Gemini,じぇみに", &clean_rules);
+        assert!(clean.text.contains("**フィラーワードの削除:** 会話中の「えーっと」「あのー」「なんか」といった"));
+
+        let verbatim_rules = SubtitleRules { verbatim: true, ..clean_rules };
+        let verbatim = enhance_with_dictionary_prompt(&templates, "えーっと、今日は", "Gemini,じぇみに", &verbatim_rules);
+        assert!(!verbatim.text.contains("フィラーワードの削除"));
+        assert!(verbatim.text.contains("言い直し、繰り返し、言いよどみも削除せず"));
+        assert!(verbatim.text.contains("**18文字以内**"));
+        assert!(verbatim.text.contains("`アオイ: `"));
+    }
+
+    #[test]
+    fn test_enhancement_follows_language_and_filler_words() {
+        let templates = empty_template_dir();
+        let english = TranscriptionLanguage::English;
+        let fillers = vec!["well".to_string(), "I mean".to_string()];
+        let rules = SubtitleRules { filler_words: Some(&fillers), ..rules(&english, false, None) };
+        let prompt = enhance_with_dictionary_prompt(&templates, "well, today", "Gemini,じぇみに", &rules);
+        assert!(prompt.text.contains("Delete meaningless filler words such as \"well\", \"I mean\""));
+        assert!(prompt.text.contains("Do not add speaker names"));
+        assert!(!prompt.text.contains("フィラーワードの削除"));
+    }
+
+    #[test]
+    fn test_chunk_range_instruction_is_localized() {
+        let japanese = chunk_range_instruction(&TranscriptionLanguage::Japanese, 600_000, 1_200_000);
+        assert!(japanese.contains("**00:10:00,000 から 00:20:00,000 まで** の区間（600秒間）"));
+
+        let english = chunk_range_instruction(&TranscriptionLanguage::English, 600_000, 1_200_000);
+        assert!(english.contains("**from 00:10:00,000 to 00:20:00,000** of this file (600 seconds)"));
+        assert!(!english.contains("区間"));
+    }
+
+    #[test]
+    fn test_custom_filler_words_replace_the_defaults() {
+        let fillers = vec!["えーっと".to_string(), "ですね".to_string()];
+        let japanese = TranscriptionLanguage::Japanese.editing_guidance(20, false, false, Some(&fillers));
+        assert!(japanese.contains("会話中の「えーっと」「ですね」といった"));
+        assert!(!japanese.contains("「なんか」"));
+
+        let fillers = vec!["so".to_string(), "basically".to_string()];
+        let english = TranscriptionLanguage::English.filler_examples(Some(&fillers));
+        assert_eq!(english, "\"so\", \"basically\"");
+
+        let templates = empty_template_dir();
+        let rules = SubtitleRules { filler_words: Some(&fillers), ..rules(&TranscriptionLanguage::English, false, None) };
+        let basic = build_transcription_prompt(&templates, "gemini-2.0-flash", &rules);
+        assert!(basic.text.contains("フィラーワード（\"so\", \"basically\"等）"));
+    }
+}
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Intermediate results of the transcribe → analyze_topic → create_dictionary
+/// → enhance pipeline, so a failed step can be retried without paying for the
+/// transcription again
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub id: String,
+    /// Unix timestamp (seconds) of the transcription
+    pub created_at: u64,
+    pub source_filename: Option<String>,
+    pub model: String,
+    pub max_chars_per_subtitle: u32,
+    pub enable_speaker_detection: bool,
+    pub duration_ms: Option<u32>,
+    pub transcription: String,
+    pub topic: Option<String>,
+    pub dictionary: Option<String>,
+}
+
+/// Identifier for a new session
+pub fn new_session_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// Sessions stored as one JSON file each in a temporary directory, kept in
+/// Tauri managed state
+pub struct SessionStore {
+    dir: PathBuf,
+    /// Serializes read-modify-write updates of a session file
+    lock: Mutex<()>,
+}
+
+impl SessionStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir, lock: Mutex::new(()) }
+    }
+
+    /// Ids are generated by [`new_session_id`]; anything else could point
+    /// outside the session directory
+    fn path(&self, id: &str) -> Result<PathBuf, String> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("Invalid session id: {}", id));
+        }
+        Ok(self.dir.join(format!("{}.json", id)))
+    }
+
+    fn write(&self, session: &Session) -> Result<(), String> {
+        let path = self.path(&session.id)?;
+        std::fs::create_dir_all(&self.dir).map_err(|e| format!("Failed to create session directory: {}", e))?;
+        let json = serde_json::to_string_pretty(session).map_err(|e| format!("Failed to serialize session: {}", e))?;
+        std::fs::write(path, json).map_err(|e| format!("Failed to save session: {}", e))
+    }
+
+    pub fn save(&self, session: &Session) -> Result<(), String> {
+        let _guard = self.lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        self.write(session)
+    }
+
+    pub fn get(&self, id: &str) -> Result<Session, String> {
+        let content = std::fs::read_to_string(self.path(id)?).map_err(|_| format!("Session {} not found", id))?;
+        serde_json::from_str(&content).map_err(|e| format!("Failed to read session {}: {}", id, e))
+    }
+
+    /// Applies `update` to the stored session and saves it
+    pub fn update(&self, id: &str, update: impl FnOnce(&mut Session)) -> Result<Session, String> {
+        let _guard = self.lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut session = self.get(id)?;
+        update(&mut session);
+        self.write(&session)?;
+        Ok(session)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_round_trip_and_update() {
+        let dir = std::env::temp_dir().join(format!("sessions_{}", uuid::Uuid::new_v4()));
+        let store = SessionStore::new(dir.clone());
+        let session = Session {
+            id: new_session_id(),
+            created_at: 1_700_000_000,
+            source_filename: Some("talk.mp3".to_string()),
+            model: "gemini-2.5-pro".to_string(),
+            max_chars_per_subtitle: 20,
+            enable_speaker_detection: false,
+            duration_ms: Some(60_000),
+            transcription: "1\n00:00:00,000 --> 00:00:02,000\nこんにちは\n".to_string(),
+            topic: None,
+            dictionary: None,
+        };
+        store.save(&session).unwrap();
+        assert_eq!(store.get(&session.id).unwrap(), session);
+
+        let updated = store.update(&session.id, |session| session.topic = Some("AI".to_string())).unwrap();
+        assert_eq!(updated.topic.as_deref(), Some("AI"));
+        assert_eq!(SessionStore::new(dir.clone()).get(&session.id).unwrap(), updated);
+
+        assert!(store.get(&new_session_id()).unwrap_err().contains("not found"));
+        assert!(store.get("../history").unwrap_err().contains("Invalid session id"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
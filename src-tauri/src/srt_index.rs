@@ -0,0 +1,105 @@
+use serde::Serialize;
+use std::sync::Mutex;
+
+use crate::srt_utils::SubtitleCue;
+
+/// Cues around a playback position
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AdjacentCues {
+    /// Up to `n` cues before the position, in time order
+    pub previous: Vec<SubtitleCue>,
+    /// The active cue, `None` during gaps
+    pub current: Option<SubtitleCue>,
+    /// Up to `n` cues after the position, in time order
+    pub next: Vec<SubtitleCue>,
+}
+
+/// Cues of the subtitles loaded in the preview player, sorted by start time
+/// for lookups by playback position. Kept in Tauri managed state.
+#[derive(Default)]
+pub struct SrtIndex {
+    cues: Mutex<Vec<SubtitleCue>>,
+}
+
+impl SrtIndex {
+    fn cues(&self) -> std::sync::MutexGuard<'_, Vec<SubtitleCue>> {
+        self.cues.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Replaces the indexed cues
+    pub fn load(&self, mut cues: Vec<SubtitleCue>) {
+        cues.sort_by_key(|cue| cue.start_ms);
+        *self.cues() = cues;
+    }
+
+    /// Number of cues starting at or before `time_ms`, and whether the last
+    /// of them is still showing
+    fn position(cues: &[SubtitleCue], time_ms: u64) -> (usize, bool) {
+        let started = cues.partition_point(|cue| cue.start_ms <= time_ms);
+        let active = started > 0 && time_ms < cues[started - 1].end_ms;
+        (started, active)
+    }
+
+    /// The cue showing at `time_ms`. With overlapping cues the one that
+    /// started last is returned.
+    pub fn cue_at(&self, time_ms: u64) -> Option<SubtitleCue> {
+        let cues = self.cues();
+        match Self::position(&cues, time_ms) {
+            (started, true) => Some(cues[started - 1].clone()),
+            _ => None,
+        }
+    }
+
+    /// The cue showing at `time_ms` with up to `n` cues on either side
+    pub fn adjacent(&self, time_ms: u64, n: usize) -> AdjacentCues {
+        let cues = self.cues();
+        let (started, active) = Self::position(&cues, time_ms);
+        let previous_end = if active { started - 1 } else { started };
+        AdjacentCues {
+            previous: cues[previous_end.saturating_sub(n)..previous_end].to_vec(),
+            current: active.then(|| cues[started - 1].clone()),
+            next: cues[started..(started + n).min(cues.len())].to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::srt_utils::parse_srt;
+
+    fn index() -> SrtIndex {
+        let index = SrtIndex::default();
+        index.load(
+            parse_srt("3\n00:00:06,000 --> 00:00:08,000\nC\n\n1\n00:00:00,000 --> 00:00:02,000\nA\n\n2\n00:00:02,000 --> 00:00:04,000\nB\n").unwrap(),
+        );
+        index
+    }
+
+    #[test]
+    fn test_cue_at() {
+        let index = index();
+        assert_eq!(index.cue_at(0).map(|cue| cue.index), Some(1));
+        assert_eq!(index.cue_at(2_000).map(|cue| cue.index), Some(2));
+        assert_eq!(index.cue_at(5_000), None);
+        assert_eq!(index.cue_at(7_999).map(|cue| cue.index), Some(3));
+        assert_eq!(index.cue_at(8_000), None);
+        assert_eq!(SrtIndex::default().cue_at(0), None);
+    }
+
+    #[test]
+    fn test_adjacent_cues() {
+        let index = index();
+        let indices = |cues: &[SubtitleCue]| cues.iter().map(|cue| cue.index).collect::<Vec<_>>();
+
+        let around_b = index.adjacent(3_000, 1);
+        assert_eq!(indices(&around_b.previous), vec![1]);
+        assert_eq!(around_b.current.map(|cue| cue.index), Some(2));
+        assert_eq!(indices(&around_b.next), vec![3]);
+
+        let in_gap = index.adjacent(5_000, 5);
+        assert_eq!(indices(&in_gap.previous), vec![1, 2]);
+        assert_eq!(in_gap.current, None);
+        assert_eq!(indices(&in_gap.next), vec![3]);
+    }
+}
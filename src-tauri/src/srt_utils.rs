@@ -1,31 +1,1421 @@
-/// Extracts SRT content from text that may contain markdown code blocks
-pub fn extract_srt_content(text: &str) -> &str {
-    // Pattern to match ```srt ... ``` blocks
-    if let Some(start) = text.find("```srt") {
-        if let Some(end) = text[start + 6..].find("```") {
-            let content_start = start + 6;
-            let content_end = content_start + end;
-            
-            // Skip any whitespace/newline after ```srt and trim trailing whitespace
-            let content = &text[content_start..content_end];
-            return content.trim();
-        }
-    }
-    
-    // Pattern to match generic ``` ... ``` blocks
-    if let Some(start) = text.find("```") {
-        if let Some(end) = text[start + 3..].find("```") {
-            let content_start = start + 3;
-            let content_end = content_start + end;
-            
-            // Skip any whitespace/newline after ``` and trim trailing whitespace
-            let content = &text[content_start..content_end];
-            return content.trim();
-        }
-    }
-    
-    // Return original text if no code blocks found
-    text
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// A fenced code block found in model output
+struct CodeBlock<'a> {
+    /// Lowercased language tag after the opening fence (empty when absent)
+    language: String,
+    content: &'a str,
+}
+
+/// Finds all closed ```` ``` ```` code blocks in `text`.
+///
+/// The opening fence may carry a language tag in any case (` ```srt `,
+/// ` ```SubRip `, ` ```text `) followed by further text on the same line;
+/// the whole info line is skipped. Content is trimmed.
+fn code_blocks(text: &str) -> Vec<CodeBlock<'_>> {
+    let mut blocks = Vec::new();
+    let mut search_from = 0;
+
+    while let Some(open) = text[search_from..].find("```").map(|i| search_from + i) {
+        let after_fence = open + 3;
+        let Some(close) = text[after_fence..].find("```").map(|i| after_fence + i) else {
+            break;
+        };
+
+        let (language, content_start) = match text[after_fence..close].find('\n') {
+            Some(newline) => {
+                let info = text[after_fence..after_fence + newline].trim();
+                if info.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                    let word = info.split_whitespace().next().unwrap_or_default();
+                    (word.to_lowercase(), after_fence + newline + 1)
+                } else {
+                    (String::new(), after_fence)
+                }
+            }
+            None => (String::new(), after_fence),
+        };
+
+        blocks.push(CodeBlock {
+            language,
+            content: text[content_start..close].trim(),
+        });
+        search_from = close + 3;
+    }
+
+    blocks
+}
+
+/// Extracts SRT content from text that may contain markdown code blocks,
+/// cleaned up with [`repair_srt`].
+pub fn extract_srt_content(text: &str) -> String {
+    repair_srt(extract_srt_block(text))
+}
+
+/// Finds the SRT in text that may contain markdown code blocks, exactly as
+/// the model wrote it.
+///
+/// A block tagged `srt` or `subrip` is preferred; otherwise the first code
+/// block of any language is used. Without any code fence, the cues are
+/// located by their timestamp lines so that prose before and after them is
+/// dropped. Text with an unclosed fence is returned unchanged.
+pub fn extract_srt_block(text: &str) -> &str {
+    let blocks = code_blocks(text);
+
+    if blocks.is_empty() && !text.contains("```") {
+        return find_cue_span(text).unwrap_or(text);
+    }
+
+    blocks
+        .iter()
+        .find(|block| matches!(block.language.as_str(), "srt" | "subrip"))
+        .or_else(|| blocks.first())
+        .map_or(text, |block| block.content)
+}
+
+/// Extracts and joins every SRT code block in `text`.
+///
+/// Long answers are sometimes split over several blocks ("続きです…"), so
+/// all blocks that parse as SRT are concatenated and renumbered. The
+/// timestamps must keep increasing across each join. Without any SRT block
+/// this falls back to [`extract_srt_content`].
+pub fn extract_all_srt_content(text: &str) -> Result<String, String> {
+    let mut cues: Vec<SubtitleCue> = Vec::new();
+    let mut found_block = false;
+
+    for block in code_blocks(text) {
+        let Ok(block_cues) = parse_srt(block.content) else {
+            continue;
+        };
+        let Some(first) = block_cues.first() else {
+            continue;
+        };
+        if let Some(previous) = cues.last() {
+            if first.start_ms < previous.start_ms {
+                return Err(format!(
+                    "Timestamps go backwards where SRT blocks are joined ({} after {})",
+                    format_timestamp(first.start_ms),
+                    format_timestamp(previous.start_ms)
+                ));
+            }
+        }
+        found_block = true;
+        cues.extend(block_cues);
+    }
+
+    if !found_block {
+        return Ok(extract_srt_block(text).to_string());
+    }
+
+    renumber(&mut cues);
+    Ok(serialize_srt(&cues))
+}
+
+/// Returns the part of `text` from the first cue (including its index line)
+/// to the end of the last cue's text, or `None` if there is no timing line
+fn find_cue_span(text: &str) -> Option<&str> {
+    // Byte offset and content of every line
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        lines.push((offset, line.trim_end_matches(['\r', '\n'])));
+        offset += line.len();
+    }
+
+    let first_timing = lines.iter().position(|(_, line)| parse_timing_line(line).is_some())?;
+    let last_timing = lines.iter().rposition(|(_, line)| parse_timing_line(line).is_some())?;
+
+    let start_line = match first_timing.checked_sub(1) {
+        Some(index_line) if is_index_line(lines[index_line].1) => index_line,
+        _ => first_timing,
+    };
+    let end_line = lines[last_timing..]
+        .iter()
+        .position(|(_, line)| line.trim().is_empty())
+        .map_or(lines.len(), |blank| last_timing + blank);
+
+    let (start, _) = lines[start_line];
+    let (end_offset, end_text) = lines[end_line - 1];
+    Some(&text[start..end_offset + end_text.len()])
+}
+
+fn is_index_line(line: &str) -> bool {
+    let line = line.trim();
+    !line.is_empty() && line.chars().all(|c| c.is_ascii_digit())
+}
+
+/// A single subtitle block parsed from SRT content
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubtitleCue {
+    pub index: u32,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub lines: Vec<String>,
+}
+
+/// Error returned by [`parse_srt`] pointing at the offending input
+#[derive(Debug, Clone, PartialEq)]
+pub enum SrtParseError {
+    /// The block for subtitle `index` ends before its timestamp line
+    MissingTimestamp { index: u32 },
+    /// Line `line` (1-based) should be a `start --> end` timestamp line
+    InvalidTimestamp { line: usize, content: String },
+}
+
+impl fmt::Display for SrtParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SrtParseError::MissingTimestamp { index } => {
+                write!(f, "Missing timestamp line for subtitle {}", index)
+            }
+            SrtParseError::InvalidTimestamp { line, content } => {
+                write!(f, "Invalid timestamp line at line {}: {}", line, content)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SrtParseError {}
+
+impl From<SrtParseError> for String {
+    fn from(error: SrtParseError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Parses an SRT timestamp such as `00:01:23,456` into milliseconds
+pub fn parse_timestamp(timestamp: &str) -> Option<u64> {
+    let (hms, millis) = timestamp.trim().split_once([',', '.'])?;
+    let mut parts = hms.split(':');
+    let hours: u64 = parts.next()?.parse().ok()?;
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let seconds: u64 = parts.next()?.parse().ok()?;
+    if parts.next().is_some() || minutes >= 60 || seconds >= 60 || millis.len() != 3 {
+        return None;
+    }
+    let millis: u64 = millis.parse().ok()?;
+    Some(((hours * 60 + minutes) * 60 + seconds) * 1000 + millis)
+}
+
+/// Formats milliseconds as an SRT timestamp (`hh:mm:ss,mmm`)
+pub fn format_timestamp(ms: u64) -> String {
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        ms / 3_600_000,
+        (ms % 3_600_000) / 60_000,
+        (ms % 60_000) / 1000,
+        ms % 1000
+    )
+}
+
+/// Parses a `start --> end` line, ignoring any trailing cue settings
+fn parse_timing_line(line: &str) -> Option<(u64, u64)> {
+    let (start, rest) = line.split_once("-->")?;
+    let end = rest.split_whitespace().next()?;
+    Some((parse_timestamp(start)?, parse_timestamp(end)?))
+}
+
+/// Parses SRT content into cues.
+///
+/// Blank lines between blocks are expected, but a block that starts right
+/// after the previous cue's text (index line followed by a timing line) is
+/// also accepted since the model occasionally omits the separator.
+pub fn parse_srt(content: &str) -> Result<Vec<SubtitleCue>, SrtParseError> {
+    let lines: Vec<&str> = content
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .collect();
+    let is_cue_start = |i: usize| {
+        lines[i].trim().parse::<u32>().is_ok()
+            && lines.get(i + 1).is_some_and(|next| parse_timing_line(next).is_some())
+    };
+
+    let mut cues = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if lines[i].trim().is_empty() {
+            i += 1;
+            continue;
+        }
+
+        let index = match lines[i].trim().parse::<u32>() {
+            Ok(index) if !lines[i].contains("-->") => {
+                i += 1;
+                index
+            }
+            _ => cues.len() as u32 + 1,
+        };
+
+        let timing_line = lines
+            .get(i)
+            .ok_or(SrtParseError::MissingTimestamp { index })?;
+        let (start_ms, end_ms) = parse_timing_line(timing_line)
+            .ok_or_else(|| SrtParseError::InvalidTimestamp {
+                line: i + 1,
+                content: timing_line.to_string(),
+            })?;
+        i += 1;
+
+        let mut text_lines = Vec::new();
+        while i < lines.len() && !lines[i].trim().is_empty() && !is_cue_start(i) {
+            text_lines.push(lines[i].trim().to_string());
+            i += 1;
+        }
+
+        cues.push(SubtitleCue {
+            index,
+            start_ms,
+            end_ms,
+            lines: text_lines,
+        });
+    }
+
+    Ok(cues)
+}
+
+/// Serializes cues back into SRT, numbering them by their `index` field
+pub fn serialize_srt(cues: &[SubtitleCue]) -> String {
+    cues.iter()
+        .map(|cue| {
+            format!(
+                "{}\n{} --> {}\n{}\n",
+                cue.index,
+                format_timestamp(cue.start_ms),
+                format_timestamp(cue.end_ms),
+                cue.lines.join("\n")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Re-serializes SRT content through the parser so that numbering, timestamp
+/// formatting and block separators are consistent. Content that does not
+/// parse as SRT (for example a plain-text transcription) is returned as-is.
+pub fn normalize_srt(content: &str) -> String {
+    match parse_srt(content) {
+        Ok(mut cues) if !cues.is_empty() => {
+            renumber(&mut cues);
+            serialize_srt(&cues)
+        }
+        _ => content.to_string(),
+    }
+}
+
+/// Renumbers cues sequentially starting from 1
+pub fn renumber(cues: &mut [SubtitleCue]) {
+    for (i, cue) in cues.iter_mut().enumerate() {
+        cue.index = i as u32 + 1;
+    }
+}
+
+/// Splits a leading speaker label such as `アオイ: ` or `話者1：` from a line.
+///
+/// Half-width colons must be followed by a space so that times like `10:30`
+/// are not mistaken for labels.
+pub fn split_speaker(line: &str) -> Option<(&str, &str)> {
+    let (label, text) = if let Some((label, text)) = line.split_once('：') {
+        (label, text.trim_start())
+    } else {
+        let (label, text) = line.split_once(':')?;
+        // Without a following space, `10:30` or `https://` are not labels
+        if !text.starts_with(' ') && text.starts_with(|c: char| c.is_ascii_digit() || c == '/') {
+            return None;
+        }
+        (label, text.trim_start())
+    };
+
+    let label_chars = label.chars().count();
+    let looks_like_label = label_chars > 0
+        && label_chars <= 20
+        && label.trim() == label
+        && !label.contains(['。', '、', '！', '？', ',', '.', '!', '?', ':', '「', '」']);
+
+    looks_like_label.then_some((label, text))
+}
+
+/// Returns the speaker label of a cue, if its first line has one
+pub fn cue_speaker(cue: &SubtitleCue) -> Option<&str> {
+    cue.lines.first().and_then(|line| split_speaker(line)).map(|(speaker, _)| speaker)
+}
+
+/// Returns the cue text on one line without its leading speaker label
+pub fn cue_body(cue: &SubtitleCue) -> String {
+    cue.lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| match split_speaker(line) {
+            Some((_, text)) if i == 0 => text,
+            _ => line.as_str(),
+        })
+        .fold(String::new(), |body, line| join_text(&body, line))
+}
+
+/// Joins two pieces of cue text, inserting a space only between words of
+/// space-separated scripts so that Japanese text is concatenated directly
+pub fn join_text(left: &str, right: &str) -> String {
+    if left.is_empty() {
+        return right.to_string();
+    }
+    let needs_space = left.chars().last().is_some_and(|c| c.is_ascii_alphanumeric() || c.is_ascii_punctuation())
+        && right.chars().next().is_some_and(|c| c.is_ascii_alphanumeric());
+    if needs_space {
+        format!("{} {}", left, right)
+    } else {
+        format!("{}{}", left, right)
+    }
+}
+
+/// Applies `f` to every line of every cue that starts with a speaker label,
+/// passing the label and the text after the separator
+fn map_speaker_lines(srt: &str, f: impl Fn(&str, &str, &str) -> String) -> Result<String, String> {
+    let mut cues = parse_srt(srt)?;
+    for cue in cues.iter_mut() {
+        for line in cue.lines.iter_mut() {
+            if let Some((label, text)) = split_speaker(line) {
+                *line = f(line, label, text);
+            }
+        }
+    }
+    Ok(serialize_srt(&cues))
+}
+
+/// Removes `話者: ` style labels from every line
+pub fn remove_speaker_labels(srt: &str) -> Result<String, String> {
+    map_speaker_lines(srt, |_, _, text| text.to_string())
+}
+
+/// Renames speaker labels found in `mapping`, keeping the original
+/// separator; labels not in the mapping are left alone
+pub fn remap_speaker_labels(srt: &str, mapping: &HashMap<String, String>) -> Result<String, String> {
+    map_speaker_lines(srt, |line, label, _| match mapping.get(label) {
+        Some(name) => format!("{}{}", name, &line[label.len()..]),
+        None => line.to_string(),
+    })
+}
+
+/// Separators that are removed together with the filler before them
+const FILLER_SEPARATORS: [char; 5] = ['、', '，', ',', ' ', '　'];
+
+fn is_word_break(c: char) -> bool {
+    c.is_whitespace() || "、。，,.！？!?…「」".contains(c)
+}
+
+/// Removes fillers that stand as a word of their own, i.e. after a word break
+/// and followed by one. Elongated fillers such as `えーっと` are unambiguous
+/// and are also removed when text follows directly.
+fn strip_fillers(text: &str, fillers: &[&str]) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut at_break = true;
+
+    while let Some(c) = rest.chars().next() {
+        if at_break {
+            let filler = fillers.iter().find(|filler| {
+                rest.len() >= filler.len()
+                    && rest.as_bytes()[..filler.len()].eq_ignore_ascii_case(filler.as_bytes())
+                    && (filler.contains('ー') || rest[filler.len()..].chars().next().is_none_or(is_word_break))
+            });
+            if let Some(filler) = filler {
+                rest = rest[filler.len()..].trim_start_matches(FILLER_SEPARATORS);
+                // Drop a separator left dangling before the end of a sentence
+                if rest.is_empty() || rest.starts_with(['。', '！', '？', '!', '?', '.']) {
+                    result.truncate(result.trim_end_matches(FILLER_SEPARATORS).len());
+                }
+                continue;
+            }
+        }
+        result.push(c);
+        at_break = is_word_break(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    let result = result.split(' ').filter(|word| !word.is_empty()).collect::<Vec<_>>().join(" ");
+    let result = result.trim_start_matches(FILLER_SEPARATORS).trim_end();
+    if result.chars().all(is_word_break) {
+        String::new()
+    } else {
+        result.to_string()
+    }
+}
+
+/// Removes filler words from cue text, keeping speaker labels, and drops
+/// lines and cues left empty. Longer fillers are tried first.
+pub fn remove_filler_words(srt: &str, fillers: &[&str]) -> Result<String, String> {
+    let mut fillers = fillers.to_vec();
+    fillers.retain(|filler| !filler.is_empty());
+    fillers.sort_by_key(|filler| std::cmp::Reverse(filler.len()));
+
+    let mut cues = parse_srt(srt)?;
+    for cue in cues.iter_mut() {
+        cue.lines = cue
+            .lines
+            .iter()
+            .filter_map(|line| {
+                // The body is a suffix of the line, so what precedes it is the label and separator
+                let body = split_speaker(line).map_or(line.as_str(), |(_, body)| body);
+                let stripped = strip_fillers(body, &fillers);
+                (!stripped.is_empty()).then(|| format!("{}{}", &line[..line.len() - body.len()], stripped))
+            })
+            .collect();
+    }
+    cues.retain(|cue| !cue.lines.is_empty());
+    renumber(&mut cues);
+    Ok(serialize_srt(&cues))
+}
+
+/// HTML tags kept by [`strip_markup`] by default; italics are part of SRT
+pub const DEFAULT_ALLOWED_TAGS: [&str; 1] = ["i"];
+
+/// Length of the HTML tag starting at the beginning of `text` and its
+/// lowercase name, or `None` when `text` does not start with a tag. A tag
+/// name must start with a letter, so `<3` and `a < b` are left alone.
+fn html_tag(text: &str) -> Option<(usize, String)> {
+    let inner = text.strip_prefix('<')?;
+    let name_start = inner.strip_prefix('/').unwrap_or(inner);
+    if !name_start.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let end = inner.find(['>', '<'])?;
+    if inner.as_bytes()[end] != b'>' {
+        return None;
+    }
+    let name: String = name_start
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .collect();
+    Some((end + 2, name.to_ascii_lowercase()))
+}
+
+/// Removes `*emphasis*` markers around non-space text, keeping the text
+fn strip_single_asterisks(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut removed = vec![false; chars.len()];
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '*' && chars.get(i + 1).is_some_and(|c| !c.is_whitespace() && *c != '*') {
+            let close = (i + 2..chars.len()).find(|&j| chars[j] == '*' && !chars[j - 1].is_whitespace());
+            if let Some(close) = close {
+                removed[i] = true;
+                removed[close] = true;
+                i = close + 1;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    chars.iter().zip(removed).filter(|(_, removed)| !removed).map(|(c, _)| c).collect()
+}
+
+/// Strips HTML tags not in `allowed_tags` and markdown emphasis markers
+/// (`**`, `__`, `*`) from a line of cue text
+pub fn strip_markup(text: &str, allowed_tags: &[&str]) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(open) = rest.find('<') {
+        stripped.push_str(&rest[..open]);
+        rest = &rest[open..];
+        match html_tag(rest) {
+            Some((len, name)) => {
+                if allowed_tags.iter().any(|tag| tag.eq_ignore_ascii_case(&name)) {
+                    stripped.push_str(&rest[..len]);
+                }
+                rest = &rest[len..];
+            }
+            None => {
+                stripped.push('<');
+                rest = &rest[1..];
+            }
+        }
+    }
+    stripped.push_str(rest);
+
+    strip_single_asterisks(&stripped.replace("**", "").replace("__", "")).trim().to_string()
+}
+
+/// Applies [`strip_markup`] to every cue and drops lines and cues left empty
+pub fn sanitize_markup(srt: &str, allowed_tags: &[&str]) -> Result<String, String> {
+    let mut cues = parse_srt(srt)?;
+    for cue in cues.iter_mut() {
+        cue.lines = cue
+            .lines
+            .iter()
+            .map(|line| strip_markup(line, allowed_tags))
+            .filter(|line| !line.is_empty())
+            .collect();
+    }
+    cues.retain(|cue| !cue.lines.is_empty());
+    renumber(&mut cues);
+    Ok(serialize_srt(&cues))
+}
+
+/// Merges adjacent short cues.
+///
+/// A cue absorbs the following one when their combined text is at most
+/// `min_chars` characters, the gap between them is at most `max_gap_ms`,
+/// and the following cue does not start a different speaker's turn. The
+/// merged cue spans both time ranges and its text is joined into one line.
+pub fn merge_short_cues(srt: &str, min_chars: u32, max_gap_ms: u32) -> Result<String, String> {
+    let cues = parse_srt(srt)?;
+
+    let mut merged: Vec<SubtitleCue> = Vec::with_capacity(cues.len());
+    for cue in cues {
+        if let Some(last) = merged.last_mut() {
+            let last_text = last.lines.iter().fold(String::new(), |text, line| join_text(&text, line));
+            let next_speaker = cue_speaker(&cue);
+            let same_speaker = next_speaker.is_none() || next_speaker == cue_speaker(last);
+            let text = cue_body(&cue);
+            let combined_chars = last_text.chars().count() + text.chars().count();
+            let gap = cue.start_ms.saturating_sub(last.end_ms);
+
+            if same_speaker && combined_chars <= min_chars as usize && gap <= max_gap_ms as u64 {
+                last.lines = vec![join_text(&last_text, &text)];
+                last.end_ms = last.end_ms.max(cue.end_ms);
+                continue;
+            }
+        }
+        merged.push(cue);
+    }
+
+    renumber(&mut merged);
+    Ok(serialize_srt(&merged))
+}
+
+/// Splits text into segments that each end at sentence punctuation or a space
+fn split_segments(text: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    for c in text.chars() {
+        current.push(c);
+        if matches!(c, '。' | '、' | '！' | '？' | '!' | '?' | ' ' | '　') {
+            segments.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        segments.push(current);
+    }
+    segments
+}
+
+/// Packs segments greedily into pieces of at most `max_chars` characters,
+/// hard-splitting any single segment that is longer than the limit
+fn pack_segments(segments: Vec<String>, max_chars: usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    for segment in segments {
+        if current.trim().chars().count() + segment.trim_end().chars().count() <= max_chars {
+            current.push_str(&segment);
+            continue;
+        }
+        if !current.trim().is_empty() {
+            pieces.push(current.trim().to_string());
+        }
+        current = String::new();
+
+        let chars: Vec<char> = segment.chars().collect();
+        let mut chunks = chars.chunks(max_chars).peekable();
+        while let Some(chunk) = chunks.next() {
+            let chunk: String = chunk.iter().collect();
+            if chunks.peek().is_some() {
+                pieces.push(chunk.trim().to_string());
+            } else {
+                current = chunk;
+            }
+        }
+    }
+    if !current.trim().is_empty() {
+        pieces.push(current.trim().to_string());
+    }
+    pieces
+}
+
+/// Splits a cue into several cues whose text fits `max_chars`, dividing the
+/// time span proportionally to each piece's character count
+fn split_cue(cue: SubtitleCue, max_chars: usize) -> Vec<SubtitleCue> {
+    let speaker = cue_speaker(&cue).map(str::to_string);
+    let body = cue_body(&cue);
+    if body.chars().count() <= max_chars {
+        return vec![cue];
+    }
+
+    let pieces = pack_segments(split_segments(&body), max_chars);
+    let total_chars: u64 = pieces.iter().map(|piece| piece.chars().count() as u64).sum();
+    let duration = cue.end_ms.saturating_sub(cue.start_ms);
+
+    let mut elapsed_chars = 0;
+    pieces
+        .into_iter()
+        .enumerate()
+        .map(|(i, piece)| {
+            let start_ms = cue.start_ms + duration * elapsed_chars / total_chars;
+            elapsed_chars += piece.chars().count() as u64;
+            let end_ms = cue.start_ms + duration * elapsed_chars / total_chars;
+            let text = match &speaker {
+                Some(speaker) if i == 0 => format!("{}: {}", speaker, piece),
+                _ => piece,
+            };
+            SubtitleCue {
+                index: cue.index,
+                start_ms,
+                end_ms,
+                lines: vec![text],
+            }
+        })
+        .collect()
+}
+
+/// Broadcast style guides keep cues between 1 and 7 seconds on screen
+pub const DEFAULT_MIN_CUE_MS: u64 = 1000;
+pub const DEFAULT_MAX_CUE_MS: u64 = 7000;
+
+/// Minimum and maximum cue duration for [`enforce_duration_limits`]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct DurationLimits {
+    pub min_ms: u64,
+    pub max_ms: u64,
+}
+
+/// How many cues [`enforce_duration_limits`] changed, by kind of change
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DurationAdjustments {
+    pub extended: usize,
+    pub merged: usize,
+    pub split: usize,
+}
+
+fn cue_duration(cue: &SubtitleCue) -> u64 {
+    cue.end_ms.saturating_sub(cue.start_ms)
+}
+
+/// Whether `next` can be appended to `cue` without a speaker change or
+/// exceeding `max_ms`
+fn can_absorb(cue: &SubtitleCue, next: &SubtitleCue, max_ms: u64) -> bool {
+    let next_speaker = cue_speaker(next);
+    (next_speaker.is_none() || next_speaker == cue_speaker(cue)) && next.end_ms.saturating_sub(cue.start_ms) <= max_ms
+}
+
+fn absorb(cue: &mut SubtitleCue, next: &SubtitleCue) {
+    let text = cue.lines.iter().fold(String::new(), |text, line| join_text(&text, line));
+    cue.lines = vec![join_text(&text, &cue_body(next))];
+    cue.end_ms = cue.end_ms.max(next.end_ms);
+}
+
+/// Brings cue durations within `limits`.
+///
+/// Cues longer than `max_ms` are split at sentence boundaries with timing
+/// proportional to the text. Cues shorter than `min_ms` are first extended
+/// into the silence around them; when there is not enough silence they are
+/// merged into the following cue, or else the preceding one, as long as the
+/// speaker stays the same and the result does not exceed `max_ms`.
+pub fn enforce_duration_limits(cues: Vec<SubtitleCue>, limits: DurationLimits) -> (Vec<SubtitleCue>, DurationAdjustments) {
+    let DurationLimits { min_ms, max_ms } = limits;
+    let mut adjustments = DurationAdjustments::default();
+
+    let mut result: Vec<SubtitleCue> = Vec::with_capacity(cues.len());
+    for cue in cues {
+        let duration = cue_duration(&cue);
+        if max_ms == 0 || duration <= max_ms {
+            result.push(cue);
+            continue;
+        }
+        let parts = duration.div_ceil(max_ms) as usize;
+        let max_chars = cue_body(&cue).chars().count().div_ceil(parts).max(1);
+        let pieces = split_cue(cue, max_chars);
+        if pieces.len() > 1 {
+            adjustments.split += 1;
+        }
+        result.extend(pieces);
+    }
+
+    let mut i = 0;
+    while i < result.len() {
+        if cue_duration(&result[i]) >= min_ms {
+            i += 1;
+            continue;
+        }
+
+        let next_start = result.get(i + 1).map_or(u64::MAX, |next| next.start_ms);
+        let previous_end = i.checked_sub(1).map_or(0, |previous| result[previous].end_ms);
+        let cue = &mut result[i];
+        let original = (cue.start_ms, cue.end_ms);
+        cue.end_ms = (cue.start_ms + min_ms).min(next_start.max(cue.end_ms));
+        if cue_duration(cue) < min_ms {
+            cue.start_ms = cue.end_ms.saturating_sub(min_ms).max(previous_end.min(cue.start_ms));
+        }
+        if cue_duration(cue) >= min_ms {
+            adjustments.extended += 1;
+            i += 1;
+            continue;
+        }
+
+        let extended = (result[i].start_ms, result[i].end_ms);
+        (result[i].start_ms, result[i].end_ms) = original;
+        if i + 1 < result.len() && can_absorb(&result[i], &result[i + 1], max_ms) {
+            let next = result.remove(i + 1);
+            absorb(&mut result[i], &next);
+            adjustments.merged += 1;
+        } else if i > 0 && can_absorb(&result[i - 1], &result[i], max_ms) {
+            let cue = result.remove(i);
+            absorb(&mut result[i - 1], &cue);
+            adjustments.merged += 1;
+        } else {
+            // Nothing to merge with, so keep as much of the extension as there was room for
+            (result[i].start_ms, result[i].end_ms) = extended;
+            if extended != original {
+                adjustments.extended += 1;
+            }
+            i += 1;
+        }
+    }
+
+    renumber(&mut result);
+    (result, adjustments)
+}
+
+/// Cues on screen for less than this are flagged by [`lint_srt`]
+pub const LINT_MIN_DISPLAY_MS: u64 = 700;
+/// Reading speed above which [`lint_srt`] flags a cue
+pub const LINT_MAX_CHARS_PER_SECOND: f64 = 20.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintKind {
+    /// A line has more than `max_chars` characters
+    LineTooLong,
+    /// The cue is shown for less than [`LINT_MIN_DISPLAY_MS`]
+    TooShort,
+    /// The cue needs more than [`LINT_MAX_CHARS_PER_SECOND`] to read
+    TooFast,
+}
+
+/// A readability problem in one cue
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LintWarning {
+    pub cue_index: u32,
+    pub start_ms: u64,
+    pub kind: LintKind,
+    pub message: String,
+    /// Characters per second of the cue, without speaker labels and whitespace
+    pub chars_per_second: f64,
+}
+
+/// Characters in the cue, without speaker labels and whitespace
+fn text_chars(cue: &SubtitleCue) -> usize {
+    cue_body(cue).chars().filter(|c| !c.is_whitespace()).count()
+}
+
+/// Reading speed of the cue; a cue without duration counts as one second
+fn chars_per_second(cue: &SubtitleCue) -> f64 {
+    let duration_ms = cue_duration(cue);
+    let char_count = text_chars(cue);
+    if duration_ms == 0 { char_count as f64 } else { char_count as f64 * 1000.0 / duration_ms as f64 }
+}
+
+/// Checks every cue for lines longer than `max_chars` (speaker labels not
+/// counted), display times under [`LINT_MIN_DISPLAY_MS`] and reading speeds
+/// over [`LINT_MAX_CHARS_PER_SECOND`]. Warnings are in cue order.
+pub fn lint_srt(srt: &str, max_chars: u32) -> Result<Vec<LintWarning>, String> {
+    if max_chars == 0 {
+        return Err("max_chars must be greater than 0".to_string());
+    }
+
+    let mut warnings = Vec::new();
+    for cue in parse_srt(srt)? {
+        let duration_ms = cue_duration(&cue);
+        let chars_per_second = chars_per_second(&cue);
+        let mut warn = |kind, message| {
+            warnings.push(LintWarning {
+                cue_index: cue.index,
+                start_ms: cue.start_ms,
+                kind,
+                message,
+                chars_per_second,
+            })
+        };
+
+        for (i, line) in cue.lines.iter().enumerate() {
+            let text = split_speaker(line).map_or(line.as_str(), |(_, text)| text);
+            let line_chars = text.trim().chars().count();
+            if line_chars > max_chars as usize {
+                warn(LintKind::LineTooLong, format!("Line {} has {} characters (max {})", i + 1, line_chars, max_chars));
+            }
+        }
+        if duration_ms < LINT_MIN_DISPLAY_MS {
+            warn(LintKind::TooShort, format!("Shown for {} ms (min {} ms)", duration_ms, LINT_MIN_DISPLAY_MS));
+        }
+        if chars_per_second > LINT_MAX_CHARS_PER_SECOND {
+            warn(LintKind::TooFast, format!("{:.1} characters per second (max {})", chars_per_second, LINT_MAX_CHARS_PER_SECOND));
+        }
+    }
+    Ok(warnings)
+}
+
+/// Markers the prompts ask for when speech cannot be made out
+pub const UNCLEAR_MARKERS: [&str; 2] = ["[不明瞭]", "[inaudible]"];
+/// Reading speed below which a cue likely misses words; see [`estimate_cue_quality`]
+pub const QUALITY_MIN_CHARS_PER_SECOND: f64 = 1.0;
+/// Cues with fewer characters are likely fragments
+pub const QUALITY_MIN_TEXT_CHARS: usize = 3;
+
+/// Rough 0–1 score per cue of how likely its text is right, for picking the
+/// cues to review first. Unclear-speech markers, reading speeds above
+/// [`LINT_MAX_CHARS_PER_SECOND`] or below [`QUALITY_MIN_CHARS_PER_SECOND`],
+/// and very short text each lower the score; an empty cue scores 0.
+pub fn estimate_cue_quality(cues: &[SubtitleCue]) -> Vec<f32> {
+    cues.iter()
+        .map(|cue| {
+            let char_count = text_chars(cue);
+            if char_count == 0 {
+                return 0.0;
+            }
+            let text = cue.lines.join(" ");
+            let chars_per_second = chars_per_second(cue);
+
+            let mut score = 1.0;
+            if UNCLEAR_MARKERS.iter().any(|marker| text.contains(marker)) {
+                score *= 0.3;
+            }
+            if chars_per_second > LINT_MAX_CHARS_PER_SECOND {
+                score *= 0.5;
+            } else if chars_per_second < QUALITY_MIN_CHARS_PER_SECOND {
+                score *= 0.6;
+            }
+            if char_count < QUALITY_MIN_TEXT_CHARS {
+                score *= 0.7;
+            }
+            score
+        })
+        .collect()
+}
+
+/// Appends the lines of each translated cue below the original text.
+///
+/// Timing always comes from `original`; the translation must contain
+/// exactly one cue per original cue.
+pub fn merge_translation(original: &[SubtitleCue], translated: &[SubtitleCue]) -> Result<Vec<SubtitleCue>, String> {
+    if original.len() != translated.len() {
+        return Err(format!(
+            "Translation returned {} subtitles but the original has {}",
+            translated.len(),
+            original.len()
+        ));
+    }
+
+    Ok(original
+        .iter()
+        .zip(translated)
+        .map(|(cue, translation)| {
+            let mut cue = cue.clone();
+            cue.lines.extend(translation.lines.iter().cloned());
+            cue
+        })
+        .collect())
+}
+
+/// Returns an error naming the first cue that starts before its predecessor
+pub fn check_monotonic(cues: &[SubtitleCue]) -> Result<(), String> {
+    for pair in cues.windows(2) {
+        if pair[1].start_ms < pair[0].start_ms || pair[1].end_ms < pair[1].start_ms {
+            return Err(format!(
+                "Timestamps are not monotonic at subtitle {} ({})",
+                pair[1].index,
+                format_timestamp(pair[1].start_ms)
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Sorts cues by start time and resolves overlaps, returning how many cues
+/// were adjusted.
+///
+/// When a cue starts before the previous one ends, the previous cue is cut
+/// to end `min_gap_ms` before it. If that would leave the previous cue with
+/// no duration (it starts at the same time or later), the cue is instead
+/// moved to start `min_gap_ms` after the previous one ends, keeping its
+/// length.
+pub fn fix_overlaps(cues: &mut [SubtitleCue], min_gap_ms: u64) -> usize {
+    cues.sort_by_key(|cue| cue.start_ms);
+
+    let mut fixed = 0;
+    for i in 1..cues.len() {
+        let (before, after) = cues.split_at_mut(i);
+        let (prev, cue) = (&mut before[i - 1], &mut after[0]);
+        if cue.start_ms >= prev.end_ms {
+            continue;
+        }
+
+        let clamped_end = cue.start_ms.saturating_sub(min_gap_ms);
+        if clamped_end > prev.start_ms {
+            prev.end_ms = clamped_end;
+        } else {
+            let duration = cue.end_ms.saturating_sub(cue.start_ms).max(1);
+            cue.start_ms = prev.end_ms + min_gap_ms;
+            cue.end_ms = cue.start_ms + duration;
+        }
+        fixed += 1;
+    }
+    fixed
+}
+
+/// Sorts and renumbers cues by start time, then extends cues shorter than
+/// `min_ms` and cuts cues longer than `max_ms`, returning how many cues were
+/// adjusted.
+///
+/// Unlike [`enforce_duration_limits`] this only moves end times. A short cue
+/// is extended at most to `min_gap_ms` before the next cue starts, so it may
+/// stay below `min_ms`; it is never shortened, even if it already overlaps.
+pub fn enforce_durations(cues: &mut [SubtitleCue], min_ms: u64, max_ms: u64, min_gap_ms: u64) -> usize {
+    cues.sort_by_key(|cue| cue.start_ms);
+    renumber(cues);
+
+    let mut adjusted = 0;
+    for i in 0..cues.len() {
+        let next_start = cues.get(i + 1).map(|next| next.start_ms);
+        let cue = &mut cues[i];
+        let duration = cue_duration(cue);
+        let end_ms = if duration > max_ms {
+            cue.start_ms + max_ms
+        } else if duration < min_ms {
+            let target = cue.start_ms + min_ms;
+            let limit = next_start.map_or(target, |start| start.saturating_sub(min_gap_ms));
+            target.min(limit).max(cue.end_ms)
+        } else {
+            cue.end_ms
+        };
+
+        if end_ms != cue.end_ms {
+            cue.end_ms = end_ms;
+            adjusted += 1;
+        }
+    }
+    adjusted
+}
+
+/// Splits cues whose text exceeds `max_chars` at Japanese sentence
+/// punctuation (。、！？) or spaces, then renumbers the result
+pub fn split_long_cues(srt: &str, max_chars: u32) -> Result<String, String> {
+    if max_chars == 0 {
+        return Err("max_chars must be greater than 0".to_string());
+    }
+
+    let cues = parse_srt(srt)?;
+    let mut split: Vec<SubtitleCue> = cues
+        .into_iter()
+        .flat_map(|cue| split_cue(cue, max_chars as usize))
+        .collect();
+
+    renumber(&mut split);
+    check_monotonic(&split)?;
+    Ok(serialize_srt(&split))
+}
+
+/// Characters that must not start a line (kinsoku shori)
+const NO_LINE_START: [char; 14] = ['、', '。', '，', '．', '」', '』', '）', '】', '！', '？', '・', '…', '!', '?'];
+
+/// Characters that must not end a line
+const NO_LINE_END: [char; 4] = ['「', '『', '（', '【'];
+
+/// Wraps `text` into at most `max_lines` lines of `max_line_chars` Unicode
+/// scalar values. Lines never start with closing punctuation or end with an
+/// opening bracket; text with spaces breaks at the last space that fits.
+/// Whatever does not fit stays on the last line rather than being dropped.
+fn wrap_text(text: &str, max_line_chars: usize, max_lines: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let max_line_chars = max_line_chars.max(2);
+    let mut lines = Vec::new();
+    let mut start = 0;
+
+    while start < chars.len() {
+        if lines.len() + 1 >= max_lines.max(1) || chars.len() - start <= max_line_chars {
+            lines.push(chars[start..].iter().collect::<String>().trim().to_string());
+            break;
+        }
+
+        let mut end = start + max_line_chars;
+        if let Some(space) = chars[start + 1..=end].iter().rposition(|c| *c == ' ') {
+            end = start + 1 + space;
+        }
+        while end > start + 1 && (NO_LINE_START.contains(&chars[end]) || NO_LINE_END.contains(&chars[end - 1])) {
+            end -= 1;
+        }
+
+        lines.push(chars[start..end].iter().collect::<String>().trim().to_string());
+        start = end;
+        while chars.get(start) == Some(&' ') {
+            start += 1;
+        }
+    }
+
+    lines
+}
+
+/// Rewraps the text of every cue into at most `max_lines` lines of
+/// `max_line_chars` characters following Japanese kinsoku rules. Existing
+/// line breaks are folded first; a speaker label stays attached to the first
+/// line and does not count towards its length.
+pub fn wrap_cue_lines(cues: &mut [SubtitleCue], max_line_chars: usize, max_lines: usize) {
+    for cue in cues.iter_mut() {
+        let speaker = cue_speaker(cue).map(str::to_string);
+        let mut lines = wrap_text(&cue_body(cue), max_line_chars, max_lines);
+        if let (Some(speaker), Some(first)) = (speaker, lines.first_mut()) {
+            *first = format!("{}: {}", speaker, first);
+        }
+        cue.lines = lines;
+    }
+}
+
+/// Closes a code fence left open by a response that was cut off, so the
+/// usual extraction can find the block
+pub fn close_truncated_fence(text: &str) -> String {
+    if text.matches("```").count() % 2 == 1 {
+        format!("{}\n```", text.trim_end())
+    } else {
+        text.to_string()
+    }
+}
+
+/// Normalizes newlines to `\n`, drops trailing blocks without a valid
+/// timestamp line (a cue cut off after its index, or a closing remark) and
+/// ends every block with a blank line. Content without any timestamp line is
+/// not SRT, e.g. a plain-text transcription, and only has its newlines
+/// normalized.
+pub fn repair_srt(content: &str) -> String {
+    let content = content.replace("\r\n", "\n").replace('\r', "\n");
+    let mut blocks: Vec<Vec<&str>> = Vec::new();
+    let mut block = Vec::new();
+    for line in content.lines() {
+        if !line.trim().is_empty() {
+            block.push(line);
+        } else if !block.is_empty() {
+            blocks.push(std::mem::take(&mut block));
+        }
+    }
+    if !block.is_empty() {
+        blocks.push(block);
+    }
+
+    let has_timing = |block: &Vec<&str>| block.iter().any(|line| parse_timing_line(line).is_some());
+    if !blocks.iter().any(has_timing) {
+        return content;
+    }
+    while blocks.last().is_some_and(|block| !has_timing(block)) {
+        blocks.pop();
+    }
+    blocks.iter().map(|block| format!("{}\n\n", block.join("\n"))).collect()
+}
+
+/// Removes the final block of `srt` when it is incomplete (a lone index, a
+/// partial timestamp, or a timestamp without text), or unconditionally with
+/// `drop_last`, e.g. when the output token limit cut the text mid-sentence.
+///
+/// Returns the remaining content and whether a block was dropped.
+pub fn drop_incomplete_final_cue(srt: &str, drop_last: bool) -> (String, bool) {
+    let lines: Vec<&str> = srt.trim_end().lines().map(|line| line.trim_end_matches('\r')).collect();
+    let block_start = lines
+        .iter()
+        .rposition(|line| line.trim().is_empty())
+        .map_or(0, |blank| blank + 1);
+    let final_block = &lines[block_start..];
+    if final_block.is_empty() {
+        return (srt.to_string(), false);
+    }
+
+    let has_text_after_timing = final_block
+        .iter()
+        .position(|line| parse_timing_line(line).is_some())
+        .is_some_and(|timing| timing + 1 < final_block.len());
+    if has_text_after_timing && !drop_last {
+        return (srt.to_string(), false);
+    }
+
+    let remaining = lines[..block_start].join("\n");
+    let remaining = remaining.trim_end();
+    (if remaining.is_empty() { String::new() } else { format!("{}\n", remaining) }, true)
+}
+
+/// Appends the cues of a continuation response to `srt`.
+///
+/// Continuations usually restate the last cue or overlap it slightly, so
+/// cues ending before the seam or repeating the last cue's text are dropped
+/// and a slightly early start is moved to the seam.
+pub fn stitch_continuation(srt: &str, continuation: &str) -> Result<String, String> {
+    let mut cues = parse_srt(srt)?;
+    let continuation = parse_srt(continuation)?;
+
+    let Some(last) = cues.last().cloned() else {
+        return Ok(serialize_srt(&continuation));
+    };
+    let last_key = repetition_key(&last);
+
+    for mut cue in continuation {
+        if cue.end_ms <= last.end_ms || repetition_key(&cue) == last_key {
+            continue;
+        }
+        cue.start_ms = cue.start_ms.max(last.end_ms);
+        cues.push(cue);
+    }
+
+    renumber(&mut cues);
+    check_monotonic(&cues)?;
+    Ok(serialize_srt(&cues))
+}
+
+/// Runs shorter than this are never collapsed, so that genuine
+/// two-in-a-row repeats ("はい" "はい") survive
+pub const MIN_REPEAT_RUN: usize = 3;
+
+/// Text used to compare cues for repetition: whitespace and trailing
+/// punctuation are ignored and ASCII letters are lowercased
+fn repetition_key(cue: &SubtitleCue) -> String {
+    let text: String = cue
+        .lines
+        .iter()
+        .flat_map(|line| line.chars())
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    text.trim_end_matches(['。', '、', '！', '？', '.', ',', '!', '?', '…'])
+        .to_ascii_lowercase()
+}
+
+/// Collapses runs of at least `min_run` consecutive cues with the same text
+/// (as the model produces when it loops on noisy audio) into a single cue
+/// spanning the whole run. `min_run` is raised to [`MIN_REPEAT_RUN`].
+///
+/// Returns the remaining cues, renumbered, and a warning per collapsed run.
+pub fn collapse_repeated_cues(cues: Vec<SubtitleCue>, min_run: usize) -> (Vec<SubtitleCue>, Vec<String>) {
+    let min_run = min_run.max(MIN_REPEAT_RUN);
+    let mut collapsed: Vec<SubtitleCue> = Vec::with_capacity(cues.len());
+    let mut warnings = Vec::new();
+
+    let mut i = 0;
+    while i < cues.len() {
+        let key = repetition_key(&cues[i]);
+        let run_len = cues[i..]
+            .iter()
+            .take_while(|cue| !key.is_empty() && repetition_key(cue) == key)
+            .count()
+            .max(1);
+
+        if run_len >= min_run {
+            let mut cue = cues[i].clone();
+            cue.end_ms = cues[i..i + run_len].iter().map(|cue| cue.end_ms).max().unwrap_or(cue.end_ms);
+            warnings.push(format!(
+                "Collapsed {} repeated subtitles ({} - {}): {}",
+                run_len,
+                format_timestamp(cue.start_ms),
+                format_timestamp(cue.end_ms),
+                cue.lines.join(" ")
+            ));
+            collapsed.push(cue);
+        } else {
+            collapsed.extend(cues[i..i + run_len].iter().cloned());
+        }
+        i += run_len;
+    }
+
+    renumber(&mut collapsed);
+    (collapsed, warnings)
+}
+
+/// Splits a timeline of `duration_ms` into consecutive `(start_ms, end_ms)`
+/// ranges of at most `chunk_ms`
+pub fn chunk_ranges(duration_ms: u64, chunk_ms: u64) -> Vec<(u64, u64)> {
+    let chunk_ms = chunk_ms.max(1);
+    (0..duration_ms.div_ceil(chunk_ms))
+        .map(|i| (i * chunk_ms, ((i + 1) * chunk_ms).min(duration_ms)))
+        .collect()
+}
+
+/// Joins SRT transcribed per chunk into one file. The cues of each chunk are
+/// relative to the chunk, so they are shifted by its start offset before
+/// being concatenated and renumbered.
+pub fn stitch_chunks(chunks: &[(u64, String)]) -> Result<String, String> {
+    let mut cues = Vec::new();
+    for (offset_ms, srt) in chunks {
+        let mut chunk_cues = parse_srt(srt)?;
+        shift_cues(&mut chunk_cues, *offset_ms as i64);
+        cues.extend(chunk_cues);
+    }
+    renumber(&mut cues);
+    Ok(serialize_srt(&cues))
+}
+
+/// Overlap allowed where one part of a multi-part recording meets the next
+pub const DEFAULT_SEAM_TOLERANCE_MS: u64 = 1000;
+
+/// Joins separately transcribed parts of a recording, given as
+/// `(srt, offset_ms)` pairs in order. Overlaps at the seams of up to
+/// `tolerance_ms` are resolved with [`fix_overlaps`]; a larger overlap means
+/// an offset is wrong and is reported as an error.
+pub fn merge_srt_parts(parts: &[(String, u64)], tolerance_ms: u64) -> Result<String, String> {
+    let mut cues: Vec<SubtitleCue> = Vec::new();
+    for (i, (srt, offset_ms)) in parts.iter().enumerate() {
+        let mut part = parse_srt(srt).map_err(|e| format!("Part {}: {}", i + 1, e))?;
+        shift_cues(&mut part, *offset_ms as i64);
+
+        let previous_end = cues.iter().map(|cue| cue.end_ms).max();
+        let part_start = part.iter().map(|cue| cue.start_ms).min();
+        if let (Some(previous_end), Some(part_start)) = (previous_end, part_start) {
+            if previous_end > part_start + tolerance_ms {
+                return Err(format!(
+                    "Part {} starts at {}, {} ms before part {} ends at {}. Check the offset of part {}.",
+                    i + 1,
+                    format_timestamp(part_start),
+                    previous_end - part_start,
+                    i,
+                    format_timestamp(previous_end),
+                    i + 1
+                ));
+            }
+        }
+        cues.extend(part);
+    }
+
+    fix_overlaps(&mut cues, 0);
+    renumber(&mut cues);
+    Ok(serialize_srt(&cues))
+}
+
+/// Cuts subtitles at `split_at_ms` into two renumbered halves, the second
+/// re-based to start at zero. A cue spanning the split point is shortened to
+/// end there in the first half and starts at zero in the second.
+pub fn split_srt_at(srt: &str, split_at_ms: u64) -> Result<(String, String), String> {
+    let cues = parse_srt(srt)?;
+    let mut first = Vec::new();
+    let mut second = Vec::new();
+
+    for cue in cues {
+        if cue.start_ms < split_at_ms {
+            first.push(SubtitleCue {
+                end_ms: cue.end_ms.min(split_at_ms),
+                ..cue.clone()
+            });
+        }
+        if cue.end_ms > split_at_ms {
+            second.push(SubtitleCue {
+                start_ms: cue.start_ms.max(split_at_ms) - split_at_ms,
+                end_ms: cue.end_ms - split_at_ms,
+                ..cue
+            });
+        }
+    }
+
+    renumber(&mut first);
+    renumber(&mut second);
+    Ok((serialize_srt(&first), serialize_srt(&second)))
+}
+
+/// Adds `offset_ms` to every timestamp, clamping results below zero to zero
+pub fn shift_cues(cues: &mut [SubtitleCue], offset_ms: i64) {
+    let shift = |ms: u64| (ms as i64).saturating_add(offset_ms).max(0) as u64;
+    for cue in cues.iter_mut() {
+        cue.start_ms = shift(cue.start_ms);
+        cue.end_ms = shift(cue.end_ms);
+    }
+}
+
+/// Shifts every cue in `srt` by `offset_ms` (negative values move cues earlier)
+pub fn shift_srt(srt: &str, offset_ms: i64) -> Result<String, String> {
+    let mut cues = parse_srt(srt)?;
+    shift_cues(&mut cues, offset_ms);
+    Ok(serialize_srt(&cues))
+}
+
+/// A pause longer than this between two words starts a new cue in
+/// [`word_timings_to_srt`]
+pub const WORD_CUE_MAX_GAP_MS: u64 = 1000;
+
+/// One spoken word with its timing in milliseconds from the start of the audio
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WordTiming {
+    pub word: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+}
+
+impl WordTiming {
+    /// Response schema for a JSON array of words
+    pub fn list_schema() -> serde_json::Value {
+        serde_json::json!({
+            "type": "ARRAY",
+            "items": {
+                "type": "OBJECT",
+                "properties": {
+                    "word": { "type": "STRING" },
+                    "start_ms": { "type": "INTEGER" },
+                    "end_ms": { "type": "INTEGER" }
+                },
+                "required": ["word", "start_ms", "end_ms"],
+                "propertyOrdering": ["word", "start_ms", "end_ms"]
+            }
+        })
+    }
+}
+
+/// Checks that every word ends after it starts and that words are in order
+/// without overlapping; the error names the first offending word
+pub fn validate_word_timings(words: &[WordTiming]) -> Result<(), String> {
+    let mut previous_end = 0;
+    for (i, word) in words.iter().enumerate() {
+        if word.end_ms < word.start_ms {
+            return Err(format!(
+                "Word {} ({:?}) ends at {} before it starts at {}",
+                i + 1,
+                word.word,
+                format_timestamp(word.end_ms),
+                format_timestamp(word.start_ms)
+            ));
+        }
+        if word.start_ms < previous_end {
+            return Err(format!(
+                "Word {} ({:?}) starts at {}, before the previous word ends at {}",
+                i + 1,
+                word.word,
+                format_timestamp(word.start_ms),
+                format_timestamp(previous_end)
+            ));
+        }
+        previous_end = word.end_ms;
+    }
+    Ok(())
+}
+
+/// Groups words into cues of at most `max_chars` characters (a single longer
+/// word gets a cue of its own), starting a new cue after pauses longer than
+/// [`WORD_CUE_MAX_GAP_MS`]. Words are joined as in [`join_text`].
+pub fn word_timings_to_srt(words: &[WordTiming], max_chars: u32) -> Result<String, String> {
+    if max_chars == 0 {
+        return Err("max_chars must be greater than 0".to_string());
+    }
+    validate_word_timings(words)?;
+
+    let mut cues: Vec<SubtitleCue> = Vec::new();
+    let mut current: Option<SubtitleCue> = None;
+    for word in words.iter().filter(|word| !word.word.trim().is_empty()) {
+        let text = word.word.trim();
+        if let Some(cue) = current.as_mut() {
+            let joined = join_text(&cue.lines[0], text);
+            if joined.chars().count() <= max_chars as usize && word.start_ms - cue.end_ms <= WORD_CUE_MAX_GAP_MS {
+                cue.lines[0] = joined;
+                cue.end_ms = word.end_ms;
+                continue;
+            }
+            cues.extend(current.take());
+        }
+        current = Some(SubtitleCue {
+            index: 0,
+            start_ms: word.start_ms,
+            end_ms: word.end_ms,
+            lines: vec![text.to_string()],
+        });
+    }
+    cues.extend(current);
+
+    renumber(&mut cues);
+    Ok(serialize_srt(&cues))
 }
 
 #[cfg(test)]
@@ -35,21 +1425,21 @@ mod tests {
     #[test]
     fn test_extract_srt_with_code_block() {
         let input = "```srt\n1\n00:00:00,000 --> 00:00:05,000\nHello world\n```";
-        let expected = "1\n00:00:00,000 --> 00:00:05,000\nHello world";
+        let expected = "1\n00:00:00,000 --> 00:00:05,000\nHello world\n\n";
         assert_eq!(extract_srt_content(input), expected);
     }
 
     #[test]
     fn test_extract_generic_code_block() {
         let input = "```\n1\n00:00:00,000 --> 00:00:05,000\nHello world\n```";
-        let expected = "1\n00:00:00,000 --> 00:00:05,000\nHello world";
+        let expected = "1\n00:00:00,000 --> 00:00:05,000\nHello world\n\n";
         assert_eq!(extract_srt_content(input), expected);
     }
 
     #[test]
     fn test_no_code_block() {
         let input = "1\n00:00:00,000 --> 00:00:05,000\nHello world";
-        assert_eq!(extract_srt_content(input), input);
+        assert_eq!(extract_srt_content(input), format!("{}\n\n", input));
     }
 
     #[test]
@@ -61,37 +1451,111 @@ mod tests {
     #[test]
     fn test_multiple_code_blocks() {
         let input = "Some text\n```srt\n1\n00:00:00,000 --> 00:00:05,000\nFirst subtitle\n```\nMore text\n```\n2\n00:00:05,000 --> 00:00:10,000\nSecond subtitle\n```";
-        let expected = "1\n00:00:00,000 --> 00:00:05,000\nFirst subtitle";
+        let expected = "1\n00:00:00,000 --> 00:00:05,000\nFirst subtitle\n\n";
         assert_eq!(extract_srt_content(input), expected);
     }
 
     #[test]
     fn test_code_block_with_extra_whitespace() {
         let input = "```srt\n\n\n1\n00:00:00,000 --> 00:00:05,000\nHello world\n\n```";
-        let expected = "1\n00:00:00,000 --> 00:00:05,000\nHello world";
+        let expected = "1\n00:00:00,000 --> 00:00:05,000\nHello world\n\n";
         assert_eq!(extract_srt_content(input), expected);
     }
 
     #[test]
     fn test_incomplete_code_block() {
         let input = "```srt\n1\n00:00:00,000 --> 00:00:05,000\nHello world";
-        assert_eq!(extract_srt_content(input), input);
+        assert_eq!(extract_srt_content(input), format!("{}\n\n", input));
     }
 
     #[test]
     fn test_nested_backticks_in_content() {
         let input = "```srt\n1\n00:00:00,000 --> 00:00:05,000\nHe said `hello` to me\n```";
-        let expected = "1\n00:00:00,000 --> 00:00:05,000\nHe said `hello` to me";
+        let expected = "1\n00:00:00,000 --> 00:00:05,000\nHe said `hello` to me\n\n";
         assert_eq!(extract_srt_content(input), expected);
     }
 
     #[test]
     fn test_japanese_content() {
         let input = "```srt\n1\n00:00:00,000 --> 00:00:05,000\nこんにちは世界\n```";
-        let expected = "1\n00:00:00,000 --> 00:00:05,000\nこんにちは世界";
+        let expected = "1\n00:00:00,000 --> 00:00:05,000\nこんにちは世界\n\n";
+        assert_eq!(extract_srt_content(input), expected);
+    }
+
+    #[test]
+    fn test_extract_subrip_and_text_fences() {
+        let expected = "1\n00:00:00,000 --> 00:00:05,000\nHello world\n\n";
+        let subrip = "```subrip\n1\n00:00:00,000 --> 00:00:05,000\nHello world\n```";
+        assert_eq!(extract_srt_content(subrip), expected);
+        let text = "```text\n1\n00:00:00,000 --> 00:00:05,000\nHello world\n```";
+        assert_eq!(extract_srt_content(text), expected);
+    }
+
+    #[test]
+    fn test_extract_uppercase_fence() {
+        let input = "```SRT\n1\n00:00:00,000 --> 00:00:05,000\nHello world\n```";
+        assert_eq!(extract_srt_content(input), "1\n00:00:00,000 --> 00:00:05,000\nHello world\n\n");
+    }
+
+    #[test]
+    fn test_extract_fence_with_trailing_text() {
+        let expected = "1\n00:00:00,000 --> 00:00:05,000\nHello world\n\n";
+        let spaces = "```srt   \n1\n00:00:00,000 --> 00:00:05,000\nHello world\n```";
+        assert_eq!(extract_srt_content(spaces), expected);
+        let label = "```srt (Japanese)\n1\n00:00:00,000 --> 00:00:05,000\nHello world\n```";
+        assert_eq!(extract_srt_content(label), expected);
+    }
+
+    #[test]
+    fn test_extract_prefers_srt_block() {
+        let input = "```text\nNotes\n```\n```srt\n1\n00:00:00,000 --> 00:00:05,000\nHello world\n```";
+        assert_eq!(extract_srt_content(input), "1\n00:00:00,000 --> 00:00:05,000\nHello world\n\n");
+    }
+
+    #[test]
+    fn test_extract_without_fences_drops_preamble() {
+        let input = "以下がSRTです:\n\n1\n00:00:00,000 --> 00:00:05,000\nこんにちは\n\n2\n00:00:05,000 --> 00:00:07,000\n世界\n";
+        let expected = "1\n00:00:00,000 --> 00:00:05,000\nこんにちは\n\n2\n00:00:05,000 --> 00:00:07,000\n世界\n\n";
+        assert_eq!(extract_srt_content(input), expected);
+    }
+
+    #[test]
+    fn test_extract_without_fences_drops_trailer() {
+        let input = "1\n00:00:00,000 --> 00:00:05,000\nこんにちは\n\n2\n00:00:05,000 --> 00:00:07,000\n世界\n二行目\n\n以上です。";
+        let expected = "1\n00:00:00,000 --> 00:00:05,000\nこんにちは\n\n2\n00:00:05,000 --> 00:00:07,000\n世界\n二行目\n\n";
         assert_eq!(extract_srt_content(input), expected);
     }
 
+    #[test]
+    fn test_extract_without_fences_drops_preamble_and_trailer() {
+        let input = "以下がSRTです:\r\n1\r\n00:00:00,000 --> 00:00:05,000\r\nこんにちは\r\n\r\n以上です。\r\n";
+        assert_eq!(extract_srt_content(input), "1\n00:00:00,000 --> 00:00:05,000\nこんにちは\n\n");
+        assert_eq!(extract_srt_content("以上です。"), "以上です。");
+    }
+
+    #[test]
+    fn test_extract_all_joins_blocks() {
+        let input = "```srt\n1\n00:00:00,000 --> 00:00:05,000\nFirst\n```\n続きです…\n```srt\n1\n00:00:05,000 --> 00:00:10,000\nSecond\n```";
+        assert_eq!(
+            extract_all_srt_content(input).unwrap(),
+            "1\n00:00:00,000 --> 00:00:05,000\nFirst\n\n2\n00:00:05,000 --> 00:00:10,000\nSecond\n"
+        );
+        assert_eq!(extract_srt_content(input), "1\n00:00:00,000 --> 00:00:05,000\nFirst\n\n");
+    }
+
+    #[test]
+    fn test_extract_all_rejects_backwards_join() {
+        let input = "```srt\n1\n00:00:05,000 --> 00:00:10,000\nFirst\n```\n```srt\n1\n00:00:00,000 --> 00:00:05,000\nSecond\n```";
+        let error = extract_all_srt_content(input).unwrap_err();
+        assert!(error.contains("00:00:00,000 after 00:00:05,000"));
+    }
+
+    #[test]
+    fn test_extract_all_without_blocks_falls_back() {
+        let input = "以下です\n1\n00:00:00,000 --> 00:00:05,000\nHello";
+        assert_eq!(extract_all_srt_content(input).unwrap(), "1\n00:00:00,000 --> 00:00:05,000\nHello");
+    }
+
     #[test]
     fn test_real_world_example() {
         let input = r#"```srt
@@ -103,7 +1567,534 @@ Welcome to our presentation
 00:00:02,500 --> 00:00:05,000
 Today we'll discuss AI technology
 ```"#;
-        let expected = "1\n00:00:00,000 --> 00:00:02,500\nWelcome to our presentation\n\n2\n00:00:02,500 --> 00:00:05,000\nToday we'll discuss AI technology";
+        let expected = "1\n00:00:00,000 --> 00:00:02,500\nWelcome to our presentation\n\n2\n00:00:02,500 --> 00:00:05,000\nToday we'll discuss AI technology\n\n";
         assert_eq!(extract_srt_content(input), expected);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_srt_basic() {
+        let input = "1\n00:00:00,000 --> 00:00:02,500\nHello\nworld\n\n2\n00:00:02,500 --> 00:01:05,000\nこんにちは\n";
+        let cues = parse_srt(input).unwrap();
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[0].lines, vec!["Hello", "world"]);
+        assert_eq!(cues[1].start_ms, 2500);
+        assert_eq!(cues[1].end_ms, 65000);
+        assert_eq!(cues[1].lines, vec!["こんにちは"]);
+    }
+
+    #[test]
+    fn test_parse_srt_crlf_and_missing_blank_line() {
+        let input = "1\r\n00:00:00,000 --> 00:00:01,000\r\nFirst\r\n2\r\n00:00:01,000 --> 00:00:02,000\r\nSecond";
+        let cues = parse_srt(input).unwrap();
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[0].lines, vec!["First"]);
+        assert_eq!(cues[1].index, 2);
+    }
+
+    #[test]
+    fn test_parse_srt_invalid_timestamp() {
+        let input = "1\n00:00:00 --> 00:00:01,000\nHello";
+        assert_eq!(
+            parse_srt(input),
+            Err(SrtParseError::InvalidTimestamp {
+                line: 2,
+                content: "00:00:00 --> 00:00:01,000".to_string()
+            })
+        );
+        assert_eq!(parse_srt("1\n"), Err(SrtParseError::MissingTimestamp { index: 1 }));
+    }
+
+    #[test]
+    fn test_round_trip_serialize_then_parse() {
+        let cues = vec![
+            SubtitleCue {
+                index: 1,
+                start_ms: 5_520,
+                end_ms: 8_910,
+                lines: vec!["これは1番目の字幕の".to_string(), "テキストです。".to_string()],
+            },
+            SubtitleCue {
+                index: 2,
+                start_ms: 9_150,
+                end_ms: 3_611_300,
+                lines: vec!["アオイ: そして、これが2番目の字幕です。".to_string()],
+            },
+        ];
+        assert_eq!(parse_srt(&serialize_srt(&cues)).unwrap(), cues);
+    }
+
+    #[test]
+    fn test_round_trip_parse_then_serialize() {
+        let input = "1\n00:00:05,520 --> 00:00:08,910\nこれは1番目の字幕の\nテキストです。\n\n2\n00:00:09,150 --> 00:00:11,300\nそして、これが2番目の字幕です。\n";
+        assert_eq!(serialize_srt(&parse_srt(input).unwrap()), input);
+    }
+
+    #[test]
+    fn test_normalize_srt() {
+        let input = "3\r\n00:00:00,000 --> 00:00:01,000\r\nHello\r\n\r\n\r\n7\r\n00:00:01.000 --> 00:00:02,000\r\nWorld";
+        let expected = "1\n00:00:00,000 --> 00:00:01,000\nHello\n\n2\n00:00:01,000 --> 00:00:02,000\nWorld\n";
+        assert_eq!(normalize_srt(input), expected);
+
+        let plain = "話者1: こんにちは\n話者2: どうも";
+        assert_eq!(normalize_srt(plain), plain);
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("01:02:03,456"), Some(3_723_456));
+        assert_eq!(parse_timestamp("00:00:01.500"), Some(1_500));
+        assert_eq!(parse_timestamp("00:61:00,000"), None);
+    }
+
+    #[test]
+    fn test_split_speaker() {
+        assert_eq!(split_speaker("アオイ: こんにちは"), Some(("アオイ", "こんにちは")));
+        assert_eq!(split_speaker("話者1：はい"), Some(("話者1", "はい")));
+        assert_eq!(split_speaker("会議は10:30からです"), None);
+        assert_eq!(split_speaker("つまり、結論: 良い"), None);
+        assert_eq!(split_speaker("話者1:はい"), Some(("話者1", "はい")));
+        assert_eq!(split_speaker("詳細は https://example.com へ"), None);
+    }
+
+    const SPEAKER_LABEL_SRT: &str = "1\n00:00:00,000 --> 00:00:01,000\n話者1：こんにちは\n\n2\n00:00:01,000 --> 00:00:02,000\n話者2: 会議は10:30からです\n\n3\n00:00:02,000 --> 00:00:03,000\n結論として、比率は1:2です\n";
+
+    #[test]
+    fn test_remove_speaker_labels() {
+        assert_eq!(
+            remove_speaker_labels(SPEAKER_LABEL_SRT).unwrap(),
+            "1\n00:00:00,000 --> 00:00:01,000\nこんにちは\n\n2\n00:00:01,000 --> 00:00:02,000\n会議は10:30からです\n\n3\n00:00:02,000 --> 00:00:03,000\n結論として、比率は1:2です\n"
+        );
+    }
+
+    #[test]
+    fn test_remap_speaker_labels() {
+        let mapping = HashMap::from([("話者1".to_string(), "田中".to_string()), ("話者3".to_string(), "佐藤".to_string())]);
+        assert_eq!(
+            remap_speaker_labels(SPEAKER_LABEL_SRT, &mapping).unwrap(),
+            "1\n00:00:00,000 --> 00:00:01,000\n田中：こんにちは\n\n2\n00:00:01,000 --> 00:00:02,000\n話者2: 会議は10:30からです\n\n3\n00:00:02,000 --> 00:00:03,000\n結論として、比率は1:2です\n"
+        );
+    }
+
+    const JAPANESE_FILLERS: [&str; 14] = [
+        "えーっと", "えっと", "ええと", "えーと", "えー", "あのー", "あのぉ", "そのー", "なんか", "まあ", "まぁ", "うーん", "んー", "まー",
+    ];
+
+    #[test]
+    fn test_remove_filler_words() {
+        let input = "1\n00:00:00,000 --> 00:00:01,000\nえーっと、今日はなんかいい天気\n\n2\n00:00:01,000 --> 00:00:02,000\n田中: あの、えー、まあ。\n\n3\n00:00:02,000 --> 00:00:03,000\n話者2: 結論は、えーと、明日です\n\n4\n00:00:03,000 --> 00:00:04,000\nUm, so we start now\n";
+        let fillers = JAPANESE_FILLERS.iter().copied().chain(["あの", "um"]).collect::<Vec<_>>();
+        assert_eq!(
+            remove_filler_words(input, &fillers).unwrap(),
+            "1\n00:00:00,000 --> 00:00:01,000\n今日はなんかいい天気\n\n2\n00:00:02,000 --> 00:00:03,000\n話者2: 結論は、明日です\n\n3\n00:00:03,000 --> 00:00:04,000\nso we start now\n"
+        );
+        assert_eq!(strip_fillers("それで なんか 次に", &JAPANESE_FILLERS), "それで 次に");
+        assert_eq!(strip_fillers("それで、なんか。", &JAPANESE_FILLERS), "それで。");
+    }
+
+    fn timed_cue(index: u32, start_ms: u64, end_ms: u64) -> SubtitleCue {
+        SubtitleCue {
+            index,
+            start_ms,
+            end_ms,
+            lines: vec![format!("cue {}", index)],
+        }
+    }
+
+    #[test]
+    fn test_fix_overlaps_clamps_previous_end() {
+        let mut cues = vec![timed_cue(1, 0, 3000), timed_cue(2, 2500, 4000), timed_cue(3, 4000, 5000)];
+        assert_eq!(fix_overlaps(&mut cues, 0), 1);
+        assert_eq!((cues[0].end_ms, cues[1].start_ms), (2500, 2500));
+
+        let mut cues = vec![timed_cue(1, 0, 3000), timed_cue(2, 2500, 4000)];
+        assert_eq!(fix_overlaps(&mut cues, 100), 1);
+        assert_eq!(cues[0].end_ms, 2400);
+        assert!(check_monotonic(&cues).is_ok());
+    }
+
+    #[test]
+    fn test_enforce_durations() {
+        let mut cues = vec![timed_cue(1, 0, 200), timed_cue(2, 500, 700), timed_cue(3, 2000, 12000), timed_cue(4, 13000, 13100)];
+        assert_eq!(enforce_durations(&mut cues, 1000, 7000, 100), 4);
+        let timings: Vec<(u64, u64)> = cues.iter().map(|cue| (cue.start_ms, cue.end_ms)).collect();
+        // Cue 1 stops short of cue 2, cue 2 gets its full second, cue 3 is capped
+        assert_eq!(timings, vec![(0, 400), (500, 1500), (2000, 9000), (13000, 14000)]);
+
+        // Touching cues leave no room to extend into
+        let mut cues = vec![timed_cue(1, 0, 200), timed_cue(2, 200, 1500)];
+        assert_eq!(enforce_durations(&mut cues, 1000, 7000, 0), 0);
+        assert_eq!(cues[0].end_ms, 200);
+
+        // Out-of-order cues are sorted and numbered again
+        let mut cues = vec![timed_cue(1, 3000, 4500), timed_cue(2, 0, 1500), timed_cue(3, 1500, 3000)];
+        assert_eq!(enforce_durations(&mut cues, 1000, 7000, 0), 0);
+        let order: Vec<(u32, u64)> = cues.iter().map(|cue| (cue.index, cue.start_ms)).collect();
+        assert_eq!(order, vec![(1, 0), (2, 1500), (3, 3000)]);
+    }
+
+    #[test]
+    fn test_fix_overlaps_contained_and_out_of_order() {
+        // Cue 2 lies entirely within cue 1: cue 1 is cut where cue 2 starts
+        let mut cues = vec![timed_cue(1, 1000, 9000), timed_cue(2, 3000, 5000)];
+        assert_eq!(fix_overlaps(&mut cues, 0), 1);
+        assert_eq!((cues[0].start_ms, cues[0].end_ms), (1000, 3000));
+        assert_eq!((cues[1].start_ms, cues[1].end_ms), (3000, 5000));
+
+        // Same start: the second cue is moved after the first, keeping its length
+        let mut cues = vec![timed_cue(1, 1000, 4000), timed_cue(2, 1000, 2000)];
+        assert_eq!(fix_overlaps(&mut cues, 50), 1);
+        assert_eq!((cues[1].start_ms, cues[1].end_ms), (4050, 5050));
+
+        // Out-of-order cues are sorted first
+        let mut cues = vec![timed_cue(1, 5000, 6000), timed_cue(2, 0, 1000)];
+        assert_eq!(fix_overlaps(&mut cues, 0), 0);
+        assert_eq!(cues[0].index, 2);
+    }
+
+    #[test]
+    fn test_merge_srt_parts() {
+        let part1 = "1\n00:00:00,000 --> 00:00:02,000\n前半\n\n2\n00:00:58,000 --> 00:01:00,500\n前半の最後\n".to_string();
+        let part2 = "1\n00:00:00,000 --> 00:00:02,000\n後半\n".to_string();
+        assert_eq!(
+            merge_srt_parts(&[(part1.clone(), 0), (part2.clone(), 60_000)], DEFAULT_SEAM_TOLERANCE_MS).unwrap(),
+            "1\n00:00:00,000 --> 00:00:02,000\n前半\n\n2\n00:00:58,000 --> 00:01:00,000\n前半の最後\n\n3\n00:01:00,000 --> 00:01:02,000\n後半\n"
+        );
+
+        let error = merge_srt_parts(&[(part1, 0), (part2, 30_000)], DEFAULT_SEAM_TOLERANCE_MS).unwrap_err();
+        assert!(error.starts_with("Part 2 starts at 00:00:30,000, 30500 ms before part 1 ends"), "{}", error);
+    }
+
+    const SPLIT_SRT: &str = "1\n00:29:55,000 --> 00:30:00,000\n前\n\n2\n00:30:00,000 --> 00:30:03,000\n後\n\n3\n00:30:04,000 --> 00:30:06,000\n最後\n";
+
+    #[test]
+    fn test_split_srt_at_cue_boundary() {
+        let (first, second) = split_srt_at(SPLIT_SRT, 1_800_000).unwrap();
+        assert_eq!(first, "1\n00:29:55,000 --> 00:30:00,000\n前\n");
+        assert_eq!(
+            second,
+            "1\n00:00:00,000 --> 00:00:03,000\n後\n\n2\n00:00:04,000 --> 00:00:06,000\n最後\n"
+        );
+    }
+
+    #[test]
+    fn test_split_srt_inside_cue() {
+        let (first, second) = split_srt_at(SPLIT_SRT, 1_801_000).unwrap();
+        assert_eq!(
+            first,
+            "1\n00:29:55,000 --> 00:30:00,000\n前\n\n2\n00:30:00,000 --> 00:30:01,000\n後\n"
+        );
+        assert_eq!(
+            second,
+            "1\n00:00:00,000 --> 00:00:02,000\n後\n\n2\n00:00:03,000 --> 00:00:05,000\n最後\n"
+        );
+    }
+
+    const LIMITS: DurationLimits = DurationLimits { min_ms: DEFAULT_MIN_CUE_MS, max_ms: DEFAULT_MAX_CUE_MS };
+
+    #[test]
+    fn test_enforce_duration_limits_extends_and_merges_short_cues() {
+        let cues = parse_srt("1\n00:00:00,000 --> 00:00:00,400\nはい\n\n2\n00:00:01,500 --> 00:00:01,800\nええ\n\n3\n00:00:01,800 --> 00:00:04,000\nそうですね\n").unwrap();
+        let (cues, adjustments) = enforce_duration_limits(cues, LIMITS);
+
+        assert_eq!(adjustments, DurationAdjustments { extended: 1, merged: 1, split: 0 });
+        assert_eq!(
+            serialize_srt(&cues),
+            "1\n00:00:00,000 --> 00:00:01,000\nはい\n\n2\n00:00:01,500 --> 00:00:04,000\nええそうですね\n"
+        );
+    }
+
+    #[test]
+    fn test_enforce_duration_limits_splits_long_cues() {
+        let cues = parse_srt("1\n00:00:00,000 --> 00:00:10,000\n話者1: 今日は晴れです。明日は雨です。\n").unwrap();
+        let (cues, adjustments) = enforce_duration_limits(cues, LIMITS);
+
+        assert_eq!(adjustments.split, 1);
+        assert_eq!(
+            serialize_srt(&cues),
+            "1\n00:00:00,000 --> 00:00:05,333\n話者1: 今日は晴れです。\n\n2\n00:00:05,333 --> 00:00:10,000\n明日は雨です。\n"
+        );
+    }
+
+    #[test]
+    fn test_lint_srt() {
+        let srt = "1\n00:00:00,000 --> 00:00:02,000\n話者1: こんにちは\n\n2\n00:00:02,000 --> 00:00:02,500\nはい\n\n3\n00:00:03,000 --> 00:00:04,000\n今日はとても良い天気ですね散歩に行きましょう\n";
+        let warnings = lint_srt(srt, 10).unwrap();
+        let summary: Vec<(u32, LintKind)> = warnings.iter().map(|w| (w.cue_index, w.kind)).collect();
+
+        assert_eq!(summary, vec![(2, LintKind::TooShort), (3, LintKind::LineTooLong), (3, LintKind::TooFast)]);
+        assert_eq!(warnings[0].chars_per_second, 4.0);
+        assert_eq!(warnings[2].chars_per_second, 22.0);
+        assert!(lint_srt(srt, 0).is_err());
+    }
+
+    #[test]
+    fn test_estimate_cue_quality() {
+        let srt = "1\n00:00:00,000 --> 00:00:02,000\n話者1: こんにちは\n\n2\n00:00:02,000 --> 00:00:02,500\nはい\n\n3\n00:00:03,000 --> 00:00:04,000\n今日はとても良い天気ですね散歩に行きましょう\n\n4\n00:00:04,000 --> 00:00:14,000\n[不明瞭]です\n";
+        let scores = estimate_cue_quality(&parse_srt(srt).unwrap());
+        let rounded: Vec<f32> = scores.iter().map(|score| (score * 100.0).round() / 100.0).collect();
+        assert_eq!(rounded, vec![1.0, 0.7, 0.5, 0.18]);
+    }
+
+    #[test]
+    fn test_strip_markup() {
+        assert_eq!(strip_markup("<b>重要</b>な<i>話</i>", &DEFAULT_ALLOWED_TAGS), "重要な<i>話</i>");
+        assert_eq!(strip_markup("<FONT color=\"red\">赤</font>", &DEFAULT_ALLOWED_TAGS), "赤");
+        assert_eq!(strip_markup("**太字**と*強調*と__下線__", &DEFAULT_ALLOWED_TAGS), "太字と強調と下線");
+        assert_eq!(strip_markup("a < b と <3 と 2 * 3 * 4", &DEFAULT_ALLOWED_TAGS), "a < b と <3 と 2 * 3 * 4");
+        assert_eq!(strip_markup("<b>太字</b>", &["b"]), "<b>太字</b>");
+    }
+
+    #[test]
+    fn test_sanitize_markup_keeps_arrows_in_dialogue() {
+        let srt = "1\n00:00:00,000 --> 00:00:01,000\n<b></b>\n\n2\n00:00:01,000 --> 00:00:02,000\n3\nA --> B は**矢印**\n";
+        assert_eq!(
+            sanitize_markup(srt, &DEFAULT_ALLOWED_TAGS).unwrap(),
+            "1\n00:00:01,000 --> 00:00:02,000\n3\nA --> B は矢印\n"
+        );
+    }
+
+    #[test]
+    fn test_serialize_srt() {
+        let cues = vec![SubtitleCue {
+            index: 1,
+            start_ms: 3_723_456,
+            end_ms: 3_725_000,
+            lines: vec!["Hello".to_string(), "world".to_string()],
+        }];
+        assert_eq!(serialize_srt(&cues), "1\n01:02:03,456 --> 01:02:05,000\nHello\nworld\n");
+    }
+
+    #[test]
+    fn test_merge_short_cues() {
+        let input = "1\n00:00:00,000 --> 00:00:01,000\nこんにちは\n\n2\n00:00:01,100 --> 00:00:02,000\n皆さん\n\n3\n00:00:05,000 --> 00:00:06,000\n遅れて\n";
+        let expected = "1\n00:00:00,000 --> 00:00:02,000\nこんにちは皆さん\n\n2\n00:00:05,000 --> 00:00:06,000\n遅れて\n";
+        assert_eq!(merge_short_cues(input, 20, 500).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_merge_short_cues_respects_char_limit() {
+        let input = "1\n00:00:00,000 --> 00:00:01,000\nHello there\n\n2\n00:00:01,000 --> 00:00:02,000\nmy friend\n";
+        let merged = merge_short_cues(input, 20, 500).unwrap();
+        assert_eq!(merged, "1\n00:00:00,000 --> 00:00:02,000\nHello there my friend\n");
+        assert_eq!(merge_short_cues(input, 10, 500).unwrap(), input);
+    }
+
+    #[test]
+    fn test_merge_short_cues_keeps_speaker_turns() {
+        let input = "1\n00:00:00,000 --> 00:00:01,000\nアオイ: はい\n\n2\n00:00:01,000 --> 00:00:02,000\nアオイ: そうです\n\n3\n00:00:02,000 --> 00:00:03,000\nユーザー: なるほど\n";
+        let expected = "1\n00:00:00,000 --> 00:00:02,000\nアオイ: はいそうです\n\n2\n00:00:02,000 --> 00:00:03,000\nユーザー: なるほど\n";
+        assert_eq!(merge_short_cues(input, 30, 500).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_split_long_cues_at_punctuation() {
+        let input = "1\n00:00:00,000 --> 00:00:09,000\n今日はいい天気ですね。散歩に行きましょう。\n";
+        let expected = "1\n00:00:00,000 --> 00:00:04,714\n今日はいい天気ですね。\n\n2\n00:00:04,714 --> 00:00:09,000\n散歩に行きましょう。\n";
+        assert_eq!(split_long_cues(input, 12).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_split_long_cues_at_spaces_with_speaker() {
+        let input = "1\n00:00:10,000 --> 00:00:14,000\nAoi: hello there my friend\n\n2\n00:00:14,000 --> 00:00:15,000\nshort\n";
+        let expected = "1\n00:00:10,000 --> 00:00:12,200\nAoi: hello there\n\n2\n00:00:12,200 --> 00:00:14,000\nmy friend\n\n3\n00:00:14,000 --> 00:00:15,000\nshort\n";
+        assert_eq!(split_long_cues(input, 12).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_split_long_cues_without_break_points() {
+        let input = "1\n00:00:00,000 --> 00:00:03,000\nあいうえおかきくけこさしすせそ\n";
+        let cues = parse_srt(&split_long_cues(input, 5).unwrap()).unwrap();
+        assert_eq!(cues.len(), 3);
+        assert_eq!(cues[1].lines, vec!["かきくけこ"]);
+        assert_eq!((cues[1].start_ms, cues[1].end_ms), (1000, 2000));
+    }
+
+    #[test]
+    fn test_merge_translation() {
+        let original = parse_srt("1\n00:00:01,000 --> 00:00:02,000\nこんにちは\n\n2\n00:00:03,000 --> 00:00:04,000\nさようなら\n").unwrap();
+        let translated = parse_srt("1\n00:00:01,100 --> 00:00:02,000\nHello\n\n2\n00:00:03,000 --> 00:00:04,000\nGoodbye\n").unwrap();
+        let merged = merge_translation(&original, &translated).unwrap();
+        assert_eq!(
+            serialize_srt(&merged),
+            "1\n00:00:01,000 --> 00:00:02,000\nこんにちは\nHello\n\n2\n00:00:03,000 --> 00:00:04,000\nさようなら\nGoodbye\n"
+        );
+
+        let error = merge_translation(&original, &translated[..1]).unwrap_err();
+        assert_eq!(error, "Translation returned 1 subtitles but the original has 2");
+    }
+
+    #[test]
+    fn test_collapse_repeated_cues() {
+        let mut input = String::from("1\n00:00:00,000 --> 00:00:01,000\n始めます\n");
+        for i in 0..5 {
+            let text = if i % 2 == 0 { "ありがとうございます。" } else { " ありがとうございます " };
+            input.push_str(&format!("\n{}\n00:00:0{},000 --> 00:00:0{},500\n{}\n", i + 2, i + 1, i + 1, text));
+        }
+        input.push_str("\n7\n00:00:07,000 --> 00:00:08,000\n終わり\n");
+
+        let (cues, warnings) = collapse_repeated_cues(parse_srt(&input).unwrap(), 3);
+        assert_eq!(
+            serialize_srt(&cues),
+            "1\n00:00:00,000 --> 00:00:01,000\n始めます\n\n2\n00:00:01,000 --> 00:00:05,500\nありがとうございます。\n\n3\n00:00:07,000 --> 00:00:08,000\n終わり\n"
+        );
+        assert_eq!(
+            warnings,
+            vec!["Collapsed 5 repeated subtitles (00:00:01,000 - 00:00:05,500): ありがとうございます。"]
+        );
+    }
+
+    #[test]
+    fn test_collapse_repeated_cues_keeps_pairs() {
+        let input = "1\n00:00:00,000 --> 00:00:01,000\nはい\n\n2\n00:00:01,000 --> 00:00:02,000\nはい\n\n3\n00:00:02,000 --> 00:00:03,000\nそうです\n";
+        // Thresholds below the minimum are raised so pairs are never collapsed
+        let (cues, warnings) = collapse_repeated_cues(parse_srt(input).unwrap(), 2);
+        assert_eq!(serialize_srt(&cues), input);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_close_truncated_fence() {
+        let truncated = "```srt\n1\n00:00:00,000 --> 00:00:05,000\nHello\n\n2\n00:00:0";
+        assert_eq!(
+            extract_srt_block(&close_truncated_fence(truncated)),
+            "1\n00:00:00,000 --> 00:00:05,000\nHello\n\n2\n00:00:0"
+        );
+        let complete = "```srt\n1\n00:00:00,000 --> 00:00:05,000\nHello\n```";
+        assert_eq!(close_truncated_fence(complete), complete);
+    }
+
+    #[test]
+    fn test_repair_srt_drops_dangling_partial_cue() {
+        let complete = "1\n00:00:00,000 --> 00:00:05,000\nHello\n\n2\n00:00:05,000 --> 00:00:07,000\nWorld\n\n";
+        for tail in ["3", "3\n00:00:0", "3\n00:00:07,000 --> 00:00"] {
+            let input = format!("1\r\n00:00:00,000 --> 00:00:05,000\r\nHello\r\n\r\n2\n00:00:05,000 --> 00:00:07,000\nWorld\n\n{}", tail);
+            assert_eq!(repair_srt(&input), complete, "tail {:?}", tail);
+        }
+        assert_eq!(repair_srt(complete.trim_end()), complete);
+        assert_eq!(repair_srt(complete), complete);
+
+        let truncated = "```srt\n1\n00:00:00,000 --> 00:00:05,000\nHello\n\n2\n00:00:0";
+        assert_eq!(extract_srt_content(&close_truncated_fence(truncated)), "1\n00:00:00,000 --> 00:00:05,000\nHello\n\n");
+    }
+
+    #[test]
+    fn test_repair_srt_keeps_plain_text() {
+        assert_eq!(repair_srt("こんにちは\r\n\r\n世界"), "こんにちは\n\n世界");
+        assert_eq!(repair_srt(""), "");
+    }
+
+    #[test]
+    fn test_drop_incomplete_final_cue() {
+        let complete = "1\n00:00:00,000 --> 00:00:05,000\nHello\n";
+        assert_eq!(drop_incomplete_final_cue(complete, false), (complete.to_string(), false));
+
+        for tail in ["2", "2\n00:00:0", "2\n00:00:05,000 --> 00:00:06,000"] {
+            let input = format!("{}\n{}", complete, tail);
+            assert_eq!(drop_incomplete_final_cue(&input, false), (complete.to_string(), true), "tail {:?}", tail);
+        }
+
+        let cut_mid_text = format!("{}\n2\n00:00:05,000 --> 00:00:06,000\nThis sen", complete);
+        assert_eq!(drop_incomplete_final_cue(&cut_mid_text, true), (complete.to_string(), true));
+    }
+
+    #[test]
+    fn test_stitch_continuation_dedupes_seam() {
+        let first = "1\n00:00:00,000 --> 00:00:05,000\nHello\n\n2\n00:00:05,000 --> 00:00:08,000\nWorld\n";
+        let continuation = "2\n00:00:05,000 --> 00:00:08,000\nWorld\n\n3\n00:00:07,900 --> 00:00:10,000\nAgain\n\n4\n00:00:10,000 --> 00:00:12,000\nDone\n";
+        assert_eq!(
+            stitch_continuation(first, continuation).unwrap(),
+            "1\n00:00:00,000 --> 00:00:05,000\nHello\n\n2\n00:00:05,000 --> 00:00:08,000\nWorld\n\n3\n00:00:08,000 --> 00:00:10,000\nAgain\n\n4\n00:00:10,000 --> 00:00:12,000\nDone\n"
+        );
+    }
+
+    #[test]
+    fn test_wrap_text_kinsoku() {
+        // A plain split would start the second line with 「。」
+        assert_eq!(wrap_text("今日はいい天気です。明日も晴れ", 9, 2), vec!["今日はいい天気で", "す。明日も晴れ"]);
+        // Opening brackets are moved to the next line
+        assert_eq!(wrap_text("彼は言った「行こう」と", 6, 2), vec!["彼は言った", "「行こう」と"]);
+        // Text beyond the last line is kept
+        assert_eq!(wrap_text("あいうえおかきくけこさしす", 5, 2), vec!["あいうえお", "かきくけこさしす"]);
+    }
+
+    #[test]
+    fn test_wrap_text_breaks_at_spaces() {
+        assert_eq!(wrap_text("the quick brown fox jumps", 12, 2), vec!["the quick", "brown fox jumps"]);
+    }
+
+    #[test]
+    fn test_wrap_cue_lines_keeps_speaker_on_first_line() {
+        let mut cues = parse_srt("1\n00:00:00,000 --> 00:00:02,000\nアオイ：今日は\nいい天気ですね。\n").unwrap();
+        wrap_cue_lines(&mut cues, 6, 2);
+        assert_eq!(cues[0].lines, vec!["アオイ: 今日はいい天", "気ですね。"]);
+    }
+
+    #[test]
+    fn test_check_monotonic() {
+        let input = "1\n00:00:05,000 --> 00:00:06,000\nA\n\n2\n00:00:01,000 --> 00:00:02,000\nB\n";
+        assert!(check_monotonic(&parse_srt(input).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_chunk_ranges() {
+        assert_eq!(chunk_ranges(25_000, 10_000), vec![(0, 10_000), (10_000, 20_000), (20_000, 25_000)]);
+        assert_eq!(chunk_ranges(20_000, 10_000), vec![(0, 10_000), (10_000, 20_000)]);
+        assert!(chunk_ranges(0, 10_000).is_empty());
+    }
+
+    #[test]
+    fn test_stitch_chunks_offsets_and_renumbers() {
+        let chunks = vec![
+            (0, "1\n00:00:01,000 --> 00:00:02,000\n最初\n".to_string()),
+            (600_000, "1\n00:00:00,500 --> 00:00:03,000\n次\n\n2\n00:00:04,000 --> 00:00:05,000\n最後\n".to_string()),
+        ];
+        assert_eq!(
+            stitch_chunks(&chunks).unwrap(),
+            "1\n00:00:01,000 --> 00:00:02,000\n最初\n\n2\n00:10:00,500 --> 00:10:03,000\n次\n\n3\n00:10:04,000 --> 00:10:05,000\n最後\n"
+        );
+    }
+
+    #[test]
+    fn test_shift_srt_forward() {
+        let input = "1\n00:00:01,000 --> 00:00:02,500\nHello\n";
+        let expected = "1\n00:00:03,500 --> 00:00:05,000\nHello\n";
+        assert_eq!(shift_srt(input, 2500).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_shift_srt_clamps_below_zero() {
+        let input = "1\n00:00:00,500 --> 00:00:01,000\nFirst\n\n2\n00:00:01,500 --> 00:00:03,000\nSecond\n\n3\n00:00:05,000 --> 00:00:06,000\nThird\n";
+        let expected = "1\n00:00:00,000 --> 00:00:00,000\nFirst\n\n2\n00:00:00,000 --> 00:00:01,000\nSecond\n\n3\n00:00:03,000 --> 00:00:04,000\nThird\n";
+        assert_eq!(shift_srt(input, -2000).unwrap(), expected);
+    }
+
+    fn word(word: &str, start_ms: u64, end_ms: u64) -> WordTiming {
+        WordTiming { word: word.to_string(), start_ms, end_ms }
+    }
+
+    #[test]
+    fn test_word_timings_to_srt() {
+        let words = [
+            word("今日は", 0, 400),
+            word("いい", 400, 600),
+            word("天気", 600, 900),
+            word("ですね", 900, 1300),
+            word("Hi", 3000, 3400),
+            word("there", 3400, 3800),
+        ];
+        assert_eq!(
+            word_timings_to_srt(&words, 8).unwrap(),
+            "1\n00:00:00,000 --> 00:00:00,900\n今日はいい天気\n\n2\n00:00:00,900 --> 00:00:01,300\nですね\n\n3\n00:00:03,000 --> 00:00:03,800\nHi there\n"
+        );
+        assert!(word_timings_to_srt(&words, 0).is_err());
+    }
+
+    #[test]
+    fn test_validate_word_timings_rejects_disorder() {
+        assert!(validate_word_timings(&[word("a", 0, 100), word("b", 100, 200)]).is_ok());
+        let error = validate_word_timings(&[word("a", 0, 500), word("b", 300, 600)]).unwrap_err();
+        assert!(error.starts_with("Word 2 (\"b\") starts at"), "{}", error);
+        assert!(validate_word_timings(&[word("a", 500, 100)]).unwrap_err().contains("ends at"));
+        assert!(word_timings_to_srt(&[word("b", 1000, 1200), word("a", 0, 100)], 10).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+use serde::Serialize;
+
+use crate::srt_utils::{cue_body, cue_speaker, format_timestamp, parse_srt, UNCLEAR_MARKERS};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpeakerTime {
+    pub speaker: String,
+    pub cue_count: usize,
+    pub talk_time_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SrtStatistics {
+    pub cue_count: usize,
+    /// Time with at least one subtitle on screen; overlapping cues count once
+    pub on_screen_ms: u64,
+    pub audio_duration_ms: Option<u64>,
+    /// `on_screen_ms` as a percentage of the audio duration, when known
+    pub coverage_percent: Option<f64>,
+    pub average_cue_duration_ms: f64,
+    /// Characters per second of cue duration, without speaker labels and spaces
+    pub average_cps: f64,
+    pub character_count: usize,
+    /// In order of first appearance; empty when there are no speaker labels
+    pub speakers: Vec<SpeakerTime>,
+    pub unclear_count: usize,
+}
+
+/// Computes delivery statistics for SRT content
+pub fn srt_statistics(content: &str, duration_ms: Option<u64>) -> Result<SrtStatistics, String> {
+    let cues = parse_srt(content)?;
+
+    let mut intervals: Vec<(u64, u64)> = cues.iter().map(|cue| (cue.start_ms, cue.end_ms.max(cue.start_ms))).collect();
+    intervals.sort_unstable();
+    let mut on_screen_ms = 0;
+    let mut covered_until = 0;
+    for (start, end) in intervals {
+        let start = start.max(covered_until);
+        if end > start {
+            on_screen_ms += end - start;
+            covered_until = end;
+        }
+    }
+
+    let total_cue_ms: u64 = cues.iter().map(|cue| cue.end_ms.saturating_sub(cue.start_ms)).sum();
+    let character_count: usize = cues
+        .iter()
+        .map(|cue| cue_body(cue).chars().filter(|c| !c.is_whitespace()).count())
+        .sum();
+
+    let mut speakers: Vec<SpeakerTime> = Vec::new();
+    for cue in &cues {
+        let Some(speaker) = cue_speaker(cue) else {
+            continue;
+        };
+        let duration = cue.end_ms.saturating_sub(cue.start_ms);
+        match speakers.iter_mut().find(|entry| entry.speaker == speaker) {
+            Some(entry) => {
+                entry.cue_count += 1;
+                entry.talk_time_ms += duration;
+            }
+            None => speakers.push(SpeakerTime {
+                speaker: speaker.to_string(),
+                cue_count: 1,
+                talk_time_ms: duration,
+            }),
+        }
+    }
+
+    let unclear_count = cues
+        .iter()
+        .flat_map(|cue| cue.lines.iter())
+        .map(|line| UNCLEAR_MARKERS.iter().map(|marker| line.matches(marker).count()).sum::<usize>())
+        .sum();
+
+    Ok(SrtStatistics {
+        cue_count: cues.len(),
+        on_screen_ms,
+        audio_duration_ms: duration_ms,
+        coverage_percent: duration_ms
+            .filter(|duration| *duration > 0)
+            .map(|duration| on_screen_ms as f64 / duration as f64 * 100.0),
+        average_cue_duration_ms: if cues.is_empty() { 0.0 } else { total_cue_ms as f64 / cues.len() as f64 },
+        average_cps: if total_cue_ms == 0 { 0.0 } else { character_count as f64 / (total_cue_ms as f64 / 1000.0) },
+        character_count,
+        speakers,
+        unclear_count,
+    })
+}
+
+/// An unclear-speech marker found in a cue
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UnclearSegment {
+    pub index: u32,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub start: String,
+    pub end: String,
+    pub marker: String,
+    pub text: String,
+    /// Text of the neighbouring cues, empty at the start or end
+    pub previous_text: String,
+    pub next_text: String,
+}
+
+/// Lists every occurrence of an unclear-speech marker, sorted by time.
+/// `[不明瞭]` and `[inaudible]` are always searched; `extra_markers` adds
+/// markers used by custom prompts.
+pub fn find_unclear(content: &str, extra_markers: &[String]) -> Result<Vec<UnclearSegment>, String> {
+    let mut cues = parse_srt(content)?;
+    cues.sort_by_key(|cue| cue.start_ms);
+
+    let markers: Vec<&str> = UNCLEAR_MARKERS
+        .iter()
+        .copied()
+        .chain(extra_markers.iter().map(String::as_str))
+        .filter(|marker| !marker.is_empty())
+        .collect();
+    let cue_text = |i: Option<usize>| i.and_then(|i| cues.get(i)).map(|cue| cue.lines.join(" ")).unwrap_or_default();
+
+    let mut segments = Vec::new();
+    for (i, cue) in cues.iter().enumerate() {
+        let text = cue.lines.join(" ");
+        let mut found: Vec<(usize, &str)> = markers
+            .iter()
+            .flat_map(|marker| text.match_indices(marker))
+            .collect();
+        found.sort_unstable();
+        for (_, marker) in found {
+            segments.push(UnclearSegment {
+                index: cue.index,
+                start_ms: cue.start_ms,
+                end_ms: cue.end_ms,
+                start: format_timestamp(cue.start_ms),
+                end: format_timestamp(cue.end_ms),
+                marker: marker.to_string(),
+                text: text.clone(),
+                previous_text: cue_text(i.checked_sub(1)),
+                next_text: cue_text(Some(i + 1)),
+            });
+        }
+    }
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_srt_statistics() {
+        let srt = "1\n00:00:00,000 --> 00:00:02,000\nアオイ: こんにちは\n\n2\n00:00:01,000 --> 00:00:04,000\nユーザー: [不明瞭]です\n\n3\n00:00:06,000 --> 00:00:07,000\nアオイ: はい\n";
+        let stats = srt_statistics(srt, Some(10_000)).unwrap();
+
+        assert_eq!(stats.cue_count, 3);
+        assert_eq!(stats.on_screen_ms, 5_000);
+        assert_eq!(stats.coverage_percent, Some(50.0));
+        assert_eq!(stats.average_cue_duration_ms, 2_000.0);
+        // こんにちは + [不明瞭]です + はい over 6 seconds
+        assert_eq!(stats.character_count, 14);
+        assert!((stats.average_cps - 14.0 / 6.0).abs() < 1e-9);
+        assert_eq!(stats.unclear_count, 1);
+        assert_eq!(
+            stats.speakers,
+            vec![
+                SpeakerTime { speaker: "アオイ".to_string(), cue_count: 2, talk_time_ms: 3_000 },
+                SpeakerTime { speaker: "ユーザー".to_string(), cue_count: 1, talk_time_ms: 3_000 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_unclear_with_extra_markers() {
+        let srt = "2\n00:00:05,000 --> 00:00:06,000\n[聞き取れず]と[不明瞭]\n\n1\n00:00:01,000 --> 00:00:02,000\n最初は[不明瞭]\n\n3\n00:00:07,000 --> 00:00:08,000\n終わり\n";
+        let segments = find_unclear(srt, &["[聞き取れず]".to_string()]).unwrap();
+
+        let found: Vec<(u32, &str)> = segments.iter().map(|segment| (segment.index, segment.marker.as_str())).collect();
+        assert_eq!(found, vec![(1, "[不明瞭]"), (2, "[聞き取れず]"), (2, "[不明瞭]")]);
+        assert_eq!(segments[0].start, "00:00:01,000");
+        assert_eq!(segments[0].previous_text, "");
+        assert_eq!(segments[0].next_text, "[聞き取れず]と[不明瞭]");
+        assert_eq!(segments[1].next_text, "終わり");
+    }
+
+    #[test]
+    fn test_srt_statistics_without_duration_or_cues() {
+        let stats = srt_statistics("", None).unwrap();
+        assert_eq!(stats.cue_count, 0);
+        assert_eq!(stats.coverage_percent, None);
+        assert_eq!(stats.average_cps, 0.0);
+    }
+}
@@ -0,0 +1,88 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use tokio_util::sync::CancellationToken;
+
+use crate::error::AppError;
+
+/// Allows one transcription at a time and lets it be cancelled; kept in
+/// Tauri managed state
+#[derive(Default)]
+pub struct TranscriptionLock {
+    running: AtomicBool,
+    /// Token of the running transcription, replaced when it finishes so a
+    /// cancellation never carries over to the next one
+    cancel: Mutex<CancellationToken>,
+}
+
+/// Releases the [`TranscriptionLock`] when dropped, including on early returns
+pub struct TranscriptionGuard<'a> {
+    lock: &'a TranscriptionLock,
+}
+
+impl TranscriptionLock {
+    /// Fails with [`AppError::Busy`] instead of waiting when a transcription is running
+    pub fn try_acquire(&self) -> Result<TranscriptionGuard<'_>, AppError> {
+        self.running
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .map(|_| TranscriptionGuard { lock: self })
+            .map_err(|_| AppError::Busy)
+    }
+
+    fn token(&self) -> MutexGuard<'_, CancellationToken> {
+        self.cancel.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Cancels the running transcription; returns whether one was running
+    pub fn cancel(&self) -> bool {
+        let token = self.token();
+        if self.running.load(Ordering::Acquire) {
+            token.cancel();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl TranscriptionGuard<'_> {
+    /// Fires when `cancel_transcription` is called for this transcription
+    pub fn cancellation(&self) -> CancellationToken {
+        self.lock.token().clone()
+    }
+}
+
+impl Drop for TranscriptionGuard<'_> {
+    fn drop(&mut self) {
+        *self.lock.token() = CancellationToken::new();
+        self.lock.running.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_acquire_is_busy_until_released() {
+        let lock = TranscriptionLock::default();
+        let guard = lock.try_acquire().unwrap();
+        assert_eq!(lock.try_acquire().err(), Some(AppError::Busy));
+        drop(guard);
+        assert!(lock.try_acquire().is_ok());
+    }
+
+    #[test]
+    fn test_cancel_only_affects_the_running_transcription() {
+        let lock = TranscriptionLock::default();
+        assert!(!lock.cancel());
+
+        let guard = lock.try_acquire().unwrap();
+        let token = guard.cancellation();
+        assert!(lock.cancel());
+        assert!(token.is_cancelled());
+        drop(guard);
+
+        let next = lock.try_acquire().unwrap();
+        assert!(!next.cancellation().is_cancelled());
+    }
+}
@@ -0,0 +1,193 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tokio::io::AsyncBufReadExt;
+use tracing::warn;
+
+/// Cached files must stay valid at least this long to be reused, so they do
+/// not expire while the transcription request is running
+const MIN_REMAINING_VALIDITY_MINUTES: i64 = 10;
+
+/// Bytes read from disk at a time while hashing a file
+const HASH_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// A file previously uploaded to the Gemini Files API
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedUpload {
+    pub name: String,
+    pub uri: String,
+    pub mime_type: String,
+    /// RFC 3339 timestamp reported by the Files API
+    pub expiration_time: String,
+    /// Files belong to the project of the uploading key, see [`key_fingerprint`]
+    pub key_fingerprint: String,
+}
+
+impl CachedUpload {
+    fn is_valid_at(&self, now: DateTime<Utc>) -> bool {
+        DateTime::parse_from_rfc3339(&self.expiration_time)
+            .map(|expiration| expiration > now + Duration::minutes(MIN_REMAINING_VALIDITY_MINUTES))
+            .unwrap_or(false)
+    }
+}
+
+/// Hex-encoded SHA-256 of the given data
+pub fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// SHA-256 of the file at `path`, read in pieces so the file is never held
+/// in memory as a whole
+pub async fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut reader = tokio::io::BufReader::with_capacity(HASH_BUFFER_SIZE, tokio::fs::File::open(path).await?);
+    let mut hasher = Sha256::new();
+    loop {
+        let buf = reader.fill_buf().await?;
+        if buf.is_empty() {
+            break;
+        }
+        hasher.update(buf);
+        let len = buf.len();
+        reader.consume(len);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Short, non-reversible identifier for an API key
+pub fn key_fingerprint(api_key: &str) -> String {
+    sha256_hex(api_key.as_bytes())[..16].to_string()
+}
+
+/// Maps file content hashes to Gemini file URIs, persisted as JSON in the
+/// app data directory and kept in Tauri managed state
+pub struct UploadCache {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, CachedUpload>>,
+}
+
+impl UploadCache {
+    /// Loads the cache; a missing or unreadable file starts an empty cache
+    pub fn load(path: PathBuf) -> Self {
+        let entries = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, HashMap<String, CachedUpload>> {
+        self.entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn save(&self, entries: &HashMap<String, CachedUpload>) {
+        let result = serde_json::to_string_pretty(entries)
+            .map_err(|e| e.to_string())
+            .and_then(|json| std::fs::write(&self.path, json).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            warn!("Failed to save upload cache: {}", e);
+        }
+    }
+
+    /// Returns a still-valid upload of the same content, MIME type and key.
+    /// Expired entries are dropped along the way.
+    pub fn get(&self, sha256: &str, mime_type: &str, key_fingerprint: &str) -> Option<CachedUpload> {
+        let mut entries = self.entries();
+        let now = Utc::now();
+        let before = entries.len();
+        entries.retain(|_, entry| entry.is_valid_at(now));
+        if entries.len() != before {
+            self.save(&entries);
+        }
+
+        entries
+            .get(sha256)
+            .filter(|entry| entry.mime_type == mime_type && entry.key_fingerprint == key_fingerprint)
+            .cloned()
+    }
+
+    pub fn insert(&self, sha256: String, upload: CachedUpload) {
+        let mut entries = self.entries();
+        entries.insert(sha256, upload);
+        self.save(&entries);
+    }
+
+    pub fn remove(&self, sha256: &str) {
+        let mut entries = self.entries();
+        if entries.remove(sha256).is_some() {
+            self.save(&entries);
+        }
+    }
+
+    /// Drops the entries pointing at the remote file `name`, e.g. after it was
+    /// deleted
+    pub fn remove_file(&self, name: &str) {
+        let mut entries = self.entries();
+        let before = entries.len();
+        entries.retain(|_, entry| entry.name != name);
+        if entries.len() != before {
+            self.save(&entries);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upload(expiration_time: String) -> CachedUpload {
+        CachedUpload {
+            name: "files/abc".to_string(),
+            uri: "https://generativelanguage.googleapis.com/v1beta/files/abc".to_string(),
+            mime_type: "audio/mp3".to_string(),
+            expiration_time,
+            key_fingerprint: key_fingerprint("key-a"),
+        }
+    }
+
+    #[test]
+    fn test_cache_round_trip_and_expiry() {
+        let path = std::env::temp_dir().join(format!("upload_cache_{}.json", uuid::Uuid::new_v4()));
+        let cache = UploadCache::load(path.clone());
+        let valid = upload((Utc::now() + Duration::hours(47)).to_rfc3339());
+        cache.insert("valid".to_string(), valid.clone());
+        cache.insert("expired".to_string(), upload((Utc::now() + Duration::minutes(1)).to_rfc3339()));
+
+        let reloaded = UploadCache::load(path.clone());
+        assert_eq!(reloaded.get("valid", "audio/mp3", &key_fingerprint("key-a")), Some(valid.clone()));
+        assert_eq!(reloaded.get("valid", "audio/wav", &key_fingerprint("key-a")), None);
+        assert_eq!(reloaded.get("valid", "audio/mp3", &key_fingerprint("key-b")), None);
+        assert_eq!(reloaded.get("expired", "audio/mp3", &key_fingerprint("key-a")), None);
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("expired"));
+
+        reloaded.remove("valid");
+        assert_eq!(UploadCache::load(path.clone()).get("valid", "audio/mp3", &key_fingerprint("key-a")), None);
+
+        reloaded.insert("copy".to_string(), valid.clone());
+        reloaded.remove_file(&valid.name);
+        assert_eq!(reloaded.get("copy", "audio/mp3", &key_fingerprint("key-a")), None);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sha256_file_matches_in_memory_hash() {
+        let data: Vec<u8> = (0..HASH_BUFFER_SIZE * 2 + 123).map(|i| (i % 251) as u8).collect();
+        let path = std::env::temp_dir().join(format!("hash_{}.wav", uuid::Uuid::new_v4()));
+        std::fs::write(&path, &data).unwrap();
+        assert_eq!(sha256_file(&path).await.unwrap(), sha256_hex(&data));
+        std::fs::remove_file(path).unwrap();
+    }
+}
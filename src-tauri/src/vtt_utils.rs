@@ -0,0 +1,260 @@
+use crate::srt_utils::{parse_srt, serialize_srt, split_speaker, SubtitleCue};
+
+/// Formats milliseconds as a WebVTT timestamp (`hh:mm:ss.mmm`)
+pub fn format_vtt_timestamp(ms: u64) -> String {
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        (ms % 3_600_000) / 60_000,
+        (ms % 60_000) / 1000,
+        ms % 1000
+    )
+}
+
+/// Escapes characters that are not allowed verbatim in WebVTT cue text,
+/// keeping the basic `<i>`, `<b>` and `<u>` tags that SRT and WebVTT share
+fn escape_vtt_text(text: &str) -> String {
+    const ALLOWED_TAGS: [&str; 6] = ["<i>", "</i>", "<b>", "</b>", "<u>", "</u>"];
+
+    let mut escaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if let Some(tag) = ALLOWED_TAGS.iter().find(|tag| rest.starts_with(**tag)) {
+            escaped.push_str(tag);
+            rest = &rest[tag.len()..];
+            continue;
+        }
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            _ => escaped.push(c),
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    escaped
+}
+
+fn cue_to_vtt_text(cue: &SubtitleCue, speaker_voice_tags: bool) -> String {
+    cue.lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| match split_speaker(line) {
+            Some((speaker, text)) if speaker_voice_tags && i == 0 => {
+                format!("<v {}>{}", escape_vtt_text(speaker), escape_vtt_text(text))
+            }
+            _ => escape_vtt_text(line),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Converts SRT content into WebVTT.
+///
+/// Sequence numbers are kept as cue identifiers. When `speaker_voice_tags`
+/// is set, a leading `話者: ` label is turned into a `<v 話者>` voice tag.
+pub fn srt_to_vtt(content: &str, speaker_voice_tags: bool) -> Result<String, String> {
+    let cues = parse_srt(content)?;
+
+    let mut vtt = String::from("WEBVTT\n");
+    for cue in &cues {
+        vtt.push_str(&format!(
+            "\n{}\n{} --> {}\n{}\n",
+            cue.index,
+            format_vtt_timestamp(cue.start_ms),
+            format_vtt_timestamp(cue.end_ms),
+            cue_to_vtt_text(cue, speaker_voice_tags)
+        ));
+    }
+
+    Ok(vtt)
+}
+
+/// Parses a WebVTT timestamp (`hh:mm:ss.mmm` or `mm:ss.mmm`) into milliseconds
+pub fn parse_vtt_timestamp(timestamp: &str) -> Option<u64> {
+    let (hms, millis) = timestamp.trim().split_once('.')?;
+    let parts: Vec<u64> = hms
+        .split(':')
+        .map(|part| part.parse().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let (hours, minutes, seconds) = match parts.as_slice() {
+        [hours, minutes, seconds] => (*hours, *minutes, *seconds),
+        [minutes, seconds] => (0, *minutes, *seconds),
+        _ => return None,
+    };
+    if minutes >= 60 || seconds >= 60 || millis.len() != 3 {
+        return None;
+    }
+    let millis: u64 = millis.parse().ok()?;
+    Some(((hours * 60 + minutes) * 60 + seconds) * 1000 + millis)
+}
+
+/// Converts WebVTT cue text into SRT text: voice tags become `話者: `
+/// prefixes, `<i>`/`<b>`/`<u>` are kept, other tags are dropped and
+/// character references are decoded
+fn vtt_text_to_srt(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(tag_start) = rest.find('<') {
+        text.push_str(&rest[..tag_start]);
+        let Some(tag_len) = rest[tag_start..].find('>') else {
+            text.push_str(&rest[tag_start..]);
+            rest = "";
+            break;
+        };
+        let tag = &rest[tag_start + 1..tag_start + tag_len];
+        // Voice tags look like `<v Name>` or `<v.class Name>`
+        if let Some(voice) = tag.strip_prefix('v').filter(|voice| voice.starts_with([' ', '.'])) {
+            let speaker = voice.split_once(' ').map_or("", |(_, name)| name).trim();
+            if !speaker.is_empty() {
+                text.push_str(&format!("{}: ", speaker));
+            }
+        } else if matches!(tag, "i" | "/i" | "b" | "/b" | "u" | "/u") {
+            text.push_str(&format!("<{}>", tag));
+        }
+        rest = &rest[tag_start + tag_len + 1..];
+    }
+    text.push_str(rest);
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+/// Parses WebVTT content into cues.
+///
+/// The header, NOTE, STYLE and REGION blocks are skipped, cue settings after
+/// the end timestamp are ignored and voice tags are turned into speaker labels.
+/// Errors name the offending (1-based) line.
+pub fn parse_vtt(content: &str) -> Result<Vec<SubtitleCue>, String> {
+    let lines: Vec<&str> = content
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .collect();
+
+    match lines.first() {
+        Some(first) if first.starts_with("WEBVTT") => {}
+        _ => return Err("Invalid WebVTT file: missing WEBVTT header on line 1".to_string()),
+    }
+
+    // Skip the header block
+    let mut i = 0;
+    while i < lines.len() && !lines[i].trim().is_empty() {
+        i += 1;
+    }
+
+    let mut cues = Vec::new();
+    while i < lines.len() {
+        if lines[i].trim().is_empty() {
+            i += 1;
+            continue;
+        }
+
+        let block_start = lines[i].trim();
+        if block_start.starts_with("NOTE") || block_start == "STYLE" || block_start == "REGION" {
+            while i < lines.len() && !lines[i].trim().is_empty() {
+                i += 1;
+            }
+            continue;
+        }
+
+        // Optional cue identifier
+        if !lines[i].contains("-->") {
+            i += 1;
+        }
+
+        let timing_line = lines
+            .get(i)
+            .filter(|line| !line.trim().is_empty())
+            .ok_or_else(|| format!("Invalid WebVTT file: missing timestamp line after line {}", i))?;
+        let parse_timing = || {
+            let (start, rest) = timing_line.split_once("-->")?;
+            let end = rest.split_whitespace().next()?;
+            Some((parse_vtt_timestamp(start)?, parse_vtt_timestamp(end)?))
+        };
+        let (start_ms, end_ms) = parse_timing()
+            .ok_or_else(|| format!("Invalid WebVTT timestamp at line {}: {}", i + 1, timing_line))?;
+        i += 1;
+
+        let mut text_lines = Vec::new();
+        while i < lines.len() && !lines[i].trim().is_empty() {
+            text_lines.push(vtt_text_to_srt(lines[i].trim()));
+            i += 1;
+        }
+
+        cues.push(SubtitleCue {
+            index: cues.len() as u32 + 1,
+            start_ms,
+            end_ms,
+            lines: text_lines,
+        });
+    }
+
+    Ok(cues)
+}
+
+/// Converts WebVTT content into SRT
+pub fn vtt_to_srt(content: &str) -> Result<String, String> {
+    Ok(serialize_srt(&parse_vtt(content)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_srt_to_vtt_basic() {
+        let input = "1\n00:00:05,520 --> 00:00:08,910\nこれは1番目の字幕の\nテキストです。\n\n2\n00:00:09,150 --> 00:00:11,300\nそして、これが2番目の字幕です。";
+        let expected = "WEBVTT\n\n1\n00:00:05.520 --> 00:00:08.910\nこれは1番目の字幕の\nテキストです。\n\n2\n00:00:09.150 --> 00:00:11.300\nそして、これが2番目の字幕です。\n";
+        assert_eq!(srt_to_vtt(input, false).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_srt_to_vtt_speaker_voice_tags() {
+        let input = "1\n00:00:00,000 --> 00:00:02,000\nアオイ: こんにちは";
+        let with_tags = srt_to_vtt(input, true).unwrap();
+        assert!(with_tags.contains("<v アオイ>こんにちは"));
+
+        let without_tags = srt_to_vtt(input, false).unwrap();
+        assert!(without_tags.contains("アオイ: こんにちは"));
+    }
+
+    #[test]
+    fn test_srt_to_vtt_escapes_markup() {
+        let input = "1\n00:00:00,000 --> 00:00:02,000\n<i>A & B</i> -> C";
+        let vtt = srt_to_vtt(input, false).unwrap();
+        assert!(vtt.contains("<i>A &amp; B</i> -&gt; C"));
+    }
+
+    #[test]
+    fn test_srt_to_vtt_invalid_input() {
+        assert!(srt_to_vtt("1\nnot a timestamp\nHello", false).is_err());
+    }
+
+    #[test]
+    fn test_vtt_to_srt() {
+        let input = "WEBVTT - exported\nKind: captions\n\nNOTE This is a comment\nspanning two lines\n\nSTYLE\n::cue { color: white }\n\nintro\n00:05.520 --> 00:08.910 align:start position:10%\n<v アオイ>こんにちは &amp; <i>ようこそ</i></v>\n\n01:00:09.150 --> 01:00:11.300\n<c.yellow>二番目</c>\n";
+        let expected = "1\n00:00:05,520 --> 00:00:08,910\nアオイ: こんにちは & <i>ようこそ</i>\n\n2\n01:00:09,150 --> 01:00:11,300\n二番目\n";
+        assert_eq!(vtt_to_srt(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_vtt_round_trip() {
+        let srt = "1\n00:00:00,000 --> 00:00:02,000\nアオイ: A & B\n";
+        assert_eq!(vtt_to_srt(&srt_to_vtt(srt, true).unwrap()).unwrap(), srt);
+    }
+
+    #[test]
+    fn test_parse_vtt_errors_name_the_line() {
+        assert_eq!(
+            parse_vtt("1\n00:00:00,000 --> 00:00:01,000\nHello").unwrap_err(),
+            "Invalid WebVTT file: missing WEBVTT header on line 1"
+        );
+        assert_eq!(
+            parse_vtt("WEBVTT\n\n00:00.000 --> 00:0x.000\nHello").unwrap_err(),
+            "Invalid WebVTT timestamp at line 3: 00:00.000 --> 00:0x.000"
+        );
+    }
+}
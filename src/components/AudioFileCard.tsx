@@ -2,6 +2,8 @@ import { useState } from 'react'
 import { invoke } from '@tauri-apps/api/core'
 import { GEMINI_MODELS } from '../constants/config'
 import { storageUtils } from '../utils/storage'
+import { TranscriptionResult } from '../types/srt'
+import { formatCommandError } from '../lib/utils'
 import './AudioFileCard.css'
 
 export type TranscriptionType = 'basic' | 'srt' | 'summary'
@@ -70,12 +72,15 @@ const AudioFileCard = ({ fileData, onUpdate, onDelete }: AudioFileCardProps) =>
       
       onUpdate(fileData.id, { progress: 'Gemini APIにアップロード中...' })
       
-      const result = await invoke<string>('transcribe_audio', {
+      const { srt: result } = await invoke<TranscriptionResult>('transcribe_audio', {
         filePath: tempFilePath,
-        maxCharsPerSubtitle: 20, // デフォルト値
-        enableSpeakerDetection: false, // デフォルト値  
-        durationMs: null,
-        model: fileData.selectedModel,
+        options: {
+          maxCharsPerSubtitle: 20, // デフォルト値
+          enableSpeakerDetection: false, // デフォルト値  
+          durationMs: null,
+          model: fileData.selectedModel,
+        },
+        jobId: fileData.id,
         apiKey
       })
 
@@ -87,7 +92,7 @@ const AudioFileCard = ({ fileData, onUpdate, onDelete }: AudioFileCardProps) =>
     } catch (error) {
       onUpdate(fileData.id, {
         status: 'error',
-        error: `エラーが発生しました: ${error}`,
+        error: `エラーが発生しました: ${formatCommandError(error)}`,
         progress: ''
       })
     }
@@ -1,5 +1,7 @@
 import { useState, useEffect } from 'react'
+import { invoke } from '@tauri-apps/api/core'
 import { storageUtils } from '../utils/storage'
+import { RetrySettings, DEFAULT_RETRY_SETTINGS } from '../types/srt'
 import './Settings.css'
 
 const Settings = () => {
@@ -9,11 +11,61 @@ const Settings = () => {
   const [showApiKey, setShowApiKey] = useState(false)
   const [hasExistingKey, setHasExistingKey] = useState(false)
   const [apiKeyPreview, setApiKeyPreview] = useState('')
+  const [baseUrl, setBaseUrl] = useState('')
+  const [baseUrlMessage, setBaseUrlMessage] = useState('')
+  const [retrySettings, setRetrySettings] = useState<RetrySettings>(DEFAULT_RETRY_SETTINGS)
+  const [retryMessage, setRetryMessage] = useState('')
 
   useEffect(() => {
     loadApiKey()
+    loadBaseUrl()
+    loadRetrySettings()
   }, [])
 
+  const loadRetrySettings = async () => {
+    try {
+      const settings = await invoke<RetrySettings>('get_retry_settings')
+      setRetrySettings(settings ?? DEFAULT_RETRY_SETTINGS)
+    } catch (error) {
+      console.error('Failed to load retry settings:', error)
+    }
+  }
+
+  const handleSaveRetrySettings = async (e: React.FormEvent) => {
+    e.preventDefault()
+    setRetryMessage('')
+
+    try {
+      const saved = await invoke<RetrySettings>('set_retry_settings', { settings: retrySettings })
+      setRetrySettings(saved)
+      setRetryMessage('リトライ設定が保存されました')
+    } catch (error) {
+      setRetryMessage(`保存に失敗しました: ${error}`)
+    }
+  }
+
+  const loadBaseUrl = async () => {
+    try {
+      const url = await invoke<string>('get_base_url')
+      setBaseUrl(url ?? '')
+    } catch (error) {
+      console.error('Failed to load base URL:', error)
+    }
+  }
+
+  const handleSaveBaseUrl = async (e: React.FormEvent) => {
+    e.preventDefault()
+    setBaseUrlMessage('')
+
+    try {
+      const saved = await invoke<string>('set_base_url', { baseUrl })
+      setBaseUrl(saved)
+      setBaseUrlMessage('エンドポイントが保存されました')
+    } catch (error) {
+      setBaseUrlMessage(`保存に失敗しました: ${error}`)
+    }
+  }
+
   const loadApiKey = () => {
     try {
       console.log('Loading API key preview...')
@@ -147,6 +199,79 @@ const Settings = () => {
           </button>
         </div>
       </form>
+
+      <form onSubmit={handleSaveBaseUrl} className="settings-form">
+        <div className="form-group">
+          <label htmlFor="base-url">APIエンドポイント</label>
+          <input
+            id="base-url"
+            type="url"
+            value={baseUrl}
+            onChange={(e) => setBaseUrl(e.target.value)}
+            placeholder="https://generativelanguage.googleapis.com"
+          />
+        </div>
+
+        {baseUrlMessage && (
+          <div className={`message ${baseUrlMessage.includes('失敗') ? 'error' : 'success'}`}>
+            {baseUrlMessage}
+          </div>
+        )}
+
+        <button type="submit">エンドポイントを保存</button>
+      </form>
+
+      <form onSubmit={handleSaveRetrySettings} className="settings-form">
+        <div className="form-group">
+          <label htmlFor="retry-max-attempts">最大試行回数</label>
+          <input
+            id="retry-max-attempts"
+            type="number"
+            min={1}
+            max={10}
+            value={retrySettings.max_attempts}
+            onChange={(e) => setRetrySettings({ ...retrySettings, max_attempts: Number(e.target.value) })}
+          />
+        </div>
+        <div className="form-group">
+          <label htmlFor="retry-base-delay">初回待機時間 (ms)</label>
+          <input
+            id="retry-base-delay"
+            type="number"
+            min={0}
+            value={retrySettings.base_delay_ms}
+            onChange={(e) => setRetrySettings({ ...retrySettings, base_delay_ms: Number(e.target.value) })}
+          />
+        </div>
+        <div className="form-group">
+          <label htmlFor="retry-max-delay">最大待機時間 (ms)</label>
+          <input
+            id="retry-max-delay"
+            type="number"
+            min={0}
+            value={retrySettings.max_delay_ms}
+            onChange={(e) => setRetrySettings({ ...retrySettings, max_delay_ms: Number(e.target.value) })}
+          />
+        </div>
+        <div className="form-group">
+          <label>
+            <input
+              type="checkbox"
+              checked={retrySettings.jitter}
+              onChange={(e) => setRetrySettings({ ...retrySettings, jitter: e.target.checked })}
+            />
+            待機時間をランダムに分散する
+          </label>
+        </div>
+
+        {retryMessage && (
+          <div className={`message ${retryMessage.includes('失敗') ? 'error' : 'success'}`}>
+            {retryMessage}
+          </div>
+        )}
+
+        <button type="submit">リトライ設定を保存</button>
+      </form>
     </div>
   )
 }
@@ -80,7 +80,7 @@ describe('SrtFileCard Audio Duration Functionality', () => {
         return Promise.resolve('/tmp/test.mp3')
       }
       if (command === 'transcribe_audio') {
-        return Promise.resolve('Mock SRT result')
+        return Promise.resolve({ srt: 'Mock SRT result', usage: null, warnings: [] })
       }
       return Promise.resolve('')
     })
@@ -175,16 +175,16 @@ describe('SrtFileCard Audio Duration Functionality', () => {
         return Promise.resolve('/tmp/test.mp3')
       }
       if (command === 'transcribe_audio') {
-        return Promise.resolve('Mock initial transcription')
+        return Promise.resolve({ srt: 'Mock initial transcription', usage: null, warnings: [] })
       }
       if (command === 'analyze_topic') {
-        return Promise.resolve('メイントピック: テスト\n専門分野: IT\nキーワード: テスト,開発')
+        return Promise.resolve({ topic: 'メイントピック: テスト\n専門分野: IT\nキーワード: テスト,開発', usage: null })
       }
       if (command === 'create_dictionary') {
-        return Promise.resolve('テスト,てすと\n開発,かいはつ')
+        return Promise.resolve({ csv: 'テスト,てすと\n開発,かいはつ', citations: [], usage: null })
       }
       if (command === 'enhance_transcription_with_dictionary') {
-        return Promise.resolve('Enhanced SRT result')
+        return Promise.resolve({ srt: 'Enhanced SRT result', usage: null })
       }
       return Promise.resolve('')
     })
@@ -213,7 +213,7 @@ describe('SrtFileCard Audio Duration Functionality', () => {
     await new Promise(resolve => setTimeout(resolve, 200))
     
     expect(mockInvoke).toHaveBeenCalledWith('enhance_transcription_with_dictionary', expect.objectContaining({
-      durationMs: 150500,
+      options: expect.objectContaining({ durationMs: 150500 }),
     }))
   })
 
@@ -25,9 +25,17 @@ import {
   SelectValue,
 } from '@/components/ui/select';
 
-import { AudioFile, SrtSettings } from '@/types/srt';
+import {
+  AudioFile,
+  Citation,
+  DictionaryResult,
+  EnhancementResult,
+  SrtSettings,
+  TopicResult,
+  TranscriptionResult,
+} from '@/types/srt';
 import { storageUtils } from '@/utils/storage';
-import { formatFileSize } from '@/lib/utils';
+import { formatCommandError, formatFileSize } from '@/lib/utils';
 import { parseSrt, validateSrt } from '@/lib/srt-utils';
 import { useToast } from '@/hooks/use-toast';
 import AudioSubtitlePreview from './AudioSubtitlePreview';
@@ -132,12 +140,16 @@ const SrtFileCard = ({ audioFile, onUpdate, onDelete }: SrtFileCardProps) => {
         progress: 'ステップ 4/5: AI音声解析・SRT字幕生成中... (1-3分)',
       });
 
-      const result = await invoke<string>('transcribe_audio', {
+      const { srt: result, usage, warnings } = await invoke<TranscriptionResult>('transcribe_audio', {
         filePath: tempFilePath,
-        maxCharsPerSubtitle: audioFile.settings.maxCharsPerSubtitle,
-        enableSpeakerDetection: audioFile.settings.enableSpeakerDetection,
-        durationMs: audioDurationMs,
-        model: 'gemini-2.5-pro',
+        options: {
+          maxCharsPerSubtitle: audioFile.settings.maxCharsPerSubtitle,
+          enableSpeakerDetection: audioFile.settings.enableSpeakerDetection,
+          durationMs: audioDurationMs,
+          model: 'gemini-2.5-pro',
+          verbatim: !audioFile.settings.removeFillerWords,
+        },
+        jobId: audioFile.id,
         apiKey,
       });
 
@@ -153,13 +165,15 @@ const SrtFileCard = ({ audioFile, onUpdate, onDelete }: SrtFileCardProps) => {
         status: 'completed',
         result,
         subtitles: validation.isValid ? subtitles : undefined,
+        usage: usage ?? undefined,
+        warnings,
         progress: undefined,
         srtValidation: validation,
       });
     } catch (error) {
       onUpdate(audioFile.id, {
         status: 'error',
-        error: `エラーが発生しました: ${error}`,
+        error: `エラーが発生しました: ${formatCommandError(error)}`,
         progress: undefined,
       });
     }
@@ -191,12 +205,16 @@ const SrtFileCard = ({ audioFile, onUpdate, onDelete }: SrtFileCardProps) => {
         progress: 'ステップ 3/7: 基本文字起こし中... (Gemini 2.5 Pro)',
       });
 
-      const initialResult = await invoke<string>('transcribe_audio', {
+      const { srt: initialResult, session_id: sessionId } = await invoke<TranscriptionResult>('transcribe_audio', {
         filePath: tempFilePath,
-        maxCharsPerSubtitle: audioFile.settings.maxCharsPerSubtitle,
-        enableSpeakerDetection: audioFile.settings.enableSpeakerDetection,
-        durationMs: audioDurationMs,
-        model: 'gemini-2.5-pro',
+        options: {
+          maxCharsPerSubtitle: audioFile.settings.maxCharsPerSubtitle,
+          enableSpeakerDetection: audioFile.settings.enableSpeakerDetection,
+          durationMs: audioDurationMs,
+          model: 'gemini-2.5-pro',
+          verbatim: !audioFile.settings.removeFillerWords,
+        },
+        jobId: audioFile.id,
         apiKey,
       });
 
@@ -205,8 +223,9 @@ const SrtFileCard = ({ audioFile, onUpdate, onDelete }: SrtFileCardProps) => {
         progress: 'ステップ 4/7: 会話トピック分析中... (Gemini 2.0 Flash)',
       });
 
-      const topicResult = await invoke<string>('analyze_topic', {
+      const { topic: topicResult } = await invoke<TopicResult>('analyze_topic', {
         transcription: initialResult,
+        sessionId,
         apiKey,
       });
 
@@ -227,6 +246,7 @@ const SrtFileCard = ({ audioFile, onUpdate, onDelete }: SrtFileCardProps) => {
       });
 
       let dictionary = '';
+      let dictionaryCitations: Citation[] = [];
       if (audioFile.settings.customDictionaryPath) {
         // カスタム辞書を読み込み
         dictionary = await invoke<string>('load_dictionary_csv', {
@@ -234,10 +254,13 @@ const SrtFileCard = ({ audioFile, onUpdate, onDelete }: SrtFileCardProps) => {
         });
       } else {
         // 自動生成
-        dictionary = await invoke<string>('create_dictionary', {
+        const created = await invoke<DictionaryResult>('create_dictionary', {
           topic: topicResult,
+          sessionId,
           apiKey,
         });
+        dictionary = created.csv;
+        dictionaryCitations = created.citations;
 
         // 生成した辞書をエクスポート
         const baseName = audioFile.file.name.replace(/\.[^/.]+$/, '');
@@ -250,6 +273,7 @@ const SrtFileCard = ({ audioFile, onUpdate, onDelete }: SrtFileCardProps) => {
 
       onUpdate(audioFile.id, {
         dictionary,
+        dictionaryCitations,
       });
 
       // ステップ4: 最終SRT生成
@@ -257,14 +281,18 @@ const SrtFileCard = ({ audioFile, onUpdate, onDelete }: SrtFileCardProps) => {
         progress: 'ステップ 6/7: 高精度SRT字幕生成中... (Gemini 2.5 Pro)',
       });
 
-      const finalResult = await invoke<string>(
+      const { srt: finalResult } = await invoke<EnhancementResult>(
         'enhance_transcription_with_dictionary',
         {
           initialTranscription: initialResult,
           dictionary: dictionary,
-          maxCharsPerSubtitle: audioFile.settings.maxCharsPerSubtitle,
-          enableSpeakerDetection: audioFile.settings.enableSpeakerDetection,
-          durationMs: audioDurationMs,
+          options: {
+            maxCharsPerSubtitle: audioFile.settings.maxCharsPerSubtitle,
+            enableSpeakerDetection: audioFile.settings.enableSpeakerDetection,
+            durationMs: audioDurationMs,
+            verbatim: !audioFile.settings.removeFillerWords,
+          },
+          sourceFilename: audioFile.file.name,
           apiKey,
         }
       );
@@ -285,7 +313,7 @@ const SrtFileCard = ({ audioFile, onUpdate, onDelete }: SrtFileCardProps) => {
     } catch (error) {
       onUpdate(audioFile.id, {
         status: 'error',
-        error: `高度処理でエラーが発生しました: ${error}`,
+        error: `高度処理でエラーが発生しました: ${formatCommandError(error)}`,
         progress: undefined,
       });
     }
@@ -763,6 +791,36 @@ const SrtFileCard = ({ audioFile, onUpdate, onDelete }: SrtFileCardProps) => {
                       ? 'SRT形式の解析に失敗しましたが、生のテキストを表示・ダウンロードできます'
                       : 'テキストが生成されました'}
                 </div>
+
+                {audioFile.warnings && audioFile.warnings.length > 0 && (
+                  <div className="text-sm text-yellow-700 bg-yellow-50 p-2 rounded space-y-1">
+                    {audioFile.warnings.map((warning) => (
+                      <div key={warning}>{warning}</div>
+                    ))}
+                  </div>
+                )}
+
+                {audioFile.dictionaryCitations && audioFile.dictionaryCitations.length > 0 && (
+                  <div className="text-xs text-muted-foreground space-y-1">
+                    <div>辞書の参照元:</div>
+                    {audioFile.dictionaryCitations.map((citation) => (
+                      <div key={citation.uri}>
+                        <a href={citation.uri} target="_blank" rel="noreferrer" className="underline">
+                          {citation.title}
+                        </a>
+                      </div>
+                    ))}
+                  </div>
+                )}
+
+                {audioFile.usage && (
+                  <div className="text-xs text-muted-foreground">
+                    トークン使用量: 入力 {audioFile.usage.prompt_tokens.toLocaleString()} / 出力{' '}
+                    {audioFile.usage.output_tokens.toLocaleString()}
+                    {audioFile.usage.estimated_cost_usd !== null &&
+                      ` (推定コスト: $${audioFile.usage.estimated_cost_usd.toFixed(4)})`}
+                  </div>
+                )}
               </div>
             )}
 
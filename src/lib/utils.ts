@@ -1,5 +1,6 @@
 import { type ClassValue, clsx } from "clsx"
 import { twMerge } from "tailwind-merge"
+import type { CommandError } from "@/types/srt"
 
 export function cn(...inputs: ClassValue[]) {
   return twMerge(clsx(inputs))
@@ -37,4 +38,12 @@ export function formatSrtTime(seconds: number): string {
   const ms = Math.floor((seconds % 1) * 1000)
   
   return `${hours.toString().padStart(2, '0')}:${minutes.toString().padStart(2, '0')}:${secs.toString().padStart(2, '0')},${ms.toString().padStart(3, '0')}`
-}
\ No newline at end of file
+}
+
+/** Commands calling Gemini reject with a CommandError, the others with a string */
+export function formatCommandError(error: unknown): string {
+  if (typeof error === 'object' && error !== null && 'message' in error) {
+    return (error as CommandError).message
+  }
+  return String(error)
+}
@@ -13,6 +13,305 @@ export interface SrtSettings {
   customDictionaryPath?: string
 }
 
+export interface TokenUsage {
+  model: string
+  prompt_tokens: number
+  output_tokens: number
+  total_tokens: number
+  estimated_cost_usd: number | null
+}
+
+/** Token usage of every generation since the app started, from `get_session_usage` */
+export interface SessionUsage {
+  generations: number
+  prompt_tokens: number
+  output_tokens: number
+  total_tokens: number
+  /** Sum over the models with a known price */
+  estimated_cost_usd: number
+  /** Totals per model, in order of first use */
+  models: TokenUsage[]
+}
+
+/** Model returned by `get_available_models` */
+export interface AvailableModel {
+  name: string
+  display_name: string
+  input_token_limit: number
+  supports_audio: boolean
+}
+
+/** File on the Gemini Files API, as returned by `list_remote_files` */
+export interface RemoteFile {
+  name: string
+  uri: string
+  mimeType: string
+  sizeBytes: string
+  createTime: string
+  updateTime: string
+  expirationTime: string
+  sha256Hash: string
+  state: string
+  source: string | null
+  /** Set when `state` is FAILED */
+  error?: { code: number; message: string }
+}
+
+/** Result of `estimate_transcription_cost` */
+export interface CostEstimate {
+  model: string
+  input_tokens: number
+  audio_tokens: number
+  expected_output_tokens: number
+  estimated_cost_usd: number | null
+}
+
+/** Result of `detect_language` */
+export interface LanguageResult {
+  /** ISO 639-1 code, usable as the `language` of `transcribe_audio` */
+  code: string
+  name: string
+  confidence: number
+}
+
+/** How Gemini requests are authenticated, saved with `set_auth_mode` */
+export type AuthMode =
+  | { type: 'api_key' }
+  | { type: 'service_account'; key_file: string; location: string; project_id?: string | null }
+
+/** Result of `validate_api_key`; `message` explains any failure */
+export interface ApiKeyValidation {
+  status: 'valid' | 'invalid' | 'network_error' | 'error'
+  message: string | null
+}
+
+/** Result of `set_api_key`; the key is saved even when validation fails */
+export interface ApiKeySaved {
+  /** Set when `validate` was requested */
+  validation: ApiKeyValidation | null
+}
+
+/** Result of `test_connection` */
+export interface ConnectionTest {
+  /** Models listed for the API key */
+  models: number
+  elapsed_ms: number
+}
+
+/** Error returned by commands that call the Gemini API */
+export interface CommandError {
+  code: string
+  message: string
+  retryable: boolean
+}
+
+/** How Gemini requests failing with 429 or 5xx are retried */
+export interface RetrySettings {
+  max_attempts: number
+  base_delay_ms: number
+  max_delay_ms: number
+  jitter: boolean
+}
+
+export const DEFAULT_RETRY_SETTINGS: RetrySettings = {
+  max_attempts: 4,
+  base_delay_ms: 1000,
+  max_delay_ms: 30000,
+  jitter: true
+}
+
+/** How many generations and uploads may run at once; requests beyond that wait for a slot */
+export interface ConcurrencyLimits {
+  max_generations: number
+  max_uploads: number
+}
+
+export const DEFAULT_CONCURRENCY_LIMITS: ConcurrencyLimits = {
+  max_generations: 2,
+  max_uploads: 1
+}
+
+/** Most verbose level written to the log file, set with `set_log_level` */
+export type LogLevel = 'error' | 'warn' | 'info' | 'debug' | 'trace'
+
+/** Payload of the `gemini-queued` event sent when a request waits for a slot */
+export type QueuedRequest = 'generation' | 'upload'
+
+/** Sampling parameters; unset fields keep the per-task defaults */
+export interface GenerationConfig {
+  temperature?: number
+  topP?: number
+  topK?: number
+  maxOutputTokens?: number
+  candidateCount?: number
+  stopSequences?: string[]
+}
+
+/** Overrides saved with `set_generation_settings` */
+export interface GenerationSettings {
+  transcription: GenerationConfig
+  dictionary: GenerationConfig
+}
+
+/** Blocking threshold for one harm category, sent with every generation */
+export interface SafetySetting {
+  category: string
+  threshold: 'OFF' | 'BLOCK_NONE' | 'BLOCK_ONLY_HIGH' | 'BLOCK_MEDIUM_AND_ABOVE' | 'BLOCK_LOW_AND_ABOVE'
+}
+
+/** Payload of the `transcription-progress` event */
+export interface TranscriptionProgress {
+  job_id: string
+  stage: 'queued' | 'uploading' | 'processing' | 'generating' | 'finalizing' | 'done'
+  percent: number | null
+  detail: string
+}
+
+/** Payload of the `file-processing-status` event, sent on every Files API poll */
+export interface FileProcessingStatus {
+  job_id: string
+  state: string
+  elapsed_secs: number
+}
+
+/** Payload of the `transcription-chunk` event sent when `stream` is enabled */
+export interface TranscriptionChunk {
+  job_id: string
+  text: string
+}
+
+/** Settings of `transcribe_audio` and the commands built on it; every field may be left out */
+export interface TranscriptionOptions {
+  /** 20 when not given */
+  maxCharsPerSubtitle?: number
+  enableSpeakerDetection?: boolean
+  /** Read from the file when not given */
+  durationMs?: number | null
+  model?: string
+  mimeOverride?: string
+  /** ISO 639-1 code; Japanese when not given */
+  language?: string
+  firstBlockOnly?: boolean
+  repeatCollapseThreshold?: number
+  wrapLines?: boolean
+  /** Keep fillers, false starts and repetitions */
+  verbatim?: boolean
+  removeFillers?: boolean
+  /** Replaces the list saved with `set_filler_words` */
+  fillerWords?: string[]
+  keepMarkup?: boolean
+  durationLimits?: { min_ms: number; max_ms: number }
+  /** Emit `transcription-chunk` events while generating */
+  stream?: boolean
+  deleteUpload?: boolean
+  generationConfig?: GenerationConfig
+}
+
+export interface TranscriptionResult {
+  srt: string
+  usage: TokenUsage | null
+  warnings: string[]
+  /** Follow-up requests made because the output token limit was hit */
+  continuations: number
+  /** Pass to `analyze_topic`, `create_dictionary` and `enhance_from_session` */
+  session_id: string | null
+}
+
+/** Intermediate pipeline results returned by `get_session` */
+export interface TranscriptionSession {
+  id: string
+  created_at: number
+  source_filename: string | null
+  model: string
+  max_chars_per_subtitle: number
+  enable_speaker_detection: boolean
+  duration_ms: number | null
+  transcription: string
+  topic: string | null
+  dictionary: string | null
+}
+
+/** Payload of the `batch-progress` event; `index` is zero-based */
+export interface BatchProgress {
+  index: number
+  total: number
+  filename: string
+  status: 'processing' | 'completed' | 'failed'
+}
+
+/** Outcome of one file of `transcribe_batch`: either `srt` or `error` is set */
+export interface BatchResult {
+  filename: string
+  srt: string | null
+  error: string | null
+}
+
+/** Payload of the `pipeline-stage` event sent by `run_full_pipeline` */
+export interface PipelineStageEvent {
+  stage: 'transcription' | 'topic_analysis' | 'dictionary' | 'enhancement'
+  status: 'started' | 'completed' | 'skipped' | 'failed'
+  detail: string
+}
+
+/** Result of `run_full_pipeline`; `srt` is the initial transcription when enhancement was skipped */
+export interface PipelineResult {
+  srt: string
+  topic: string | null
+  dictionary: string | null
+  citations: Citation[]
+  session_id: string | null
+  /** One entry per step that called the API */
+  usage: TokenUsage[]
+}
+
+/** One word returned by `transcribe_word_level` */
+export interface WordTiming {
+  word: string
+  start_ms: number
+  end_ms: number
+}
+
+/** A run of characters that is unchanged, only in the new text or only in the old text */
+export interface DiffSegment {
+  kind: 'equal' | 'added' | 'removed'
+  text: string
+}
+
+/** Result of `diff_transcription_cues` for the cues with one sequence number */
+export interface DiffHunk {
+  index: number
+  /** `null` when the cue only exists on the other side */
+  before_text: string | null
+  after_text: string | null
+  changed: boolean
+  segments: DiffSegment[]
+}
+
+/** A web page consulted while generating the dictionary */
+export interface Citation {
+  title: string
+  uri: string
+}
+
+export interface DictionaryResult {
+  csv: string
+  citations: Citation[]
+  /** Both requests: the grounded answer and its structuring */
+  usage: TokenUsage | null
+}
+
+/** Result of `analyze_topic`: the `メイントピック:` / `キーワード:` lines */
+export interface TopicResult {
+  topic: string
+  usage: TokenUsage | null
+}
+
+/** Result of `enhance_transcription_with_dictionary` and `enhance_from_session` */
+export interface EnhancementResult {
+  srt: string
+  usage: TokenUsage | null
+}
+
 export interface SrtValidation {
   isValid: boolean
   errors: string[]
@@ -29,8 +328,11 @@ export interface AudioFile {
   error?: string
   progress?: string
   srtValidation?: SrtValidation
+  usage?: TokenUsage
+  warnings?: string[]
   // 高精度モード用
   dictionary?: string
+  dictionaryCitations?: Citation[]
   analyzedTopic?: string
 }
 